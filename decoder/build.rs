@@ -10,7 +10,16 @@
 //!
 //! The build script also sets the linker flags to tell it which link script to use.
 //!
-//! It also creates the flash memory data for subscriptions, secrets, and decoder ID.
+//! It also creates the secrets image holding the decoder ID, the key subscription
+//! signatures are verified with and the channel secrets, each placed in the slot a
+//! perfect hash of its channel ID picks, so the decoder reads one record per lookup. The image is
+//! not compiled into the firmware: it is written as `secrets.bin` in `OUT_DIR`
+//! and placed into the `.secrets` section after linking. The free channels are still
+//! checked against the secrets here, so a different decoder ID or secrets file needs a rebuild.
+//!
+//! Lastly, it turns the `DELAY_*` environment variables into the random delay policy
+//! used for each command type, `FREE_CHANNELS` into the channels decoded without
+//! a subscription, and `REPLAY_POLICY` into the replay protection for frames.
 
 use base64::prelude::*;
 use std::env;
@@ -18,38 +27,43 @@ use std::convert::TryInto;
 use std::fs;
 use std::fs::File;
 use std::io::Write;
+use std::fmt;
 use std::path::PathBuf;
 use std::path::Path;
-use serde_json;
+use serde::de::{Deserialize, Deserializer, MapAccess, Visitor};
 use serde_json::Value;
 
-struct RawSecret {
-    id: String,
-    aes_key: Vec<u8>,
-    iv: Vec<u8>,
-}
+use decoder_types::{AesBlock, AesKey, Secret, SecretType, SecretsHeader, SECRETS_CAPACITY, SECRETS_IMAGE_SIZE};
+use decoder_types::index::index_secrets;
 
-// Must be copied from src
-// vvv
-type AesSubBlock = u8;
-type AesBlock = [AesSubBlock; 16];
-type AesKey = [u8; 32];
+const DEFAULT_SECRETS_PATH: &str = "/global.secrets";
 
-#[derive(Debug, Clone, Copy, PartialEq)]
-pub enum SecretType {
-    Channel(u32),
-    Master
-}
+// Keeps every entry in file order, as a serde_json map silently drops duplicate keys
+struct SecretsFile(Vec<(String, Value)>);
+
+impl<'de> Deserialize<'de> for SecretsFile {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct EntriesVisitor;
+
+        impl<'de> Visitor<'de> for EntriesVisitor {
+            type Value = SecretsFile;
 
-#[derive(Debug, Clone, Copy)]
-#[repr(C, align(4))]
-pub struct Secret {
-    pub secret_type: SecretType,
-    pub valid: bool,
-    pub aes_key: AesKey,
-    pub aes_iv: AesBlock
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("an object mapping \"master\" and channel IDs to [key, iv], and \"verifying\" to a public key")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<SecretsFile, A::Error> {
+                let mut entries = Vec::new();
+                while let Some(entry) = map.next_entry::<String, Value>()? {
+                    entries.push(entry);
+                }
+                Ok(SecretsFile(entries))
+            }
+        }
+
+        deserializer.deserialize_map(EntriesVisitor)
+    }
 }
-// ^^^
 
 fn main() {
     // Put `memory.x` in our output directory and ensure it's
@@ -80,46 +94,185 @@ fn main() {
     //==================================//
     // Add secrets during build process //
     //==================================//
-    // Read the secrets JSON file
-    let decoder_id: u32 = u32::from_str_radix(&env::var("DECODER_ID").unwrap()[2..], 16).unwrap();
-
-    let secrets_path: &Path = Path::new("/global.secrets");
-    let secrets_str = fs::read_to_string(secrets_path).expect("Failed to read secrets.json");
+    // Both can be set in the environment or the `[env]` table of a cargo config
+    let decoder_id = decoder_id_from_env();
+    let secrets_path = secrets_path_from_env();
+
+    let (secrets_vec, verifying_key) = match load_secrets(&secrets_path) {
+        Ok(loaded) => { loaded },
+        Err(diagnostics) => {
+            panic!("Invalid secrets file {}:\n  - {}", secrets_path.display(), diagnostics.join("\n  - "))
+        },
+    };
 
-    // Parse secrets JSON
-    let secrets: Value = serde_json::from_str(&secrets_str).expect("Invalid JSON format");
-    let secrets_map = match secrets {
-        Value::Object(map) => { map },
-        _ => { panic!("Invalid JSON format") },
+    // load_secrets already rejected duplicates and overflow, so only an unlucky hash can fail
+    let (index, secrets_arr) = match index_secrets(&secrets_vec) {
+        Some(indexed) => { indexed },
+        None => {
+            panic!("Invalid secrets file {}:\n  - no index seed places all {} secrets", secrets_path.display(), secrets_vec.len())
+        },
     };
 
-    let mut raw_secrets_vec = Vec::<RawSecret>::new();
-    for (id, value) in secrets_map.into_iter() {
-        let value_vec = match value {
-            Value::Array(vec) => { vec },
-            _ => { panic!("Invalid JSON format") },
-        };
+    // Serialise through decoder_types so the firmware reads back the exact same layout
+    let mut secrets_image = Vec::<u8>::with_capacity(SECRETS_IMAGE_SIZE);
+    secrets_image.extend_from_slice(&SecretsHeader { decoder_id, verifying_key, index }.to_bytes());
+    for secret in secrets_arr {
+        secrets_image.extend_from_slice(&secret.to_bytes());
+    }
+    assert_eq!(secrets_image.len(), SECRETS_IMAGE_SIZE);
+
+    //=======================================//
+    // Add delay policy during build process //
+    //=======================================//
+    // Each variable is `none`, `uniform:<min_us>:<max_us>` or `exponential:<mean_us>:<max_us>`,
+    // optionally followed by `,auth-failure` to only delay after an authentication failure
+    let delay_code = format!(
+r#"// Delay policy
+// Generated by build script
+
+pub const LIST_DELAY: DelayConfig = {};
+pub const UPDATE_DELAY: DelayConfig = {};
+pub const DECODE_DELAY: DelayConfig = {};
+pub const RECEIVE_DELAY: DelayConfig = {};
+"#,
+        delay_config_code("DELAY_LIST", "none"),
+        delay_config_code("DELAY_UPDATE", "uniform:100:500"),
+        delay_config_code("DELAY_DECODE", "uniform:100:500"),
+        delay_config_code("DELAY_RECEIVE", "uniform:100:500"),
+    );
+    fs::write(out.join("delay_policy.rs"), delay_code).unwrap();
+
+    //========================================//
+    // Add free channels during build process //
+    //========================================//
+    // A comma separated list of `<channel>` or `<channel>:<priority>`, priority being
+    // `normal` or `emergency`, and `normal` when left out
+    let free_channels_code = format!(
+r#"// Free channels
+// Generated by build script
+
+pub const FREE_CHANNELS: &[FreeChannel] = &[{}];
+"#,
+        free_channels_code("FREE_CHANNELS", "0:emergency", &secrets_vec),
+    );
+    fs::write(out.join("free_channels.rs"), free_channels_code).unwrap();
+
+    //========================================//
+    // Add replay policy during build process //
+    //========================================//
+    // `strict`, or `window:<behind>:<ahead>` to accept frames up to `behind` below the
+    // newest once each, and at most `ahead` above it, `ahead` being `unbounded` for no limit
+    let replay_code = format!(
+r#"// Replay policy
+// Generated by build script
+
+pub const REPLAY_POLICY: ReplayPolicy = {};
+"#,
+        replay_policy_code("REPLAY_POLICY", "strict"),
+    );
+    fs::write(out.join("replay_policy.rs"), replay_code).unwrap();
 
-        let aes_key = match value_vec.get(0).unwrap() {
-            Value::String(s) => { s.to_string() },
-            _ => { panic!("Invalid JSON format") },
-        };
+    // Cargo reports OUT_DIR in its build-script-executed message, where the Dockerfile finds the image
+    fs::write(out.join("secrets.bin"), &secrets_image).unwrap();
+    println!("cargo:rerun-if-changed=build.rs");    
+}
 
-        let iv = match value_vec.get(1).unwrap() {
-            Value::String(s) => { s.to_string() },
-            _ => { panic!("Invalid JSON format") },
-        };
+// Accepts decimal or 0x-prefixed hexadecimal
+fn decoder_id_from_env() -> u32 {
+    println!("cargo:rerun-if-env-changed=DECODER_ID");
+    let value = match env::var("DECODER_ID") {
+        Ok(value) => { value },
+        Err(_) => { panic!("DECODER_ID is not set, pass e.g. DECODER_ID=0xdeadbeef or DECODER_ID=3735928559") },
+    };
+    let value = value.trim();
+    let decoder_id = match value.strip_prefix("0x").or(value.strip_prefix("0X")) {
+        Some(hex) => { u32::from_str_radix(hex, 16) },
+        None => { value.parse::<u32>() },
+    };
+    match decoder_id {
+        Ok(decoder_id) => { decoder_id },
+        Err(error) => { panic!("DECODER_ID: {:?} is not a 32-bit decimal or 0x-prefixed hex number ({})", value, error) },
+    }
+}
 
-        let secret_arr = RawSecret {
-            id: id, 
-            aes_key: BASE64_STANDARD.decode(&aes_key).unwrap(),
-            iv: BASE64_STANDARD.decode(&iv).unwrap(),
-        };
+// Relative paths are relative to this crate's directory
+fn secrets_path_from_env() -> PathBuf {
+    println!("cargo:rerun-if-env-changed=SECRETS_PATH");
+    let secrets_path = PathBuf::from(env::var("SECRETS_PATH").unwrap_or(String::from(DEFAULT_SECRETS_PATH)));
+    println!("cargo:rerun-if-changed={}", secrets_path.display());
+    secrets_path
+}
 
-        assert_eq!(secret_arr.aes_key.len(), 32);
-        assert_eq!(secret_arr.iv.len(), 16);
+// Collects every problem with the file instead of stopping at the first
+// A signing seed is refused outright, it belongs in the provisioning key only
+fn load_secrets(secrets_path: &Path) -> Result<(Vec<Secret>, [u8; 32]), Vec<String>> {
+    let secrets_str = match fs::read_to_string(secrets_path) {
+        Ok(secrets_str) => { secrets_str },
+        Err(error) => {
+            return Err(vec![format!("cannot be read ({}), the path is set with SECRETS_PATH and defaults to {}", error, DEFAULT_SECRETS_PATH)]);
+        },
+    };
+    let secrets_file: SecretsFile = match serde_json::from_str(&secrets_str) {
+        Ok(secrets_file) => { secrets_file },
+        Err(error) => { return Err(vec![format!("is not a JSON object of secrets: {}", error)]); },
+    };
 
-        raw_secrets_vec.push(secret_arr);
+    let mut diagnostics = Vec::<String>::new();
+    let mut secret_types = Vec::<SecretType>::new();
+    let mut secrets_vec = Vec::<Secret>::new();
+    let mut found_verifying = false;
+    let mut verifying_key = [0u8; 32];
+    for (id, value) in secrets_file.0 {
+        if id == "signing" {
+            diagnostics.push(format!("entry {:?}: holds the signing seed, which must stay in the provisioning key, regenerate the file with ectf25_design.gen_secrets", id));
+            continue;
+        }
+        if id == "verifying" {
+            if found_verifying {
+                diagnostics.push(format!("entry {:?}: duplicate of an earlier entry for the verifying key", id));
+                continue;
+            }
+            found_verifying = true;
+            match decode_base64_field(&value, "public key", 32) {
+                Ok(public_key) => { verifying_key = public_key.try_into().unwrap(); },
+                Err(diagnostic) => { diagnostics.push(format!("entry {:?}: {}", id, diagnostic)); },
+            }
+            continue;
+        }
+        let secret_type = match parse_secret_type(&id) {
+            Ok(secret_type) => { secret_type },
+            Err(diagnostic) => {
+                diagnostics.push(format!("entry {:?}: {}", id, diagnostic));
+                continue;
+            },
+        };
+        if secret_types.contains(&secret_type) {
+            diagnostics.push(format!("entry {:?}: duplicate of an earlier entry for the same {}", id, describe_secret_type(secret_type)));
+            continue;
+        }
+        secret_types.push(secret_type);
+
+        match parse_secret_value(value) {
+            Ok((aes_key, aes_iv)) => {
+                secrets_vec.push(Secret {
+                    secret_type,
+                    valid: true,
+                    aes_key,
+                    aes_iv,
+                });
+            },
+            Err(diagnostic) => { diagnostics.push(format!("entry {:?}: {}", id, diagnostic)); },
+        }
+    }
+
+    if !secret_types.contains(&SecretType::Master) {
+        diagnostics.push(String::from("missing the \"master\" secret"));
+    }
+    if !secret_types.contains(&SecretType::Channel(0)) {
+        diagnostics.push(String::from("missing channel 0, the emergency channel every decoder must decode"));
+    }
+    if !found_verifying {
+        diagnostics.push(String::from("missing the \"verifying\" key, regenerate the file with ectf25_design.gen_secrets"));
     }
 
     // Prevent overfilling flash on build
@@ -128,110 +281,139 @@ fn main() {
     // to a system of dynamically communicating channel secrets.
     // We figured if you are going to try more than 128 channels for this competition,
     // oh well.
-    assert!(raw_secrets_vec.len() <= 128);
-
-    // Transform RawSecrets from JSON into Secrets for flash
-    let mut secrets_vec = Vec::<Secret>::new();
-    for raw_secret in raw_secrets_vec {
-        let secret_type = match raw_secret.id.as_str() {
-            "master" => { SecretType::Master }
-            _ => { SecretType::Channel(raw_secret.id.parse::<u32>().unwrap()) }
-        };
+    if secret_types.len() > SECRETS_CAPACITY {
+        diagnostics.push(format!("has {} secrets but the decoder only holds {}", secret_types.len(), SECRETS_CAPACITY));
+    }
 
-        let secret = Secret {
-            secret_type: secret_type,
-            valid: true,
-            aes_key: raw_secret.aes_key.try_into().unwrap(),
-            aes_iv: raw_secret.iv.try_into().unwrap(),
-        };
+    if diagnostics.is_empty() { Ok((secrets_vec, verifying_key)) } else { Err(diagnostics) }
+}
 
-        secrets_vec.push(secret);
+fn parse_secret_type(id: &str) -> Result<SecretType, String> {
+    if id == "master" { return Ok(SecretType::Master); }
+    if id.is_empty() || !id.bytes().all(|byte| byte.is_ascii_digit()) {
+        return Err(String::from("key must be \"master\", \"verifying\" or a decimal channel ID"));
     }
-
-    let mut secrets_arr: [Secret; 128] = [
-        Secret {
-            secret_type: SecretType::Channel(u32::MAX),
-            valid: false,
-            aes_key: [0xFFu8; 32],
-            aes_iv: [0xFFu8; 16],
-        }; 128];
-
-    let mut code_secrets_vec = Vec::<String>::new();
-    for (sidx, secret) in secrets_vec.into_iter().enumerate() {
-        secrets_arr[sidx] = secret;
+    match id.parse::<u32>() {
+        Ok(channel_id) => { Ok(SecretType::Channel(channel_id)) },
+        Err(_) => { Err(String::from("channel ID does not fit in 32 bits")) },
     }
+}
 
-    for  secret in secrets_arr {
-        let code_secret_type = match secret.secret_type {
-            SecretType::Master          => { format!("SecretType::Master") },
-            SecretType::Channel(c) => { format!("SecretType::Channel(0x{:08x}u32)", c) }, 
-        };
-
-        let code_valid = match secret.valid {
-            true  => { format!("true") },
-            false => { format!("false") },
-        };
-
-        let mut code_aes_key: String = String::from("["); 
-        for aes_key_byte in secret.aes_key {
-            let code_aes_key_byte = format!("0x{:02x}u8, ", aes_key_byte);
-            code_aes_key.push_str(&code_aes_key_byte);
-        }
-        code_aes_key.push(']');
-
-        let mut code_aes_iv: String = String::from("["); 
-        for aes_iv_byte in secret.aes_iv {
-            let code_aes_iv_byte = format!("0x{:02x}u8, ", aes_iv_byte);
-            code_aes_iv.push_str(&code_aes_iv_byte);
-        }
-        code_aes_iv.push(']');
-
-        let code_secret = format!(
-            "Secret {{ secret_type: {}, valid: {}, aes_key: {}, aes_iv: {}, }},",
-            code_secret_type, code_valid, code_aes_key, code_aes_iv,
-        );
-
-        code_secrets_vec.push(code_secret);
+fn parse_secret_value(value: Value) -> Result<(AesKey, AesBlock), String> {
+    let fields = match value {
+        Value::Array(fields) => { fields },
+        other => { return Err(format!("expected [key, iv] but found {}", other)); },
+    };
+    if fields.len() != 2 {
+        return Err(format!("expected [key, iv] but found {} elements", fields.len()));
     }
+    let aes_key = decode_base64_field(&fields[0], "key", 32)?;
+    let aes_iv = decode_base64_field(&fields[1], "iv", 16)?;
+    Ok((aes_key.try_into().unwrap(), aes_iv.try_into().unwrap()))
+}
 
-    let code_secrets_arr = code_secrets_vec.join("\n");
-
-    let code_header = r#"//! Flash Secrets
-//! Generated by build script
+fn decode_base64_field(field: &Value, name: &str, length: usize) -> Result<Vec<u8>, String> {
+    let encoded = match field {
+        Value::String(encoded) => { encoded },
+        other => { return Err(format!("{} must be a base64 string but found {}", name, other)); },
+    };
+    let decoded = match BASE64_STANDARD.decode(encoded) {
+        Ok(decoded) => { decoded },
+        Err(error) => { return Err(format!("{} is not valid base64 ({})", name, error)); },
+    };
+    if decoded.len() != length {
+        return Err(format!("{} must decode to {} bytes but decodes to {}", name, length, decoded.len()));
+    }
+    Ok(decoded)
+}
 
-use super::secure_memory::{Secret, SecretType, Subscription};"#;
+fn describe_secret_type(secret_type: SecretType) -> String {
+    match secret_type {
+        SecretType::Master => { String::from("master secret") },
+        SecretType::Channel(channel_id) => { format!("channel {}", channel_id) },
+    }
+}
 
-    let code_subscriptions = r#"#[link_section = ".subscriptions"]
-pub static SUBSCRIPTIONS: [Subscription; 8] = [
-    Subscription{
-        channel_id: 0,
-        valid: false,
-        end: 0,
-        start: 0
-    }; 8
-];"#;
+fn delay_config_code(var: &str, default: &str) -> String {
+    println!("cargo:rerun-if-env-changed={}", var);
+    let value = env::var(var).unwrap_or(String::from(default));
 
-    let code_decoder_id: String = format!(r#"pub static DECODER_ID: u32 = 0x{:08x}u32;"#, decoder_id);
+    let (policy, trigger) = match value.split_once(',') {
+        Some((policy, "auth-failure")) => { (policy, "DelayTrigger::AuthenticationFailure") },
+        Some((_, other)) => { panic!("{}: unknown delay trigger {:?}", var, other) },
+        None => { (value.as_str(), "DelayTrigger::Always") },
+    };
 
-    let final_code = format!(
-r#"{}
+    let fields: Vec<&str> = policy.split(':').collect();
+    let parse_us = |field: &str| -> u32 {
+        field.parse::<u32>().unwrap_or_else(|_| panic!("{}: invalid microsecond count {:?}", var, field))
+    };
+    let code_policy = match fields.as_slice() {
+        ["none"] => { String::from("DelayPolicy::None") },
+        ["uniform", min_us, max_us] => {
+            let (min_us, max_us) = (parse_us(min_us), parse_us(max_us));
+            assert!(min_us <= max_us, "{}: minimum delay is greater than maximum delay", var);
+            format!("DelayPolicy::Uniform {{ min_us: {}, max_us: {} }}", min_us, max_us)
+        },
+        ["exponential", mean_us, max_us] => {
+            let (mean_us, max_us) = (parse_us(mean_us), parse_us(max_us));
+            assert!(mean_us <= max_us, "{}: mean delay is greater than maximum delay", var);
+            format!("DelayPolicy::Exponential {{ mean_us: {}, max_us: {} }}", mean_us, max_us)
+        },
+        _ => { panic!("{}: unknown delay policy {:?}", var, policy) },
+    };
 
-{}
+    format!("DelayConfig {{ policy: {}, trigger: {} }}", code_policy, trigger)
+}
 
-{}
+// Every free channel needs a secret to be decoded with, and channel 0 must stay free
+fn free_channels_code(var: &str, default: &str, secrets: &[Secret]) -> String {
+    println!("cargo:rerun-if-env-changed={}", var);
+    let value = env::var(var).unwrap_or(String::from(default));
+
+    let mut channel_ids = Vec::<u32>::new();
+    let mut entries = Vec::<String>::new();
+    for entry in value.split(',') {
+        let entry = entry.trim();
+        let (channel, priority) = match entry.split_once(':') {
+            Some((channel, priority)) => { (channel, priority) },
+            None => { (entry, "normal") },
+        };
+        let channel_id = channel.parse::<u32>().unwrap_or_else(|_| panic!("{}: invalid channel ID {:?}", var, channel));
+        let code_priority = match priority {
+            "normal" => { "FramePriority::Normal" },
+            "emergency" => { "FramePriority::Emergency" },
+            other => { panic!("{}: unknown priority {:?} for channel {}", var, other, channel_id) },
+        };
+        assert!(!channel_ids.contains(&channel_id), "{}: channel {} is listed twice", var, channel_id);
+        assert!(secrets.iter().any(|secret| secret.secret_type == SecretType::Channel(channel_id)), "{}: channel {} has no secret in the secrets file", var, channel_id);
+        channel_ids.push(channel_id);
+        entries.push(format!("FreeChannel {{ channel_id: {}, priority: {} }}", channel_id, code_priority));
+    }
+    assert!(channel_ids.contains(&0), "{}: channel 0, the emergency channel every decoder must decode, is not listed", var);
 
-pub static SECRETS: [Secret; 128] = [
-{}
-];
-"#,
-        code_header,
-        code_subscriptions,
-        code_decoder_id,
-        code_secrets_arr,
-    );
+    entries.join(", ")
+}
 
-    let flash_code_path = Path::new("src/sys/generated_flash.rs");
-    fs::write(&flash_code_path, final_code).unwrap();
-    println!("cargo:rerun-if-changed=/global.secrets");
-    println!("cargo:rerun-if-changed=build.rs");    
+// The window is a bitmap in the decoder, so it cannot be wider than its bits
+const REPLAY_WINDOW_MAX: u32 = 64;
+
+fn replay_policy_code(var: &str, default: &str) -> String {
+    println!("cargo:rerun-if-env-changed={}", var);
+    let value = env::var(var).unwrap_or(String::from(default));
+
+    let fields: Vec<&str> = value.split(':').collect();
+    match fields.as_slice() {
+        ["strict"] => { String::from("ReplayPolicy::STRICT") },
+        ["window", behind, ahead] => {
+            let window = behind.parse::<u32>().unwrap_or_else(|_| panic!("{}: invalid window {:?}", var, behind));
+            assert!(window <= REPLAY_WINDOW_MAX, "{}: window {} is wider than {}", var, window, REPLAY_WINDOW_MAX);
+            let max_ahead = match *ahead {
+                "unbounded" => { String::from("None") },
+                ahead => { format!("Some({})", ahead.parse::<u64>().unwrap_or_else(|_| panic!("{}: invalid forward bound {:?}", var, ahead))) },
+            };
+            format!("ReplayPolicy {{ window: {}, max_ahead: {} }}", window, max_ahead)
+        },
+        _ => { panic!("{}: unknown replay policy {:?}", var, value) },
+    }
 }
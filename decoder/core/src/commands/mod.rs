@@ -0,0 +1,287 @@
+//! Command Execution Code
+
+use crate::message::{HostMessage, ResponseMessage};
+use crate::message::{HostUpdateMessage, HostDecodeMessage, HostDecodeBatchMessage, HostUnlockMessage, HostResyncMessage};
+use crate::message::{ResponseListMessage, ResponseDecodeMessage, ResponseDecodeBatchMessage};
+
+use alloc::vec::Vec;
+
+use zeroize::Zeroizing;
+
+use decoder_types::AesBlock;
+use decoder_types::packet::{subscription_signed_message, resync_signed_message};
+
+use crate::platform::{Aes, Flash};
+
+use rand_core::RngCore;
+
+use crate::message::packet::{verify_company_stamp, company_stamp_differs};
+
+use crate::sys::secure_memory::{merge_subscription, retrieve_subscription, retrieve_subscription_sequence, retrieve_subscriptions, retrieve_decoder_id, verify_decoder_id, retrieve_verifying_key};
+use crate::sys::secure_memory::{Secret, Subscription, SecureMemoryError};
+
+use crate::sys::replay::{verify_timestamp, timestamp_stale, verify_timestamp_ahead, timestamp_too_far_ahead, set_timestamp};
+use crate::sys::replay::{verify_resync_sequence, resync_sequence_stale, resync_timestamp};
+
+use crate::sys::free_channels::{free_channel_mask, channel_not_free, FreeChannel};
+
+use crate::sys::context::{channel_secret, forget_channel, record_authentication_failure, record_decoded};
+
+use crate::sys::decrypt::{decrypt_company_stamp, decrypt_decoder_id, decrypt_frame, DecryptError};
+
+use crate::sys::signature::{verify_provisioning_signature, provisioning_signature_invalid};
+
+use crate::sys::lockout::{unlock_epoch, reset_failures};
+
+use crate::sys::hardened::{check, check_eq_u32, check_le_u64, confirm, SecureBool};
+
+#[derive(Debug, Clone, Copy)]
+#[allow(unused)]
+pub enum CommandError {
+    InvalidSubscriptionChannel(u32),
+    NotSubscribed(u32),
+    SubscriptionFuture(u32, u64),
+    SubscriptionPast(u32, u64),
+    SubscriptionSequenceStale(u32, u64),
+    SubscriptionSignatureInvalid(u32),
+    InvalidDecoderID,
+    FramePast(u64),
+    FrameTooFarAhead(u64),
+    FrameLengthIncorrect(u32, u32),
+    ZeroPaddingNotIntact(u8),
+    FrameCompanyStampIncorrect(AesBlock),
+    EmptyFrameData,
+    UnlockEpochIncorrect(u32),
+    ResyncSequenceStale(u64),
+    ResyncSignatureInvalid,
+    SecureMemoryError(SecureMemoryError),
+    DecryptError(DecryptError)
+}
+
+impl CommandError {
+    // Errors that only forged or corrupted ciphertext can cause
+    // A stale update or resync is still signed by the provisioning key, so it is not one
+    pub fn is_authentication_failure(&self) -> bool {
+        match self {
+            CommandError::InvalidDecoderID => true,
+            CommandError::FrameCompanyStampIncorrect(_) => true,
+            CommandError::ZeroPaddingNotIntact(_) => true,
+            CommandError::DecryptError(_) => true,
+            CommandError::UnlockEpochIncorrect(_) => true,
+            CommandError::SubscriptionSignatureInvalid(_) => true,
+            CommandError::ResyncSignatureInvalid => true,
+            _ => false
+        }
+    }
+}
+
+pub fn execute_command<F: Flash, A: Aes, R: RngCore>(flc: &F, aes: &A, rng: &mut R, free_channels: &[FreeChannel], host_message: HostMessage) -> Result<ResponseMessage, CommandError> {
+    match host_message {
+        HostMessage::List => Ok(ResponseMessage::List(list_subscriptions(flc)?)),
+        HostMessage::Update(host_update_message) => Ok(ResponseMessage::Update(update_subscription(flc, aes, rng, free_channels, host_update_message)?)),
+        HostMessage::Decode(host_decode_message) => Ok(ResponseMessage::Decode(decode_message(flc, aes, rng, free_channels, host_decode_message)?)),
+        HostMessage::DecodeExtended(host_decode_message) => Ok(ResponseMessage::DecodeExtended(decode_message(flc, aes, rng, free_channels, host_decode_message)?)),
+        HostMessage::DecodeBatch(host_decode_batch_message) => Ok(ResponseMessage::DecodeBatch(decode_batch(flc, aes, rng, free_channels, host_decode_batch_message))),
+        HostMessage::Unlock(host_unlock_message) => Ok(ResponseMessage::Unlock(unlock_lockout(flc, rng, host_unlock_message)?)),
+        HostMessage::Resync(host_resync_message) => Ok(ResponseMessage::Resync(resync_clock(flc, rng, host_resync_message)?))
+    }
+}
+
+fn list_subscriptions<F: Flash>(flc: &F) -> Result<ResponseListMessage, CommandError> {
+    // Retrieve all subscriptions
+    let subscriptions = retrieve_subscriptions(flc);
+    if subscriptions.is_err() { return Err(CommandError::SecureMemoryError(subscriptions.unwrap_err())); }
+    let mut subscriptions = subscriptions.unwrap();
+    // Filter only valid subscriptions and return every window, grouped by channel
+    subscriptions.retain(|sub| sub.valid);
+    subscriptions.sort_unstable_by_key(|sub| (sub.channel_id, sub.start));
+    Ok(ResponseListMessage{subscriptions})
+}
+
+fn update_subscription<F: Flash, A: Aes, R: RngCore>(flc: &F, aes: &A, rng: &mut R, free_channels: &[FreeChannel], message: HostUpdateMessage) -> Result<(), CommandError> {
+    // Free channels are decoded without a subscription, so one would never be used
+    if free_channel_mask(free_channels, message.channel_id).0 != 0 { return Err(CommandError::InvalidSubscriptionChannel(message.channel_id)); }
+    // Validate decoder id intact
+    let decoder_id = decrypt_decoder_id(flc, aes, message.channel_id, message.encrypted_decoder_id);
+    if decoder_id.is_err() { return Err(CommandError::DecryptError(decoder_id.unwrap_err())); }
+    let decoder_id = decoder_id.unwrap();
+    let id_intact = check_decoder_id(flc, rng, decoder_id)?;
+    if !id_intact.is_true() { return Err(CommandError::InvalidDecoderID); }
+    // Validate update was signed for this decoder by the provisioning key
+    let verifying_key = retrieve_verifying_key(flc);
+    if verifying_key.is_err() { return Err(CommandError::SecureMemoryError(verifying_key.unwrap_err())); }
+    let verifying_key = verifying_key.unwrap();
+    let signed_message = subscription_signed_message(decoder_id, message.channel_id, message.sequence, message.end, message.start);
+    let signature_valid = check(rng, || verify_provisioning_signature(&verifying_key, &signed_message, &message.signature), || provisioning_signature_invalid(&verifying_key, &signed_message, &message.signature));
+    if !signature_valid.is_true() { return Err(CommandError::SubscriptionSignatureInvalid(message.channel_id)); }
+    // Validate update is newer than the channel's last one, so a captured update cannot roll it back
+    let latest_sequence = retrieve_subscription_sequence(flc, message.channel_id);
+    if latest_sequence.is_err() { return Err(CommandError::SecureMemoryError(latest_sequence.unwrap_err())); }
+    let latest_sequence = latest_sequence.unwrap();
+    // A channel without an update yet takes any sequence
+    let sequence_newer = check(rng, || latest_sequence.map_or(true, |latest| latest < message.sequence), || matches!(latest_sequence, Some(latest) if latest.checked_sub(message.sequence).is_some()));
+    if !sequence_newer.is_true() { return Err(CommandError::SubscriptionSequenceStale(message.channel_id, latest_sequence.unwrap_or(0))); }
+    // Merge the window into the channel's subscription
+    let subscription = Subscription {
+        channel_id: message.channel_id,
+        valid: true,
+        end: message.end,
+        start: message.start,
+        sequence: message.sequence
+    };
+    confirm(&[id_intact, signature_valid, sequence_newer]);
+    match merge_subscription(flc, subscription) {
+        Ok(()) => {
+            // The replaced subscription's cached key goes with it, the next frame reads it again
+            forget_channel(message.channel_id);
+            Ok(())
+        },
+        Err(secure_memory_error) => Err(CommandError::SecureMemoryError(secure_memory_error))
+    }
+}
+
+fn decode_message<F: Flash, A: Aes, R: RngCore>(flc: &F, aes: &A, rng: &mut R, free_channels: &[FreeChannel], message: HostDecodeMessage) -> Result<ResponseDecodeMessage, CommandError> {
+    // Validate metadata is within bounds
+    let timestamp_fresh = check(rng, || verify_timestamp(message.timestamp), || timestamp_stale(message.timestamp));
+    if !timestamp_fresh.is_true() { return Err(CommandError::FramePast(message.timestamp)); }
+    let timestamp_near = check(rng, || verify_timestamp_ahead(message.timestamp), || timestamp_too_far_ahead(message.timestamp));
+    if !timestamp_near.is_true() { return Err(CommandError::FrameTooFarAhead(message.timestamp)); }
+    let blocks = message.encrypted_frame.len();
+    let frame_present = check(rng, || blocks >= 2, || blocks.checked_sub(2).is_none());
+    if !frame_present.is_true() { return Err(CommandError::EmptyFrameData); }
+    let frame_length = message.frame_length;
+    let length_in_range = check(rng, || frame_length != 0 && frame_length <= 64, || frame_length.wrapping_sub(1) > 63);
+    if !length_in_range.is_true() { return Err(CommandError::FrameLengthIncorrect(frame_length, 0)); }
+    let blocks_match = check_eq_u32(rng, ((frame_length - 1) / 16) + 3, blocks as u32);
+    if !blocks_match.is_true() { return Err(CommandError::FrameLengthIncorrect(frame_length, 16)); }
+    // Get and verify subscription if not on a free channel, such as the emergency broadcast channel
+    // Anything but a verified free channel goes through the subscription checks
+    let (free, priority) = free_channel_mask(free_channels, message.channel_id);
+    let channel_free = check(rng, || free != 0, || channel_not_free(free_channels, message.channel_id));
+    if !channel_free.is_true() {
+        // A channel with no subscription left, or one that has ended, drops its cached key
+        let subscription = retrieve_subscription(flc, message.channel_id, message.timestamp);
+        if subscription.is_err() {
+            forget_channel(message.channel_id);
+            return Err(CommandError::SecureMemoryError(subscription.unwrap_err()));
+        }
+        let subscription = subscription.unwrap();
+        let subscribed = check(rng, || subscription.valid, || (subscription.valid as u8 ^ 1) != 0);
+        if !subscribed.is_true() {
+            forget_channel(message.channel_id);
+            return Err(CommandError::NotSubscribed(message.channel_id));
+        }
+        let started = check_le_u64(rng, subscription.start, message.timestamp);
+        if !started.is_true() { return Err(CommandError::SubscriptionFuture(message.channel_id, subscription.start)); }
+        let not_ended = check_le_u64(rng, message.timestamp, subscription.end);
+        if !not_ended.is_true() {
+            forget_channel(message.channel_id);
+            return Err(CommandError::SubscriptionPast(message.channel_id, subscription.end));
+        }
+        confirm(&[subscribed, started, not_ended]);
+    } else {
+        // Skipping the subscription checks is acting on the free channel check
+        confirm(&[channel_free]);
+    }
+    // Channel key material comes from the channel's decode context
+    let secret = channel_secret(flc, message.channel_id);
+    if secret.is_err() { return Err(CommandError::SecureMemoryError(secret.unwrap_err())); }
+    let secret = secret.unwrap();
+    let decrypted_frame = decrypt_channel_layer(aes, rng, &secret, message.frame_length, message.encrypted_frame);
+    if decrypted_frame.is_err() {
+        record_authentication_failure(message.channel_id);
+        return Err(decrypted_frame.unwrap_err());
+    }
+    confirm(&[timestamp_fresh, timestamp_near, frame_present, length_in_range, blocks_match]);
+    // Replay protection is global, so mark the shared timestamp as well as the channel's
+    set_timestamp(message.timestamp);
+    record_decoded(message.channel_id, message.timestamp);
+    Ok(ResponseDecodeMessage{frame: decrypted_frame.unwrap(), channel_id: message.channel_id, timestamp: message.timestamp, priority})
+}
+
+// Checks both company stamps and the zero padding, and returns the frame
+fn decrypt_channel_layer<A: Aes, R: RngCore>(aes: &A, rng: &mut R, secret: &Secret, frame_length: u32, encrypted_frame: Vec<AesBlock>) -> Result<Zeroizing<Vec<u8>>, CommandError> {
+    // Validate beginning company stamp intact
+    let decrypted_company_stamp = decrypt_company_stamp(aes, secret, *encrypted_frame.first().unwrap());
+    if decrypted_company_stamp.is_err() { return Err(CommandError::DecryptError(decrypted_company_stamp.unwrap_err())); }
+    let decrypted_company_stamp = decrypted_company_stamp.unwrap();
+    let first_stamp_intact = check(rng, || verify_company_stamp(decrypted_company_stamp), || company_stamp_differs(decrypted_company_stamp));
+    if !first_stamp_intact.is_true() { return Err(CommandError::FrameCompanyStampIncorrect(decrypted_company_stamp)); }
+    // Decrypt frame data
+    let decrypted_frame = decrypt_frame(aes, secret, encrypted_frame);
+    if decrypted_frame.is_err() { return Err(CommandError::DecryptError(decrypted_frame.unwrap_err())); }
+    let mut decrypted_frame = decrypted_frame.unwrap();
+    // Validate ending company stamp intact
+    let last_block: [u8; 16] = *decrypted_frame.last_chunk::<16>().unwrap();
+    let last_stamp_intact = check(rng, || verify_company_stamp(last_block), || company_stamp_differs(last_block));
+    if !last_stamp_intact.is_true() { return Err(CommandError::FrameCompanyStampIncorrect(last_block)); }
+    // Validate frame length
+    for _ in 0..16 {
+        decrypted_frame.pop();
+    }
+    if (decrypted_frame.len() as u32) < frame_length { return Err(CommandError::FrameLengthIncorrect(decrypted_frame.len() as u32, frame_length)); }
+    if (decrypted_frame.len() as u32) - frame_length > 15 { return Err(CommandError::FrameLengthIncorrect(decrypted_frame.len() as u32, frame_length)); }
+    while (decrypted_frame.len() as u32) > 0 && (decrypted_frame.len() as u32) > frame_length {
+        let excess = decrypted_frame.pop().unwrap();
+        let padding_intact = check_eq_u32(rng, excess as u32, 0);
+        if !padding_intact.is_true() { return Err(CommandError::ZeroPaddingNotIntact(excess)); }
+        confirm(&[padding_intact]);
+    }
+    if (decrypted_frame.len() as u32) != frame_length { return Err(CommandError::FrameLengthIncorrect(decrypted_frame.len() as u32, frame_length)); }
+    confirm(&[first_stamp_intact, last_stamp_intact]);
+    Ok(decrypted_frame)
+}
+
+fn decode_batch<F: Flash, A: Aes, R: RngCore>(flc: &F, aes: &A, rng: &mut R, free_channels: &[FreeChannel], message: HostDecodeBatchMessage) -> ResponseDecodeBatchMessage {
+    // Decode in order so every frame must still pass the timestamp check against the frame before it
+    let frames = message.frames.into_iter().map(|frame| decode_message(flc, aes, rng, free_channels, frame)).collect();
+    ResponseDecodeBatchMessage{frames}
+}
+
+fn unlock_lockout<F: Flash, R: RngCore>(flc: &F, rng: &mut R, message: HostUnlockMessage) -> Result<(), CommandError> {
+    // Validate decoder id intact
+    let id_intact = check_decoder_id(flc, rng, message.decoder_id)?;
+    if !id_intact.is_true() { return Err(CommandError::InvalidDecoderID); }
+    // Validate epoch so a used unlock cannot be replayed
+    let epoch = unlock_epoch(flc);
+    if epoch.is_err() { return Err(CommandError::SecureMemoryError(epoch.unwrap_err())); }
+    let epoch = epoch.unwrap();
+    let epoch_current = check_eq_u32(rng, message.epoch, epoch);
+    if !epoch_current.is_true() { return Err(CommandError::UnlockEpochIncorrect(epoch)); }
+    // Clear failures and move to next epoch
+    confirm(&[id_intact, epoch_current]);
+    match reset_failures(flc) {
+        Ok(()) => Ok(()),
+        Err(secure_memory_error) => Err(CommandError::SecureMemoryError(secure_memory_error))
+    }
+}
+
+fn resync_clock<F: Flash, R: RngCore>(flc: &F, rng: &mut R, message: HostResyncMessage) -> Result<(), CommandError> {
+    // Validate decoder id intact
+    let id_intact = check_decoder_id(flc, rng, message.decoder_id)?;
+    if !id_intact.is_true() { return Err(CommandError::InvalidDecoderID); }
+    // Validate resync was signed for this decoder by the provisioning key
+    let verifying_key = retrieve_verifying_key(flc);
+    if verifying_key.is_err() { return Err(CommandError::SecureMemoryError(verifying_key.unwrap_err())); }
+    let verifying_key = verifying_key.unwrap();
+    let signed_message = resync_signed_message(message.decoder_id, message.sequence, message.timestamp);
+    let signature_valid = check(rng, || verify_provisioning_signature(&verifying_key, &signed_message, &message.signature), || provisioning_signature_invalid(&verifying_key, &signed_message, &message.signature));
+    if !signature_valid.is_true() { return Err(CommandError::ResyncSignatureInvalid); }
+    // Validate resync is newer than the last one, so a captured resync cannot rewind the clock again
+    let sequence_newer = check(rng, || verify_resync_sequence(message.sequence), || resync_sequence_stale(message.sequence));
+    if !sequence_newer.is_true() { return Err(CommandError::ResyncSequenceStale(message.sequence)); }
+    confirm(&[id_intact, signature_valid, sequence_newer]);
+    resync_timestamp(message.timestamp, message.sequence);
+    Ok(())
+}
+
+// Compares against the saved decoder id twice, reading flash separately for each path
+fn check_decoder_id<F: Flash, R: RngCore>(flc: &F, rng: &mut R, decoder_id: u32) -> Result<SecureBool, CommandError> {
+    let verify_id = verify_decoder_id(flc, decoder_id);
+    if verify_id.is_err() { return Err(CommandError::SecureMemoryError(verify_id.unwrap_err())); }
+    let verify_id = verify_id.unwrap();
+    let saved_decoder_id = retrieve_decoder_id(flc);
+    if saved_decoder_id.is_err() { return Err(CommandError::SecureMemoryError(saved_decoder_id.unwrap_err())); }
+    let saved_decoder_id = saved_decoder_id.unwrap();
+    Ok(check(rng, || verify_id, || (saved_decoder_id ^ decoder_id) != 0))
+}
@@ -0,0 +1,102 @@
+//! Hardware independent decoder logic
+//! Contains:
+//! - Message receiving and transmitting
+//! - Command execution
+//! - Flash, decryption, lockout, and rng functions
+//!
+//! Peripherals are reached only through the traits in `platform`, so the same
+//! code runs in the firmware and in the host simulator.
+
+#![no_std]
+
+extern crate alloc;
+
+pub mod commands;
+pub mod message;
+pub mod platform;
+pub mod sys;
+
+use rand_core::RngCore;
+
+use message::receive::receive_message;
+use message::transmit::{transmit_err, transmit_message};
+use message::{HostMessage, ResponseMessage};
+use commands::execute_command;
+use platform::{Aes, Delay, Flash, Uart};
+use sys::rng::{delay_rand, DelayConfigs, DelayTrigger};
+use sys::free_channels::FreeChannel;
+use sys::lockout::{init_failures, lockout_delay_ms, lockout_failure};
+use sys::replay::{reset_timestamp, ReplayPolicy};
+use sys::context::reset_contexts;
+
+// Runs once after reset, before serving any message
+// `replay` is the replay protection policy for frames until the next reset
+pub fn boot<F: Flash, D: Delay>(flc: &F, delay: &mut D, replay: ReplayPolicy) {
+    // RAM state is fresh on the device, but not when the simulator resets
+    reset_timestamp(replay);
+    reset_contexts();
+    // Serve out any lockout earned before the last reset
+    if let Ok(failures) = init_failures(flc) {
+        delay.delay_ms(lockout_delay_ms(failures));
+    }
+}
+
+// Receives, executes, and responds to one host message
+// On TXError, no recourse possible, so the message is dropped
+// `free_channels` are decoded without a subscription, and must include channel 0
+pub fn serve_message<F: Flash, A: Aes, U: Uart, D: Delay, R: RngCore>(flc: &F, aes: &A, uart: &U, delay: &mut D, rng: &mut R, delays: &DelayConfigs, free_channels: &[FreeChannel]) {
+    // Receive command from host device
+    let host_message = receive_message(flc, uart, aes);
+    aes.clear_key();
+
+    // Pick delay policy for this command type
+    let delay_config = match &host_message {
+        Ok(HostMessage::List) => delays.list,
+        Ok(HostMessage::Update(_)) | Ok(HostMessage::Unlock(_)) | Ok(HostMessage::Resync(_)) => delays.update,
+        Ok(HostMessage::Decode(_)) | Ok(HostMessage::DecodeExtended(_)) | Ok(HostMessage::DecodeBatch(_)) => delays.decode,
+        Err(_) => delays.receive
+    };
+    if delay_config.trigger == DelayTrigger::Always {
+        delay_rand(rng, delay, delay_config.policy);
+    }
+
+    if host_message.is_err() {
+        let error = host_message.unwrap_err();
+        if error.is_authentication_failure() {
+            lockout_failure(flc, delay);
+            if delay_config.trigger == DelayTrigger::AuthenticationFailure {
+                delay_rand(rng, delay, delay_config.policy);
+            }
+        }
+        let _ = transmit_err(uart, error);
+        return;
+    }
+    let host_message = host_message.unwrap();
+
+    // Execute instructions
+    let response_message = execute_command(flc, aes, rng, free_channels, host_message);
+    aes.clear_key();
+
+    // Count authentication failures towards lockout
+    let authentication_failure = match &response_message {
+        Ok(ResponseMessage::DecodeBatch(batch)) => batch.frames.iter().any(|frame| matches!(frame, Err(error) if error.is_authentication_failure())),
+        Ok(_) => false,
+        Err(error) => error.is_authentication_failure()
+    };
+    if authentication_failure {
+        lockout_failure(flc, delay);
+        if delay_config.trigger == DelayTrigger::AuthenticationFailure {
+            delay_rand(rng, delay, delay_config.policy);
+        }
+    }
+
+    // Respond to host device
+    match response_message {
+        Ok(response) => {
+            let _ = transmit_message(uart, response);
+        },
+        Err(error) => {
+            let _ = transmit_err(uart, error);
+        }
+    }
+}
@@ -0,0 +1,111 @@
+//! Message module
+//! Contains:
+//! - Information about message structure: (Commands, Responses, and Metadata)
+
+pub mod receive;
+pub mod transmit;
+pub mod packet;
+
+extern crate alloc;
+use alloc::vec::Vec;
+
+use zeroize::Zeroizing;
+
+use decoder_types::AesBlock;
+use decoder_types::packet::SUBSCRIPTION_SIGNATURE_SIZE;
+
+use crate::sys::secure_memory::Subscription;
+use crate::sys::free_channels::FramePriority;
+
+use crate::commands::CommandError;
+
+#[derive(Debug, Clone, Copy)]
+pub struct HostUpdateMessage {
+    pub channel_id: u32, 
+    pub sequence: u64, 
+    pub end: u64,
+    pub start: u64, 
+    pub encrypted_decoder_id: AesBlock, 
+    pub signature: [u8; SUBSCRIPTION_SIGNATURE_SIZE],
+}
+
+#[derive(Debug, Clone)]
+pub struct HostDecodeMessage {
+    pub timestamp: u64, 
+    pub channel_id: u32, 
+    pub frame_length: u32, 
+    pub encrypted_frame: Vec<AesBlock>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct HostUnlockMessage {
+    pub decoder_id: u32,
+    pub epoch: u32,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct HostResyncMessage {
+    pub decoder_id: u32,
+    pub sequence: u64,
+    pub timestamp: u64,
+    pub signature: [u8; SUBSCRIPTION_SIGNATURE_SIZE],
+}
+
+#[derive(Debug, Clone)]
+pub struct HostDecodeBatchMessage {
+    pub frames: Vec<HostDecodeMessage>,
+}
+
+#[derive(Debug, Clone)]
+pub enum HostMessage {
+    List,
+    Update (HostUpdateMessage),
+    Decode (HostDecodeMessage), 
+    DecodeExtended (HostDecodeMessage),
+    DecodeBatch (HostDecodeBatchMessage),
+    Unlock (HostUnlockMessage),
+    Resync (HostResyncMessage),
+}
+
+#[derive(Debug, Clone)]
+pub struct ResponseListMessage {
+    pub subscriptions: Vec<Subscription>
+}
+
+#[derive(Debug, Clone)]
+pub struct ResponseDecodeMessage {
+    // Wiped once the response has been sent
+    pub frame: Zeroizing<Vec<u8>>,
+    // Verified metadata, DECODE keeps the reference body of the frame alone
+    // Priority is sent in batch and extended responses, channel and timestamp only in extended ones
+    pub channel_id: u32,
+    pub timestamp: u64,
+    pub priority: FramePriority
+}
+
+#[derive(Debug, Clone)]
+pub struct ResponseDecodeBatchMessage {
+    pub frames: Vec<Result<ResponseDecodeMessage, CommandError>>
+}
+
+#[derive(Debug, Clone)]
+pub enum ResponseMessage {
+    List   (ResponseListMessage),
+    Update (()),
+    Decode (ResponseDecodeMessage), 
+    DecodeExtended (ResponseDecodeMessage),
+    DecodeBatch (ResponseDecodeBatchMessage),
+    Unlock (()),
+    Resync (()),
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct MessageHeader {
+    pub magic: u8,
+    pub opcode: u8,
+    pub length: u16
+}
+
+// Shared with the host client through decoder_types::protocol
+use decoder_types::protocol::{MAGIC_BYTE, DEBUG_OPCODE, LIST_OPCODE, UPDATE_OPCODE, DECODE_OPCODE, DECODE_EXTENDED_OPCODE, DECODE_BATCH_OPCODE, UNLOCK_OPCODE, RESYNC_OPCODE, ACK_OPCODE, ERR_OPCODE};
+use decoder_types::protocol::{CHUNK_SIZE, MAX_BATCH_FRAMES};
@@ -0,0 +1,70 @@
+//! Packet utility functions to extract metadata from raw AES blocks
+
+use core::iter::zip;
+
+use decoder_types::AesBlock;
+
+use decoder_types::packet::{COMPANY_STAMP, parse_padded_u32, parse_subscription_header, parse_timestamps, parse_unlock, parse_resync, parse_frame_metadata};
+
+#[derive(Debug, Clone, Copy)]
+#[allow(unused)]
+pub enum PacketError {
+    ZeroPaddingNotIntact(usize)
+}
+
+// Layouts live in decoder_types::packet so the host design tools build the exact same packets
+
+// Returns (channel id, sequence)
+pub fn extract_subscription_header(decoded_block: AesBlock) -> Result<(u32, u64), PacketError> {
+    match parse_subscription_header(decoded_block) {
+        Some(header) => Ok(header),
+        None => Err(PacketError::ZeroPaddingNotIntact(32))
+    }
+}
+
+pub fn extract_timestamps(decoded_block: AesBlock) -> (u64, u64) {
+    parse_timestamps(decoded_block)
+}
+
+pub fn extract_decoder_id(decoded_block: AesBlock) -> Result<u32, PacketError> {
+    match parse_padded_u32(decoded_block) {
+        Some(decoder_id) => Ok(decoder_id),
+        None => Err(PacketError::ZeroPaddingNotIntact(96))
+    }
+}
+
+pub fn extract_unlock(decoded_block: AesBlock) -> Result<(u32, u32), PacketError> {
+    match parse_unlock(decoded_block) {
+        Some(unlock) => Ok(unlock),
+        None => Err(PacketError::ZeroPaddingNotIntact(64))
+    }
+}
+
+// Returns (decoder id, sequence, timestamp)
+pub fn extract_resync(decoded_blocks: [AesBlock; 2]) -> Result<(u32, u64, u64), PacketError> {
+    match parse_resync(decoded_blocks) {
+        Some(resync) => Ok(resync),
+        None => Err(PacketError::ZeroPaddingNotIntact(96))
+    }
+}
+
+pub fn extract_frame_metadata(decoded_block: AesBlock) -> (u64, u32, u32) {
+    parse_frame_metadata(decoded_block)
+}
+
+// Inverse of verify_company_stamp along a different path, for hardened checks
+pub fn company_stamp_differs(decoded_block: AesBlock) -> bool {
+    let mut difference: u32 = 0;
+    for (block_byte, stamp_byte) in zip(decoded_block, COMPANY_STAMP).rev() {
+        difference += (block_byte ^ stamp_byte) as u32;
+    }
+    difference != 0
+}
+
+pub fn verify_company_stamp(decoded_block: AesBlock) -> bool {
+    let mut c: u8 = 0;
+    for (block_byte, stamp_byte) in zip(decoded_block, COMPANY_STAMP) {
+        c |= block_byte ^ stamp_byte
+    }
+    c == 0
+}
@@ -0,0 +1,224 @@
+//! Reciever functions
+
+extern crate alloc;
+use alloc::vec::Vec;
+
+use decoder_types::AesBlock;
+use decoder_types::packet::SUBSCRIPTION_SIGNATURE_SIZE;
+
+use crate::platform::{Aes, Flash, Uart};
+
+use crate::sys::decrypt::DecryptError;
+use crate::sys::decrypt::decrypt_message;
+
+use super::{HostDecodeBatchMessage, HostDecodeMessage, HostMessage, HostResyncMessage, HostUnlockMessage, HostUpdateMessage, MessageHeader};
+use super::{MAGIC_BYTE, LIST_OPCODE, UPDATE_OPCODE, DECODE_OPCODE, DECODE_EXTENDED_OPCODE, DECODE_BATCH_OPCODE, UNLOCK_OPCODE, RESYNC_OPCODE, DEBUG_OPCODE, ACK_OPCODE, ERR_OPCODE};
+use super::{CHUNK_SIZE, MAX_BATCH_FRAMES};
+
+use super::packet::PacketError;
+use super::packet::{extract_subscription_header, extract_timestamps, extract_frame_metadata, extract_unlock, extract_resync};
+
+use super::transmit::transmit_ack;
+
+#[derive(Debug, Clone, Copy)]
+#[allow(unused)]
+pub enum RXError {
+    IncorrectMagic(u8),
+    InvalidOpcode(u8),
+    InvalidLength(u16),
+    InvalidBatchCount(u32),
+    UnexpectedDebug,
+    UnexpectedACK,
+    UnexpectedERR,
+    PacketError(PacketError),
+    DecryptError(DecryptError)
+}
+
+impl RXError {
+    // Errors that only forged or corrupted ciphertext can cause
+    pub fn is_authentication_failure(&self) -> bool {
+        match self {
+            RXError::PacketError(_) => true,
+            RXError::DecryptError(_) => true,
+            _ => false
+        }
+    }
+}
+
+pub fn receive_message<F: Flash, U: Uart, A: Aes>(flc: &F, uart: &U, aes: &A) -> Result<HostMessage, RXError> {
+    let message_header = receive_header(uart);
+    if message_header.magic != MAGIC_BYTE { return Err(RXError::IncorrectMagic(message_header.magic)); }
+    match message_header.opcode {
+        LIST_OPCODE => {
+            if message_header.length != 0 { return Err(RXError::InvalidLength(message_header.length)); }
+            transmit_ack(uart);
+            Ok(HostMessage::List)
+        },
+        UPDATE_OPCODE => { Ok(HostMessage::Update(receive_update_body(flc, uart, aes, message_header)?)) },
+        DECODE_OPCODE => { Ok(HostMessage::Decode(receive_decode_body(flc, uart, aes, message_header)?)) },
+        // Same request as DECODE, only the response differs
+        DECODE_EXTENDED_OPCODE => { Ok(HostMessage::DecodeExtended(receive_decode_body(flc, uart, aes, message_header)?)) },
+        DECODE_BATCH_OPCODE => { Ok(HostMessage::DecodeBatch(receive_decode_batch_body(flc, uart, aes, message_header)?)) },
+        UNLOCK_OPCODE => { Ok(HostMessage::Unlock(receive_unlock_body(flc, uart, aes, message_header)?)) },
+        RESYNC_OPCODE => { Ok(HostMessage::Resync(receive_resync_body(flc, uart, aes, message_header)?)) },
+        DEBUG_OPCODE => { Err(RXError::UnexpectedDebug) },
+        ACK_OPCODE => { Err(RXError::UnexpectedACK) },
+        ERR_OPCODE => { Err(RXError::UnexpectedERR) },
+        other => { Err(RXError::InvalidOpcode(other)) }
+    }
+}
+
+pub fn receive_ack<U: Uart>(uart: &U) -> Result<(), RXError> {
+    let mut header_buf: [u8; 4] = [0; 4];
+    let mut length_buf: [u8; 2] = [0; 2];
+    uart.read_bytes(&mut header_buf);
+    length_buf.clone_from_slice(&header_buf[2..=3]);
+    let length = u16::from_le_bytes(length_buf);
+    if header_buf[0] != MAGIC_BYTE { return Err(RXError::IncorrectMagic(header_buf[0])); }
+    if header_buf[1] != ACK_OPCODE { return Err(RXError::InvalidOpcode(header_buf[1])); }
+    if length != 0 { return Err(RXError::InvalidLength(length)); }
+    Ok(())
+}
+
+fn receive_header<U: Uart>(uart: &U) -> MessageHeader {
+    let mut header_buf: [u8; 4] = [0; 4];
+    let mut length_buf: [u8; 2] = [0; 2];
+    uart.read_bytes(&mut header_buf);
+    length_buf.clone_from_slice(&header_buf[2..=3]);
+    MessageHeader{ magic: header_buf[0], opcode: header_buf[1], length: u16::from_le_bytes(length_buf) }
+}
+
+fn receive_update_body<F: Flash, U: Uart, A: Aes>(flc: &F, uart: &U, aes: &A, header: MessageHeader) -> Result<HostUpdateMessage, RXError> {
+    // Three encrypted blocks, then the signature in the clear
+    if header.length != 112 { return Err(RXError::InvalidLength(header.length)); }
+    let mut body_buf: [u8; 112] = [0; 112];
+    transmit_ack(uart);
+    uart.read_bytes(&mut body_buf);
+    let signature: [u8; SUBSCRIPTION_SIGNATURE_SIZE] = *body_buf.last_chunk::<SUBSCRIPTION_SIGNATURE_SIZE>().unwrap();
+    let encrypted_blocks: Vec<AesBlock> = body_buf[..48].chunks_exact(16).map(|x| *x.first_chunk::<16>().unwrap()).collect();
+    let decrypted_blocks = decrypt_message(flc, aes, encrypted_blocks);
+    if decrypted_blocks.is_err() { return Err(RXError::DecryptError(decrypted_blocks.unwrap_err())); }
+    let decrypted_blocks = decrypted_blocks.unwrap();
+    let header = extract_subscription_header(decrypted_blocks[0]);
+    if header.is_err() { return Err(RXError::PacketError(header.unwrap_err())); }
+    let (channel_id, sequence) = header.unwrap();
+    let (end, start) = extract_timestamps(decrypted_blocks[1]);
+    transmit_ack(uart);
+    Ok(HostUpdateMessage{ channel_id, sequence, end, start, encrypted_decoder_id: decrypted_blocks[2], signature })
+}
+
+fn receive_unlock_body<F: Flash, U: Uart, A: Aes>(flc: &F, uart: &U, aes: &A, header: MessageHeader) -> Result<HostUnlockMessage, RXError> {
+    if header.length != 16 { return Err(RXError::InvalidLength(header.length)); }
+    let mut body_buf: [u8; 16] = [0; 16];
+    transmit_ack(uart);
+    uart.read_bytes(&mut body_buf);
+    let decrypted_blocks = decrypt_message(flc, aes, [body_buf].to_vec());
+    if decrypted_blocks.is_err() { return Err(RXError::DecryptError(decrypted_blocks.unwrap_err())); }
+    let decrypted_blocks = decrypted_blocks.unwrap();
+    let unlock = extract_unlock(decrypted_blocks[0]);
+    if unlock.is_err() { return Err(RXError::PacketError(unlock.unwrap_err())); }
+    let (decoder_id, epoch) = unlock.unwrap();
+    transmit_ack(uart);
+    Ok(HostUnlockMessage{ decoder_id, epoch })
+}
+
+fn receive_resync_body<F: Flash, U: Uart, A: Aes>(flc: &F, uart: &U, aes: &A, header: MessageHeader) -> Result<HostResyncMessage, RXError> {
+    // Two encrypted blocks, then the signature in the clear
+    if header.length != 96 { return Err(RXError::InvalidLength(header.length)); }
+    let mut body_buf: [u8; 96] = [0; 96];
+    transmit_ack(uart);
+    uart.read_bytes(&mut body_buf);
+    let signature: [u8; SUBSCRIPTION_SIGNATURE_SIZE] = *body_buf.last_chunk::<SUBSCRIPTION_SIGNATURE_SIZE>().unwrap();
+    let encrypted_blocks: Vec<AesBlock> = body_buf[..32].chunks_exact(16).map(|x| *x.first_chunk::<16>().unwrap()).collect();
+    let decrypted_blocks = decrypt_message(flc, aes, encrypted_blocks);
+    if decrypted_blocks.is_err() { return Err(RXError::DecryptError(decrypted_blocks.unwrap_err())); }
+    let decrypted_blocks = decrypted_blocks.unwrap();
+    let resync = extract_resync([decrypted_blocks[0], decrypted_blocks[1]]);
+    if resync.is_err() { return Err(RXError::PacketError(resync.unwrap_err())); }
+    let (decoder_id, sequence, timestamp) = resync.unwrap();
+    transmit_ack(uart);
+    Ok(HostResyncMessage{ decoder_id, sequence, timestamp, signature })
+}
+
+fn receive_decode_body<F: Flash, U: Uart, A: Aes>(flc: &F, uart: &U, aes: &A, header: MessageHeader) -> Result<HostDecodeMessage, RXError> {
+    let encrypted_blocks: Vec<AesBlock>;
+    match header.length {
+        64 => {
+            let mut body_buf: [u8; 64] = [0; 64];
+            transmit_ack(uart);
+            uart.read_bytes(&mut body_buf);
+            encrypted_blocks = body_buf.chunks_exact(16).map(|x| *x.first_chunk::<16>().unwrap()).collect();
+        },
+        80 => {
+            let mut body_buf: [u8; 80] = [0; 80];
+            transmit_ack(uart);
+            uart.read_bytes(&mut body_buf);
+            encrypted_blocks = body_buf.chunks_exact(16).map(|x| *x.first_chunk::<16>().unwrap()).collect();
+        },
+        96 => {
+            let mut body_buf: [u8; 96] = [0; 96];
+            transmit_ack(uart);
+            uart.read_bytes(&mut body_buf);
+            encrypted_blocks = body_buf.chunks_exact(16).map(|x| *x.first_chunk::<16>().unwrap()).collect();
+        },
+        112 => {
+            let mut body_buf: [u8; 112] = [0; 112];
+            transmit_ack(uart);
+            uart.read_bytes(&mut body_buf);
+            encrypted_blocks = body_buf.chunks_exact(16).map(|x| *x.first_chunk::<16>().unwrap()).collect();
+        }
+        other => { return Err(RXError::InvalidLength(other)); }
+    }
+    let decode_message = decrypt_decode_blocks(flc, aes, encrypted_blocks)?;
+    transmit_ack(uart);
+    Ok(decode_message)
+}
+
+fn receive_decode_batch_body<F: Flash, U: Uart, A: Aes>(flc: &F, uart: &U, aes: &A, header: MessageHeader) -> Result<HostDecodeBatchMessage, RXError> {
+    // Body is a u32 frame count followed by that many (u16 length, encrypted frame) entries
+    if (header.length as usize) < 4 + 2 + 64 || (header.length as usize) > 4 + MAX_BATCH_FRAMES * (2 + 112) { return Err(RXError::InvalidLength(header.length)); }
+    transmit_ack(uart);
+    let body_buf = receive_chunks(uart, header.length as usize);
+    let frame_count = u32::from_le_bytes(*body_buf.first_chunk::<4>().unwrap());
+    if frame_count == 0 || frame_count as usize > MAX_BATCH_FRAMES { return Err(RXError::InvalidBatchCount(frame_count)); }
+    let mut frames: Vec<HostDecodeMessage> = Vec::with_capacity(frame_count as usize);
+    let mut offset: usize = 4;
+    for _ in 0..frame_count {
+        if body_buf.len() < offset + 2 { return Err(RXError::InvalidLength(header.length)); }
+        let frame_length = u16::from_le_bytes([body_buf[offset], body_buf[offset + 1]]);
+        offset += 2;
+        match frame_length {
+            64 | 80 | 96 | 112 => {},
+            other => { return Err(RXError::InvalidLength(other)); }
+        }
+        if body_buf.len() < offset + frame_length as usize { return Err(RXError::InvalidLength(header.length)); }
+        let encrypted_blocks: Vec<AesBlock> = body_buf[offset..offset + frame_length as usize].chunks_exact(16).map(|x| *x.first_chunk::<16>().unwrap()).collect();
+        offset += frame_length as usize;
+        frames.push(decrypt_decode_blocks(flc, aes, encrypted_blocks)?);
+    }
+    if offset != body_buf.len() { return Err(RXError::InvalidLength(header.length)); }
+    transmit_ack(uart);
+    Ok(HostDecodeBatchMessage{ frames })
+}
+
+// Reads a body longer than one chunk, acknowledging every chunk but the last
+// The caller acknowledges the last chunk once the body has been validated
+fn receive_chunks<U: Uart>(uart: &U, length: usize) -> Vec<u8> {
+    let mut body_buf: Vec<u8> = Vec::with_capacity(length);
+    let mut chunk_buf: [u8; CHUNK_SIZE] = [0; CHUNK_SIZE];
+    while body_buf.len() < length {
+        let chunk_length = core::cmp::min(CHUNK_SIZE, length - body_buf.len());
+        uart.read_bytes(&mut chunk_buf[..chunk_length]);
+        body_buf.extend_from_slice(&chunk_buf[..chunk_length]);
+        if body_buf.len() < length { transmit_ack(uart); }
+    }
+    body_buf
+}
+
+fn decrypt_decode_blocks<F: Flash, A: Aes>(flc: &F, aes: &A, encrypted_blocks: Vec<AesBlock>) -> Result<HostDecodeMessage, RXError> {
+    let decrypted_blocks = decrypt_message(flc, aes, encrypted_blocks);
+    if decrypted_blocks.is_err() { return Err(RXError::DecryptError(decrypted_blocks.unwrap_err())); }
+    let decrypted_blocks = decrypted_blocks.unwrap();
+    let (timestamp, channel_id, frame_length) = extract_frame_metadata(decrypted_blocks[0]);
+    Ok(HostDecodeMessage{ timestamp, channel_id, frame_length, encrypted_frame: decrypted_blocks[1..].to_vec() })
+}
\ No newline at end of file
@@ -0,0 +1,188 @@
+//! Transmitter functions
+
+use core::fmt::write;
+
+extern crate alloc;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use zeroize::Zeroizing;
+
+use decoder_types::SUBSCRIPTION_WINDOWS_CAPACITY;
+use decoder_types::protocol::DECODE_EXTENDED_HEADER_SIZE;
+
+use crate::platform::Uart;
+use crate::sys::free_channels::FramePriority;
+
+use super::{MessageHeader, ResponseListMessage, ResponseDecodeMessage, ResponseDecodeBatchMessage, ResponseMessage};
+use super::{MAGIC_BYTE, LIST_OPCODE, UPDATE_OPCODE, DECODE_OPCODE, DECODE_EXTENDED_OPCODE, DECODE_BATCH_OPCODE, UNLOCK_OPCODE, RESYNC_OPCODE, ACK_OPCODE, ERR_OPCODE};
+use super::CHUNK_SIZE;
+
+use super::receive::RXError;
+use super::receive::receive_ack;
+
+#[derive(Debug, Clone, Copy)]
+#[allow(unused)]
+pub enum TXError {
+    RXError(RXError),
+    InvalidSubscriptionCount(u32)
+}
+
+pub fn transmit_message<U: Uart>(uart: &U, message: ResponseMessage) -> Result<(), TXError> {
+    match message {
+        ResponseMessage::List(list_response) => {
+            let message_header = MessageHeader{ magic: MAGIC_BYTE, opcode: LIST_OPCODE, length: 4+(list_response.subscriptions.len()*20) as u16 };
+            transmit_header(uart, message_header);
+            let ack = receive_ack(uart);
+            if ack.is_err() { return Err(TXError::RXError(ack.unwrap_err())); }
+            transmit_list_body(uart, list_response)
+        }
+        ResponseMessage::Update(()) => {
+            let message_header = MessageHeader{ magic: MAGIC_BYTE, opcode: UPDATE_OPCODE, length: 0 };
+            transmit_header(uart, message_header);
+            let ack = receive_ack(uart);
+            if ack.is_err() { return Err(TXError::RXError(ack.unwrap_err())); }
+            Ok(())
+        }
+        ResponseMessage::Decode(decode_response) => {
+            let message_header = MessageHeader{ magic: MAGIC_BYTE, opcode: DECODE_OPCODE, length: decode_response.frame.len() as u16 };
+            transmit_header(uart, message_header);
+            let ack = receive_ack(uart);
+            if ack.is_err() { return Err(TXError::RXError(ack.unwrap_err())); }
+            transmit_decode_body(uart, decode_response)
+        }
+        ResponseMessage::DecodeExtended(decode_response) => {
+            let body = encode_decode_extended_body(decode_response);
+            let message_header = MessageHeader{ magic: MAGIC_BYTE, opcode: DECODE_EXTENDED_OPCODE, length: body.len() as u16 };
+            transmit_header(uart, message_header);
+            let ack = receive_ack(uart);
+            if ack.is_err() { return Err(TXError::RXError(ack.unwrap_err())); }
+            transmit_chunks(uart, body.as_slice())
+        }
+        ResponseMessage::DecodeBatch(decode_batch_response) => {
+            let body = encode_decode_batch_body(decode_batch_response);
+            let message_header = MessageHeader{ magic: MAGIC_BYTE, opcode: DECODE_BATCH_OPCODE, length: body.len() as u16 };
+            transmit_header(uart, message_header);
+            let ack = receive_ack(uart);
+            if ack.is_err() { return Err(TXError::RXError(ack.unwrap_err())); }
+            transmit_chunks(uart, body.as_slice())
+        }
+        ResponseMessage::Unlock(()) => {
+            let message_header = MessageHeader{ magic: MAGIC_BYTE, opcode: UNLOCK_OPCODE, length: 0 };
+            transmit_header(uart, message_header);
+            let ack = receive_ack(uart);
+            if ack.is_err() { return Err(TXError::RXError(ack.unwrap_err())); }
+            Ok(())
+        }
+        ResponseMessage::Resync(()) => {
+            let message_header = MessageHeader{ magic: MAGIC_BYTE, opcode: RESYNC_OPCODE, length: 0 };
+            transmit_header(uart, message_header);
+            let ack = receive_ack(uart);
+            if ack.is_err() { return Err(TXError::RXError(ack.unwrap_err())); }
+            Ok(())
+        }
+    }
+}
+
+pub fn transmit_ack<U: Uart>(uart: &U) -> () {
+    let header_bytes: [u8; 4] = [MAGIC_BYTE, ACK_OPCODE, 0, 0];
+    uart.write_bytes(&header_bytes);
+}
+
+pub fn transmit_err<U: Uart, T: core::fmt::Debug>(uart: &U, error: T) -> Result<(), TXError> {
+    let mut error_body = String::new();
+    write(&mut error_body, format_args!("{:?}", error)).expect("Could not create error message");
+    let message_header = MessageHeader{ magic: MAGIC_BYTE, opcode: ERR_OPCODE, length: error_body.len() as u16 };
+    transmit_header(uart, message_header);
+    let ack = receive_ack(uart);
+    if ack.is_err() { return Err(TXError::RXError(ack.unwrap_err())); }
+    let error_bytes = error_body.as_bytes();
+    uart.write_bytes(error_bytes);
+    let ack = receive_ack(uart);
+    if ack.is_err() { return Err(TXError::RXError(ack.unwrap_err())); }
+    Ok(())
+}
+
+fn transmit_header<U: Uart>(uart: &U, header: MessageHeader) -> () {
+    let header_bytes: [u8; 4] = [header.magic, header.opcode, header.length as u8, (header.length >> 8) as u8];
+    uart.write_bytes(&header_bytes);
+}
+
+// Body is a u32 window count followed by one (u32 channel, u64 start, u64 end) entry per window
+// A channel with several windows has an entry for each
+fn transmit_list_body<U: Uart>(uart: &U, message: ResponseListMessage) -> Result<(), TXError> {
+    if message.subscriptions.len() > SUBSCRIPTION_WINDOWS_CAPACITY { return Err(TXError::InvalidSubscriptionCount(message.subscriptions.len() as u32)); }
+    let mut body: Vec<u8> = Vec::with_capacity(4 + message.subscriptions.len() * 20);
+    body.extend_from_slice(&(message.subscriptions.len() as u32).to_le_bytes());
+    for subscription in message.subscriptions {
+        body.extend_from_slice(&subscription.channel_id.to_le_bytes());
+        body.extend_from_slice(&subscription.start.to_le_bytes());
+        body.extend_from_slice(&subscription.end.to_le_bytes());
+    }
+    transmit_chunks(uart, body.as_slice())
+}
+
+fn transmit_decode_body<U: Uart>(uart: &U, message: ResponseDecodeMessage) -> Result<(), TXError> {
+    uart.write_bytes(message.frame.as_slice());
+    let ack = receive_ack(uart);
+    if ack.is_err() { return Err(TXError::RXError(ack.unwrap_err())); }
+    Ok(())
+}
+
+// Body is the frame's verified (u32 channel, u64 timestamp, u8 priority) followed by the frame
+// Wiped like the frame it holds
+fn encode_decode_extended_body(message: ResponseDecodeMessage) -> Zeroizing<Vec<u8>> {
+    let mut body: Zeroizing<Vec<u8>> = Zeroizing::new(Vec::with_capacity(DECODE_EXTENDED_HEADER_SIZE + message.frame.len()));
+    body.extend_from_slice(&message.channel_id.to_le_bytes());
+    body.extend_from_slice(&message.timestamp.to_le_bytes());
+    body.push(message.priority.to_byte());
+    body.extend_from_slice(message.frame.as_slice());
+    body
+}
+
+// Body is a u32 frame count followed by one (u8 status, u8 priority, u16 length, data) entry per frame
+// Status is the DECODE opcode with the frame as data, or the ERR opcode with the error message as data
+// Priority is the decoded frame's, and normal for an error
+// Error messages are formatted first so the body is sized once, no reallocation leaves a frame behind
+fn encode_decode_batch_body(message: ResponseDecodeBatchMessage) -> Zeroizing<Vec<u8>> {
+    let entries: Vec<Result<ResponseDecodeMessage, String>> = message.frames.into_iter().map(|frame| match frame {
+        Ok(decode_response) => Ok(decode_response),
+        Err(error) => {
+            let mut error_body = String::new();
+            write(&mut error_body, format_args!("{:?}", error)).expect("Could not create error message");
+            Err(error_body)
+        }
+    }).collect();
+    let length: usize = entries.iter().map(|entry| 4 + match entry {
+        Ok(decode_response) => decode_response.frame.len(),
+        Err(error_body) => error_body.len()
+    }).sum();
+    let mut body: Zeroizing<Vec<u8>> = Zeroizing::new(Vec::with_capacity(4 + length));
+    body.extend_from_slice(&(entries.len() as u32).to_le_bytes());
+    for entry in entries {
+        match entry {
+            Ok(decode_response) => {
+                body.push(DECODE_OPCODE);
+                body.push(decode_response.priority.to_byte());
+                body.extend_from_slice(&(decode_response.frame.len() as u16).to_le_bytes());
+                body.extend_from_slice(decode_response.frame.as_slice());
+            },
+            Err(error_body) => {
+                body.push(ERR_OPCODE);
+                body.push(FramePriority::Normal.to_byte());
+                body.extend_from_slice(&(error_body.len() as u16).to_le_bytes());
+                body.extend_from_slice(error_body.as_bytes());
+            }
+        }
+    }
+    body
+}
+
+fn transmit_chunks<U: Uart>(uart: &U, body: &[u8]) -> Result<(), TXError> {
+    for chunk in body.chunks(CHUNK_SIZE) {
+        uart.write_bytes(chunk);
+        let ack = receive_ack(uart);
+        if ack.is_err() { return Err(TXError::RXError(ack.unwrap_err())); }
+    }
+    Ok(())
+}
@@ -0,0 +1,63 @@
+//! Peripherals the decoder logic runs on
+//! The firmware implements these over the MAX78000 HAL,
+//! and the host simulator implements them in memory
+//!
+//! Signatures follow the HAL so the firmware wrappers stay thin.
+
+use decoder_types::{AesBlock, AesKey};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FlashError {
+    InvalidAddress,
+    ReadFailed,
+    WriteFailed,
+    EraseFailed
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AesError {
+    BlockFailed
+}
+
+// Flash pages the decoder owns, each placed by the linker on the device
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FlashRegion {
+    Subscriptions,
+    Lockout,
+    Secrets
+}
+
+pub const FLASH_PAGE_SIZE: usize = 0x2000;
+
+// RAM the firmware gives its heap, which every allocation of the decoder logic comes from
+pub const HEAP_SIZE: usize = 8192;
+
+pub trait Flash {
+    // Address of the first byte of `region`, always page aligned
+    fn region_address(&self, region: FlashRegion) -> u32;
+    fn read_32(&self, address: u32) -> Result<u32, FlashError>;
+    fn read_bytes<const N: usize>(&self, address: u32) -> Result<[u8; N], FlashError>;
+    // Erases the whole page holding `address`
+    fn erase_page(&self, address: u32) -> Result<(), FlashError>;
+    // Writes can only clear bits, so anything but erased flash needs an erase first
+    fn write_u32_slice(&self, address: u32, data: &[u32]) -> Result<(), FlashError>;
+}
+
+// The AES engine works on byte reversed keys and blocks, see sys::decrypt
+pub trait Aes {
+    fn set_key(&self, key: &AesKey);
+    // Overwrites the key register so no key outlives the command that loaded it
+    fn clear_key(&self);
+    fn decrypt_block(&self, block: AesBlock) -> Result<AesBlock, AesError>;
+}
+
+// Blocking, a read only returns once the buffer is full
+pub trait Uart {
+    fn read_bytes(&self, buffer: &mut [u8]);
+    fn write_bytes(&self, bytes: &[u8]);
+}
+
+pub trait Delay {
+    fn delay_us(&mut self, us: u32);
+    fn delay_ms(&mut self, ms: u32);
+}
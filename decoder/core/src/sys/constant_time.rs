@@ -0,0 +1,52 @@
+//! Branchless comparisons and selection for lookups over flash records
+//!
+//! Conditions are carried as masks, all ones for true and zero for false, and
+//! values are picked with them instead of with a branch. A lookup built from
+//! these does the same work whichever record matches, or whether any does.
+//! Operands pass through `black_box` so the compiler does not turn a mask back
+//! into a branch.
+
+use core::hint::black_box;
+
+pub fn bool_mask(condition: bool) -> u64 {
+    (black_box(condition) as u64).wrapping_neg()
+}
+
+pub fn eq_mask_u32(a: u32, b: u32) -> u64 {
+    let diff = black_box(a ^ b) as u64;
+    // Bit 63 of diff | -diff is set exactly when diff is not zero
+    ((diff | diff.wrapping_neg()) >> 63).wrapping_sub(1)
+}
+
+// All ones when a < b
+pub fn lt_mask_u64(a: u64, b: u64) -> u64 {
+    let (a, b) = (black_box(a), black_box(b));
+    // Bit 63 is the borrow out of a - b
+    ((a ^ ((a ^ b) | (a.wrapping_sub(b) ^ b))) >> 63).wrapping_neg()
+}
+
+// All ones when a <= b
+pub fn le_mask_u64(a: u64, b: u64) -> u64 {
+    !lt_mask_u64(b, a)
+}
+
+// `a` where mask is set, `b` elsewhere
+pub fn select_u64(mask: u64, a: u64, b: u64) -> u64 {
+    (a & mask) | (b & !mask)
+}
+
+pub fn select_u32(mask: u64, a: u32, b: u32) -> u32 {
+    select_u64(mask, a as u64, b as u64) as u32
+}
+
+pub fn select_bool(mask: u64, a: bool, b: bool) -> bool {
+    select_u64(mask, a as u64, b as u64) != 0
+}
+
+// Overwrites `out` with `a` where mask is set
+pub fn select_bytes<const N: usize>(mask: u64, a: &[u8; N], out: &mut [u8; N]) {
+    let byte_mask = mask as u8;
+    for (out_byte, a_byte) in out.iter_mut().zip(a) {
+        *out_byte = (a_byte & byte_mask) | (*out_byte & !byte_mask);
+    }
+}
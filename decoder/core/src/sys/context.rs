@@ -0,0 +1,185 @@
+//! Per-channel decode contexts
+//! Kept in RAM for each channel decoded since boot, so a channel's key material
+//! is read from flash once and interleaved channel streams are tracked apart.
+//!
+//! Replay protection stays global, see `replay`: a frame is checked against the
+//! frames decoded on every channel, as the eCTF rules require.
+//! The last timestamp in each context is per channel and only kept as a statistic.
+//!
+//! A secret only leaves the cache as a `Zeroizing` copy. Its slot is wiped when
+//! the channel's subscription is replaced, when a frame finds the subscription
+//! ended or gone, and when the contexts are reset. An evicted context's secret is
+//! overwritten in place by the incoming channel's.
+//! A subscription that ends with no further frame on its channel keeps its secret
+//! cached until one of those happens. Free channels stay cached for the whole uptime.
+//!
+//! Lookups and updates read every slot and pick with masks (see `constant_time`),
+//! so their timing does not tell which slot holds a channel or which one is reused.
+
+use core::ptr::addr_of_mut;
+
+use zeroize::{Zeroize, Zeroizing};
+
+use decoder_types::SUBSCRIPTIONS_CAPACITY;
+
+use crate::platform::Flash;
+
+use super::constant_time::{bool_mask, eq_mask_u32, lt_mask_u64, select_bool, select_bytes, select_u32, select_u64};
+use super::secure_memory::{retrieve_channel_secret, Secret, SecretType, SecureMemoryError};
+
+// Every channel that can be subscribed to, plus the emergency channel
+pub const CONTEXT_CAPACITY: usize = SUBSCRIPTIONS_CAPACITY + 1;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChannelStats {
+    pub decoded: u32,
+    // Frames that reached this channel's key and failed to authenticate
+    pub authentication_failures: u32,
+    pub last_timestamp: Option<u64>
+}
+
+struct ChannelContext {
+    occupied: bool,
+    channel_id: u32,
+    secret: Secret,
+    stats: ChannelStats,
+    last_used: u32
+}
+
+const NO_STATS: ChannelStats = ChannelStats { decoded: 0, authentication_failures: 0, last_timestamp: None };
+const NO_CONTEXT: ChannelContext = ChannelContext { occupied: false, channel_id: 0, secret: Secret::EMPTY, stats: NO_STATS, last_used: 0 };
+
+// Wipes the secret and frees the slot
+impl Zeroize for ChannelContext {
+    fn zeroize(&mut self) {
+        self.secret.zeroize();
+        self.occupied = false;
+        self.channel_id = 0;
+        self.stats = NO_STATS;
+        self.last_used = 0;
+    }
+}
+
+static mut CONTEXTS: [ChannelContext; CONTEXT_CAPACITY] = [NO_CONTEXT; CONTEXT_CAPACITY];
+static mut USES: u32 = 0;
+
+// Returns the channel's secret from its context, reading it from flash the first time
+// When every context is taken, the least recently used one is dropped
+// Every slot is read and weighed the same way, so only whether the channel had a
+// context, and so whether flash is read, takes a different path
+pub fn channel_secret<F: Flash>(flc: &F, channel_id: u32) -> Result<Zeroizing<Secret>, SecureMemoryError> {
+    let uses = unsafe {
+        USES = USES.wrapping_add(1);
+        USES
+    };
+    let mut found = 0u64;
+    let mut secret: Zeroizing<Secret> = Zeroizing::new(Secret { secret_type: SecretType::Channel(channel_id), valid: false, aes_key: [0; 32], aes_iv: [0; 16] });
+    // First free slot, and slot and age of the least recently used context
+    let mut free = 0u64;
+    let mut free_slot = 0u32;
+    let mut oldest_slot = 0u32;
+    let mut oldest_age = 0u64;
+    for i in 0..CONTEXT_CAPACITY {
+        let context = context_slot(i);
+        let occupied = bool_mask(context.occupied);
+        let matches = occupied & eq_mask_u32(context.channel_id, channel_id);
+        found |= matches;
+        secret.valid = select_bool(matches, context.secret.valid, secret.valid);
+        select_bytes(matches, &context.secret.aes_key, &mut secret.aes_key);
+        select_bytes(matches, &context.secret.aes_iv, &mut secret.aes_iv);
+        context.last_used = select_u32(matches, uses, context.last_used);
+
+        let first_free = !occupied & !free;
+        free_slot = select_u32(first_free, i as u32, free_slot);
+        free |= !occupied;
+        let age = uses.wrapping_sub(context.last_used) as u64;
+        let older = occupied & lt_mask_u64(oldest_age, age);
+        oldest_slot = select_u32(older, i as u32, oldest_slot);
+        oldest_age = select_u64(older, age, oldest_age);
+    }
+    if found != 0 { return Ok(secret); }
+
+    let secret = retrieve_channel_secret(flc, channel_id)?;
+    let slot = select_u32(free, free_slot, oldest_slot);
+    for i in 0..CONTEXT_CAPACITY {
+        let context = context_slot(i);
+        let chosen = eq_mask_u32(i as u32, slot);
+        context.occupied = select_bool(chosen, true, context.occupied);
+        context.channel_id = select_u32(chosen, channel_id, context.channel_id);
+        context.secret.secret_type = SecretType::Channel(context.channel_id);
+        context.secret.valid = select_bool(chosen, secret.valid, context.secret.valid);
+        select_bytes(chosen, &secret.aes_key, &mut context.secret.aes_key);
+        select_bytes(chosen, &secret.aes_iv, &mut context.secret.aes_iv);
+        select_stats(chosen, &NO_STATS, &mut context.stats);
+        context.last_used = select_u32(chosen, uses, context.last_used);
+    }
+    Ok(secret)
+}
+
+pub fn record_decoded(channel_id: u32, timestamp: u64) -> () {
+    update_stats(channel_id, |stats| {
+        stats.decoded = stats.decoded.saturating_add(1);
+        stats.last_timestamp = Some(timestamp);
+    });
+}
+
+pub fn record_authentication_failure(channel_id: u32) -> () {
+    update_stats(channel_id, |stats| stats.authentication_failures = stats.authentication_failures.saturating_add(1));
+}
+
+// None if the channel has no context, because it was never decoded or was dropped
+pub fn channel_stats(channel_id: u32) -> Option<ChannelStats> {
+    for i in 0..CONTEXT_CAPACITY {
+        let context = context_slot(i);
+        if context.occupied && context.channel_id == channel_id { return Some(context.stats); }
+    }
+    None
+}
+
+// Wipes the channel's context, if it has one, without telling which slot held it
+pub fn forget_channel(channel_id: u32) -> () {
+    for i in 0..CONTEXT_CAPACITY {
+        let context = context_slot(i);
+        let matches = bool_mask(context.occupied) & eq_mask_u32(context.channel_id, channel_id);
+        context.occupied = select_bool(matches, false, context.occupied);
+        context.channel_id = select_u32(matches, 0, context.channel_id);
+        context.secret.secret_type = SecretType::Channel(context.channel_id);
+        context.secret.valid = select_bool(matches, false, context.secret.valid);
+        select_bytes(matches, &[0; 32], &mut context.secret.aes_key);
+        select_bytes(matches, &[0; 16], &mut context.secret.aes_iv);
+        select_stats(matches, &NO_STATS, &mut context.stats);
+        context.last_used = select_u32(matches, 0, context.last_used);
+    }
+}
+
+// Wipes every cached secret, not only the slot markers
+pub fn reset_contexts() -> () {
+    for i in 0..CONTEXT_CAPACITY {
+        context_slot(i).zeroize();
+    }
+}
+
+// Every slot's stats are updated and only the matching one's kept, like the secret lookup
+fn update_stats(channel_id: u32, update: impl Fn(&mut ChannelStats)) {
+    for i in 0..CONTEXT_CAPACITY {
+        let context = context_slot(i);
+        let matches = bool_mask(context.occupied) & eq_mask_u32(context.channel_id, channel_id);
+        let mut updated = context.stats;
+        update(&mut updated);
+        select_stats(matches, &updated, &mut context.stats);
+    }
+}
+
+// Overwrites `out` with `a` where mask is set
+fn select_stats(mask: u64, a: &ChannelStats, out: &mut ChannelStats) {
+    out.decoded = select_u32(mask, a.decoded, out.decoded);
+    out.authentication_failures = select_u32(mask, a.authentication_failures, out.authentication_failures);
+    let has_timestamp = select_bool(mask, a.last_timestamp.is_some(), out.last_timestamp.is_some());
+    let timestamp = select_u64(mask, a.last_timestamp.unwrap_or(0), out.last_timestamp.unwrap_or(0));
+    out.last_timestamp = has_timestamp.then_some(timestamp);
+}
+
+// Contexts are worked on in place, copying one out would leave its secret behind
+fn context_slot(i: usize) -> &'static mut ChannelContext {
+    unsafe { &mut *addr_of_mut!(CONTEXTS[i]) }
+}
@@ -0,0 +1,100 @@
+//! Decryption functions
+//! Key copies and decrypted buffers are wrapped in `Zeroizing`, so they are wiped when dropped
+
+use core::iter::zip;
+
+extern crate alloc;
+use alloc::vec::Vec;
+
+use zeroize::Zeroizing;
+
+use decoder_types::AesBlock;
+
+use crate::platform::{Aes, AesError, Flash};
+
+use crate::message::packet::PacketError;
+use crate::message::packet::extract_decoder_id;
+
+use super::secure_memory::{Secret, SecureMemoryError};
+use super::secure_memory::{retrieve_master_secret, retrieve_channel_secret};
+
+#[derive(Debug, Clone, Copy)]
+#[allow(unused)]
+pub enum DecryptError {
+    InvalidSecretChannel(u32),
+    PacketError(PacketError),
+    AesError(AesError),
+    SecureMemoryError(SecureMemoryError)
+}
+
+pub fn decrypt_message<F: Flash, A: Aes>(flc: &F, aes: &A, message: Vec<AesBlock>) -> Result<Zeroizing<Vec<AesBlock>>, DecryptError> {
+    let secret = retrieve_master_secret(flc);
+    if secret.is_err() { return Err(DecryptError::SecureMemoryError(secret.unwrap_err())); }
+    decrypt_blocks(aes, &secret.unwrap(), message)
+}
+
+pub fn decrypt_decoder_id<F: Flash, A: Aes>(flc: &F, aes: &A, channel_id: u32, block: AesBlock) -> Result<u32, DecryptError> {
+    let secret = retrieve_channel_secret(flc, channel_id);
+    if secret.is_err() { return Err(DecryptError::SecureMemoryError(secret.unwrap_err())); }
+    let secret = secret.unwrap();
+    let decoded_block = decrypt_block(aes, &secret, block)?;
+    let decoder_id = extract_decoder_id(*decoded_block);
+    if decoder_id.is_err() { return Err(DecryptError::PacketError(decoder_id.unwrap_err())); }
+    Ok(decoder_id.unwrap())
+}
+
+// Frame layers take the channel secret from the channel's decode context
+pub fn decrypt_company_stamp<A: Aes>(aes: &A, secret: &Secret, block: AesBlock) -> Result<AesBlock, DecryptError> {
+    Ok(*decrypt_block(aes, secret, block)?)
+}
+
+pub fn decrypt_frame<A: Aes>(aes: &A, secret: &Secret, blocks: Vec<AesBlock>) -> Result<Zeroizing<Vec<u8>>, DecryptError> {
+    let mut decrypted_blocks = decrypt_blocks(aes, secret, blocks)?;
+    decrypted_blocks.remove(0);
+    // Sized up front, a reallocation would free a copy of the frame without wiping it
+    let mut decrypted_frame: Zeroizing<Vec<u8>> = Zeroizing::new(Vec::with_capacity(decrypted_blocks.len() * 16));
+    for block in decrypted_blocks.iter() {
+        decrypted_frame.extend_from_slice(block);
+    }
+    Ok(decrypted_frame)
+}
+
+fn decrypt_blocks<A: Aes>(aes: &A, secret: &Secret, blocks: Vec<AesBlock>) -> Result<Zeroizing<Vec<AesBlock>>, DecryptError> {
+    let mut key = Zeroizing::new(secret.aes_key);
+    key.reverse();
+    aes.set_key(&key);
+    let mut decrypted_blocks: Zeroizing<Vec<AesBlock>> = Zeroizing::new(Vec::with_capacity(blocks.len()));
+    let mut cbc = Zeroizing::new(secret.aes_iv);
+    for mut block in blocks {
+        block.reverse();
+        let aes_out = aes.decrypt_block(block);
+        if aes_out.is_err() { return Err(DecryptError::AesError(aes_out.unwrap_err())); }
+        let mut aes_out = Zeroizing::new(aes_out.unwrap());
+        aes_out.reverse();
+        decrypted_blocks.push(*xor_blocks(&aes_out, &cbc));
+        block.reverse();
+        *cbc = block;
+    }
+    Ok(decrypted_blocks)
+}
+
+fn decrypt_block<A: Aes>(aes: &A, secret: &Secret, block: AesBlock) -> Result<Zeroizing<AesBlock>, DecryptError> {
+    let mut key = Zeroizing::new(secret.aes_key);
+    key.reverse();
+    aes.set_key(&key);
+    let mut block = block;
+    block.reverse();
+    let aes_out = aes.decrypt_block(block);
+    if aes_out.is_err() { return Err(DecryptError::AesError(aes_out.unwrap_err())); }
+    let mut aes_out = Zeroizing::new(aes_out.unwrap());
+    aes_out.reverse();
+    Ok(xor_blocks(&aes_out, &secret.aes_iv))
+}
+
+fn xor_blocks(a: &AesBlock, b: &AesBlock) -> Zeroizing<AesBlock> {
+    let mut out: Zeroizing<AesBlock> = Zeroizing::new([0; 16]);
+    for (out_byte, (a_byte, b_byte)) in zip(out.iter_mut(), zip(a, b)) {
+        *out_byte = a_byte ^ b_byte;
+    }
+    out
+}
\ No newline at end of file
@@ -0,0 +1,36 @@
+//! Free channels, decoded without a subscription
+//!
+//! The list is chosen at build time and always holds channel 0, the emergency
+//! broadcast channel. Each free channel carries the priority its frames are
+//! returned with, so the host can surface emergency frames differently.
+//! Subscription updates for a free channel are refused, as they would never be used.
+//!
+//! Lookups scan the whole list whichever entry matches, so their timing does not
+//! tell a free channel from any other.
+
+pub use decoder_types::protocol::FramePriority;
+
+use super::constant_time::{eq_mask_u32, select_u32};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FreeChannel {
+    pub channel_id: u32,
+    pub priority: FramePriority
+}
+
+// Mask of whether `channel_id` is free, and the priority of its frames
+pub fn free_channel_mask(free_channels: &[FreeChannel], channel_id: u32) -> (u64, FramePriority) {
+    let mut free = 0u64;
+    let mut priority = FramePriority::Normal.to_byte() as u32;
+    for channel in free_channels {
+        let matches = eq_mask_u32(channel.channel_id, channel_id);
+        free |= matches;
+        priority = select_u32(matches, channel.priority.to_byte() as u32, priority);
+    }
+    (free, FramePriority::from_byte(priority as u8).unwrap_or(FramePriority::Normal))
+}
+
+// Inverse of free_channel_mask for hardened checks, compared along a different path
+pub fn channel_not_free(free_channels: &[FreeChannel], channel_id: u32) -> bool {
+    free_channels.iter().all(|channel| (channel.channel_id ^ channel_id) != 0)
+}
@@ -0,0 +1,89 @@
+//! Glitch-resistant checks for security decisions
+//!
+//! Each condition is evaluated twice along different code paths, with a random
+//! delay in between so a glitch cannot be timed to hit both. Results are carried
+//! as complementary bit patterns instead of 0/1, so a skipped or zeroed
+//! computation never reads as true. Any disagreement traps into `glitch_detected`.
+//! Callers keep each result and `confirm` it again before acting on the decision.
+
+use core::hint::black_box;
+
+use rand_core::RngCore;
+
+const SECURE_TRUE: u32 = 0x5A3C_96A5;
+const SECURE_FALSE: u32 = !SECURE_TRUE;
+
+// Upper bound of busy loop iterations between the two evaluations
+const JITTER_MASK: u32 = 0x3F;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SecureBool(u32);
+
+impl SecureBool {
+    // Branchless so the encoding itself has no single branch to skip
+    fn encode(condition: bool) -> Self {
+        let mask = (condition as u32).wrapping_neg();
+        SecureBool((SECURE_TRUE & mask) | (SECURE_FALSE & !mask))
+    }
+
+    // Anything but the exact true pattern counts as false
+    pub fn is_true(self) -> bool {
+        black_box(self.0) == SECURE_TRUE
+    }
+}
+
+// Evaluates `condition`, waits a random time, then evaluates `inverse`,
+// which must compute the opposite of `condition` along a different code path
+pub fn check<R: RngCore>(rng: &mut R, condition: impl FnOnce() -> bool, inverse: impl FnOnce() -> bool) -> SecureBool {
+    let first = SecureBool::encode(black_box(condition()));
+    jitter(rng);
+    let second = SecureBool::encode(black_box(inverse()));
+    // Both paths agree only if the encodings are exact complements
+    if black_box(first.0 ^ second.0) != u32::MAX { glitch_detected(); }
+    if first.0 != SECURE_TRUE && first.0 != SECURE_FALSE { glitch_detected(); }
+    first
+}
+
+// Re-tests checks that already passed, right before acting on them, so a glitch
+// that skipped an error branch still traps here. Counting the passes means
+// skipping the loop itself does not pass either
+pub fn confirm(checks: &[SecureBool]) {
+    let mut passed = 0;
+    for check in checks {
+        if check.is_true() { passed += 1; }
+    }
+    if black_box(passed) != checks.len() { glitch_detected(); }
+}
+
+pub fn check_eq_u32<R: RngCore>(rng: &mut R, a: u32, b: u32) -> SecureBool {
+    check(rng, || black_box(a) == black_box(b), || (black_box(a) ^ black_box(b)) != 0)
+}
+
+pub fn check_le_u64<R: RngCore>(rng: &mut R, a: u64, b: u64) -> SecureBool {
+    check(rng, || black_box(a) <= black_box(b), || black_box(b).checked_sub(black_box(a)).is_none())
+}
+
+// Fault handler for disagreeing checks
+// Halts with interrupts off, so a glitch attempt costs a power cycle
+#[cfg(target_os = "none")]
+#[inline(never)]
+pub fn glitch_detected() -> ! {
+    cortex_m::interrupt::disable();
+    loop {
+        cortex_m::asm::nop();
+    }
+}
+
+// Off the device a disagreement can only be a bug, so fail loudly
+#[cfg(not(target_os = "none"))]
+#[inline(never)]
+pub fn glitch_detected() -> ! {
+    panic!("Hardened check paths disagreed");
+}
+
+fn jitter<R: RngCore>(rng: &mut R) {
+    let iterations = rng.next_u32() & JITTER_MASK;
+    for i in 0..iterations {
+        black_box(i);
+    }
+}
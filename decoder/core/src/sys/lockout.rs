@@ -0,0 +1,92 @@
+//! Authentication failure counter and lockout policy
+//!
+//! Every authentication failure burns one 128-bit line of the lockout flash page,
+//! so recording a failure never needs a page erase and survives reset.
+//! The counter is mirrored in RAM and only cleared by an authenticated unlock,
+//! which erases the page and bumps the unlock epoch so unlock messages cannot be replayed.
+
+use crate::platform::{Delay, Flash, FlashRegion};
+
+use super::secure_memory::SecureMemoryError;
+
+// Line 0 holds the unlock epoch, every other line is one failure mark
+pub const LOCKOUT_PAGE_WORDS: usize = 2048;
+const LOCKOUT_LINE_WORDS: usize = 4;
+const LOCKOUT_LINES: usize = LOCKOUT_PAGE_WORDS / LOCKOUT_LINE_WORDS;
+
+// Failures allowed before any lockout delay, to tolerate the odd corrupted transfer
+const LOCKOUT_FREE_FAILURES: u32 = 4;
+const LOCKOUT_BASE_MS: u32 = 250;
+const LOCKOUT_MAX_MS: u32 = 32_000;
+
+// Contents of the lockout page as flashed, at epoch 0 with no failures
+pub const fn blank_lockout_page() -> [u32; LOCKOUT_PAGE_WORDS] {
+    let mut page = [u32::MAX; LOCKOUT_PAGE_WORDS];
+    page[0] = 0;
+    page
+}
+
+static mut FAILURES: u32 = 0;
+
+// Delay doubles with every failure past the free ones, up to the maximum
+pub fn lockout_delay_ms(failures: u32) -> u32 {
+    if failures <= LOCKOUT_FREE_FAILURES { return 0; }
+    let doublings = core::cmp::min(failures - LOCKOUT_FREE_FAILURES - 1, 16);
+    core::cmp::min(LOCKOUT_BASE_MS << doublings, LOCKOUT_MAX_MS)
+}
+
+pub fn init_failures<F: Flash>(flc: &F) -> Result<u32, SecureMemoryError> {
+    let mut failures: u32 = 0;
+    for line in 1..LOCKOUT_LINES {
+        let mark = flc.read_32(line_address(flc, line));
+        if mark.is_err() { return Err(SecureMemoryError::FlashError(mark.unwrap_err())); }
+        if mark.unwrap() != u32::MAX { failures += 1; }
+    }
+    unsafe { FAILURES = failures; }
+    Ok(failures)
+}
+
+pub fn failures() -> u32 {
+    unsafe { FAILURES }
+}
+
+pub fn record_failure<F: Flash>(flc: &F) -> Result<u32, SecureMemoryError> {
+    // Count in RAM first so a flash error cannot hide the failure
+    let failures = unsafe {
+        FAILURES = FAILURES.saturating_add(1);
+        FAILURES
+    };
+    // Once every line is burnt the delay is long since at its maximum
+    if failures as usize >= LOCKOUT_LINES { return Ok(failures); }
+    match flc.write_u32_slice(line_address(flc, failures as usize), &[0u32; LOCKOUT_LINE_WORDS]) {
+        Ok(()) => Ok(failures),
+        Err(flash_error) => Err(SecureMemoryError::FlashError(flash_error))
+    }
+}
+
+// Records the failure before sleeping, so a reset cannot skip either
+pub fn lockout_failure<F: Flash, D: Delay>(flc: &F, delay: &mut D) {
+    let _ = record_failure(flc);
+    delay.delay_ms(lockout_delay_ms(failures()));
+}
+
+pub fn unlock_epoch<F: Flash>(flc: &F) -> Result<u32, SecureMemoryError> {
+    let epoch = flc.read_32(line_address(flc, 0));
+    if epoch.is_err() { return Err(SecureMemoryError::FlashError(epoch.unwrap_err())); }
+    Ok(epoch.unwrap())
+}
+
+pub fn reset_failures<F: Flash>(flc: &F) -> Result<(), SecureMemoryError> {
+    let epoch = unlock_epoch(flc)?;
+    let ret = flc.erase_page(line_address(flc, 0));
+    if ret.is_err() { return Err(SecureMemoryError::FlashError(ret.unwrap_err())); }
+    unsafe { FAILURES = 0; }
+    match flc.write_u32_slice(line_address(flc, 0), &[epoch.wrapping_add(1), 0, 0, 0]) {
+        Ok(()) => Ok(()),
+        Err(flash_error) => Err(SecureMemoryError::FlashError(flash_error))
+    }
+}
+
+fn line_address<F: Flash>(flc: &F, line: usize) -> u32 {
+    flc.region_address(FlashRegion::Lockout) + (line * LOCKOUT_LINE_WORDS * size_of::<u32>()) as u32
+}
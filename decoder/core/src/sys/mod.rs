@@ -0,0 +1,23 @@
+//! Functions pertaining to operating the broad microcontroller system
+//! Contains:
+//! - Flash memory interface
+//! - Constant-time selection for lookups
+//! - Free channels
+//! - Replay protection
+//! - Per-channel decode contexts
+//! - Decryption
+//! - Subscription signature verification
+//! - Random number generation and delays
+//! - Lockout
+//! - Glitch hardening
+
+pub mod secure_memory;
+pub mod constant_time;
+pub mod free_channels;
+pub mod replay;
+pub mod context;
+pub mod decrypt;
+pub mod signature;
+pub mod rng;
+pub mod lockout;
+pub mod hardened;
@@ -0,0 +1,165 @@
+//! Replay protection for frames
+//!
+//! The newest accepted timestamp is kept with a bitmap of the ones accepted just
+//! below it. A frame is accepted if it is newer, or if it falls within the policy's
+//! window below the newest and has not been seen. The strict policy has no window,
+//! so every frame must be newer than the last, as the eCTF rules require.
+//! A policy can also bound how far ahead of the newest a frame may be, so one frame
+//! with a far future timestamp cannot lock every later frame out.
+//!
+//! A signed resync from the host moves the newest timestamp, forwards or back.
+//! Its sequence must grow, so a captured resync cannot be replayed to rewind the
+//! clock again. The state is RAM only and starts over on every boot, before which
+//! any timestamp is accepted.
+
+// Bits in the seen bitmap, the widest window a policy can have
+pub const REPLAY_WINDOW_MAX: u32 = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReplayPolicy {
+    // Timestamps below the newest that are still accepted once, at most REPLAY_WINDOW_MAX
+    pub window: u32,
+    // Furthest a frame may be ahead of the newest, None for no bound
+    pub max_ahead: Option<u64>
+}
+
+impl ReplayPolicy {
+    pub const STRICT: ReplayPolicy = ReplayPolicy { window: 0, max_ahead: None };
+}
+
+// We need an unintialized state because time 0 must be
+// a valid packet on boot, however we cannot tell between
+// already receiving time 0 packet and not receiving any
+// packet yet with just a u64. If we chose to save the
+// next valid timestamp instead of the last received,
+// we would still need another state for after receiving
+// a packet with timestamp U64_MAX.
+#[derive(Debug, Clone, Copy)]
+enum Timestamp {
+    Uninitialized,
+    CurrentTime(u64)
+}
+
+static mut TIMESTAMP: Timestamp = Timestamp::Uninitialized;
+// Bit i is set once newest - i has been accepted
+static mut SEEN: u64 = 0;
+static mut POLICY: ReplayPolicy = ReplayPolicy::STRICT;
+static mut RESYNC_SEQUENCE: Option<u64> = None;
+
+// Not a replay: newer than the newest, or unseen within the window below it
+pub fn verify_timestamp(frame_timestamp: u64) -> bool {
+    unsafe {
+        match TIMESTAMP {
+            Timestamp::Uninitialized => true,
+            Timestamp::CurrentTime(current_time) => {
+                if current_time < frame_timestamp { return true; }
+                let behind = current_time - frame_timestamp;
+                behind < POLICY.window as u64 && (SEEN >> behind) & 1 == 0
+            }
+        }
+    }
+}
+
+// Inverse of verify_timestamp along a different path, for hardened checks
+pub fn timestamp_stale(frame_timestamp: u64) -> bool {
+    unsafe {
+        match TIMESTAMP {
+            Timestamp::Uninitialized => false,
+            Timestamp::CurrentTime(current_time) => match current_time.checked_sub(frame_timestamp) {
+                Some(behind) => behind >= POLICY.window as u64 || SEEN & (1u64 << behind) != 0,
+                None => false
+            }
+        }
+    }
+}
+
+pub fn verify_timestamp_ahead(frame_timestamp: u64) -> bool {
+    unsafe {
+        match (TIMESTAMP, POLICY.max_ahead) {
+            (Timestamp::CurrentTime(current_time), Some(max_ahead)) => frame_timestamp <= current_time.saturating_add(max_ahead),
+            _ => true
+        }
+    }
+}
+
+// Inverse of verify_timestamp_ahead along a different path, for hardened checks
+pub fn timestamp_too_far_ahead(frame_timestamp: u64) -> bool {
+    unsafe {
+        match (TIMESTAMP, POLICY.max_ahead) {
+            (Timestamp::CurrentTime(current_time), Some(max_ahead)) => match frame_timestamp.checked_sub(current_time) {
+                Some(ahead) => max_ahead.checked_sub(ahead).is_none(),
+                None => false
+            },
+            _ => false
+        }
+    }
+}
+
+// Marks a verified frame's timestamp as seen, sliding the window up if it is the newest
+pub fn set_timestamp(timestamp: u64) -> () {
+    unsafe {
+        match TIMESTAMP {
+            Timestamp::CurrentTime(current_time) if timestamp <= current_time => {
+                let behind = current_time - timestamp;
+                if behind < REPLAY_WINDOW_MAX as u64 { SEEN |= 1u64 << behind; }
+            },
+            Timestamp::CurrentTime(current_time) => {
+                let ahead = timestamp - current_time;
+                SEEN = if ahead < REPLAY_WINDOW_MAX as u64 { (SEEN << ahead) | 1 } else { 1 };
+                TIMESTAMP = Timestamp::CurrentTime(timestamp);
+            },
+            Timestamp::Uninitialized => {
+                SEEN = 1;
+                TIMESTAMP = Timestamp::CurrentTime(timestamp);
+            }
+        }
+    }
+}
+
+// Every timestamp at or below this one is rejected, None before the first frame
+pub fn replay_floor() -> Option<u64> {
+    unsafe {
+        match TIMESTAMP {
+            Timestamp::Uninitialized => None,
+            Timestamp::CurrentTime(current_time) => current_time.checked_sub(POLICY.window as u64)
+        }
+    }
+}
+
+pub fn verify_resync_sequence(sequence: u64) -> bool {
+    unsafe {
+        match RESYNC_SEQUENCE {
+            Some(last_sequence) => last_sequence < sequence,
+            None => true
+        }
+    }
+}
+
+// Inverse of verify_resync_sequence along a different path, for hardened checks
+pub fn resync_sequence_stale(sequence: u64) -> bool {
+    unsafe {
+        match RESYNC_SEQUENCE {
+            Some(last_sequence) => last_sequence.checked_sub(sequence).is_some(),
+            None => false
+        }
+    }
+}
+
+// Only frames newer than `timestamp` are accepted afterwards, whatever was seen before
+pub fn resync_timestamp(timestamp: u64, sequence: u64) -> () {
+    unsafe {
+        TIMESTAMP = Timestamp::CurrentTime(timestamp);
+        SEEN = u64::MAX;
+        RESYNC_SEQUENCE = Some(sequence);
+    }
+}
+
+// Starts over before the first frame, with the window clamped to the bitmap
+pub fn reset_timestamp(policy: ReplayPolicy) -> () {
+    unsafe {
+        TIMESTAMP = Timestamp::Uninitialized;
+        SEEN = 0;
+        POLICY = ReplayPolicy { window: core::cmp::min(policy.window, REPLAY_WINDOW_MAX), max_ahead: policy.max_ahead };
+        RESYNC_SEQUENCE = None;
+    }
+}
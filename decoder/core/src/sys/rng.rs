@@ -0,0 +1,215 @@
+//! Random number generator
+//! For use by entire system
+//! Uses the ChaCha20 RNG
+//! Seeded by trng with a full 256-bit seed, and reseeded after every
+//! `RESEED_INTERVAL` outputs
+//! Raw trng output is health tested before use (NIST SP 800-90B 4.4)
+//!
+//! Also holds the random delay policy types, the firmware picks a policy
+//! for each command type at build time through the `DELAY_*` environment variables
+
+use rand_chacha::ChaCha20Rng;
+use rand_core::{RngCore, SeedableRng};
+
+use crate::platform::Delay;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[allow(unused)]
+pub enum DelayPolicy {
+    None,
+    Uniform { min_us: u32, max_us: u32 },
+    Exponential { mean_us: u32, max_us: u32 }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[allow(unused)]
+pub enum DelayTrigger {
+    Always,
+    AuthenticationFailure
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DelayConfig {
+    pub policy: DelayPolicy,
+    pub trigger: DelayTrigger
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DelayConfigs {
+    pub list: DelayConfig,
+    pub update: DelayConfig,
+    pub decode: DelayConfig,
+    pub receive: DelayConfig
+}
+
+// ln(2) in 16.16 fixed point
+const LN_2_FIXED: u64 = 45426;
+
+// Outputs drawn from ChaCha20 before pulling a fresh seed from the trng
+const RESEED_INTERVAL: u32 = 256;
+
+// Health test cutoffs for byte samples, assuming 4 bits of min-entropy per byte
+// and a false positive rate of 2^-20 (NIST SP 800-90B 4.4.1 and 4.4.2)
+const REPETITION_COUNT_CUTOFF: u32 = 6;
+const ADAPTIVE_PROPORTION_WINDOW: u32 = 512;
+const ADAPTIVE_PROPORTION_CUTOFF: u32 = 62;
+
+// Samples run through the health tests on startup before any are used
+const STARTUP_TEST_WORDS: usize = 256;
+
+#[derive(Debug, Clone, Copy)]
+#[allow(unused)]
+pub enum RngError {
+    RepetitionCountFailure(u8),
+    AdaptiveProportionFailure(u8)
+}
+
+// Source of raw entropy, so host tests can inject a deterministic one
+pub trait EntropySource {
+    fn gen_u32(&mut self) -> u32;
+}
+
+#[derive(Debug, Clone, Copy)]
+struct HealthTests {
+    repetition_sample: u8,
+    repetition_count: u32,
+    proportion_sample: u8,
+    proportion_count: u32,
+    proportion_index: u32
+}
+
+impl HealthTests {
+    fn new() -> Self {
+        HealthTests {
+            repetition_sample: 0,
+            repetition_count: 0,
+            proportion_sample: 0,
+            proportion_count: 0,
+            proportion_index: ADAPTIVE_PROPORTION_WINDOW
+        }
+    }
+
+    fn test_sample(&mut self, sample: u8) -> Result<(), RngError> {
+        // Repetition count test: too many identical samples in a row
+        if self.repetition_count > 0 && sample == self.repetition_sample {
+            self.repetition_count += 1;
+            if self.repetition_count >= REPETITION_COUNT_CUTOFF { return Err(RngError::RepetitionCountFailure(sample)); }
+        } else {
+            self.repetition_sample = sample;
+            self.repetition_count = 1;
+        }
+        // Adaptive proportion test: first sample of a window repeating too often within it
+        if self.proportion_index >= ADAPTIVE_PROPORTION_WINDOW {
+            self.proportion_sample = sample;
+            self.proportion_count = 1;
+            self.proportion_index = 1;
+        } else {
+            self.proportion_index += 1;
+            if sample == self.proportion_sample {
+                self.proportion_count += 1;
+                if self.proportion_count >= ADAPTIVE_PROPORTION_CUTOFF { return Err(RngError::AdaptiveProportionFailure(sample)); }
+            }
+        }
+        Ok(())
+    }
+}
+
+pub struct ReseedingRng<E: EntropySource> {
+    source: E,
+    health: HealthTests,
+    rng: ChaCha20Rng,
+    outputs: u32
+}
+
+impl<E: EntropySource> ReseedingRng<E> {
+    pub fn new(mut source: E) -> Result<Self, RngError> {
+        let mut health = HealthTests::new();
+        for _ in 0..STARTUP_TEST_WORDS {
+            let word = source.gen_u32();
+            for sample in word.to_le_bytes() {
+                health.test_sample(sample)?;
+            }
+        }
+        let seed = draw_seed(&mut source, &mut health)?;
+        Ok(ReseedingRng { source, health, rng: ChaCha20Rng::from_seed(seed), outputs: 0 })
+    }
+
+    pub fn reseed(&mut self) -> Result<(), RngError> {
+        let seed = draw_seed(&mut self.source, &mut self.health)?;
+        self.rng = ChaCha20Rng::from_seed(seed);
+        self.outputs = 0;
+        Ok(())
+    }
+
+    fn count_output(&mut self) {
+        if self.outputs >= RESEED_INTERVAL {
+            // A failing trng means no trustworthy randomness is left, so fail closed
+            self.reseed().expect("Entropy source failed health test");
+        }
+        self.outputs += 1;
+    }
+}
+
+impl<E: EntropySource> RngCore for ReseedingRng<E> {
+    fn next_u32(&mut self) -> u32 {
+        self.count_output();
+        self.rng.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.count_output();
+        self.rng.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.count_output();
+        self.rng.fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand_core::Error> {
+        self.count_output();
+        self.rng.try_fill_bytes(dest)
+    }
+}
+
+fn draw_seed<E: EntropySource>(source: &mut E, health: &mut HealthTests) -> Result<[u8; 32], RngError> {
+    let mut seed: [u8; 32] = [0; 32];
+    for seed_word in seed.chunks_exact_mut(4) {
+        let word = source.gen_u32().to_le_bytes();
+        for sample in word {
+            health.test_sample(sample)?;
+        }
+        seed_word.copy_from_slice(&word);
+    }
+    Ok(seed)
+}
+
+pub fn new_rng<E: EntropySource>(source: E) -> ReseedingRng<E> {
+    // A failing trng means no trustworthy randomness is left, so fail closed
+    ReseedingRng::new(source).expect("Entropy source failed startup health test")
+}
+
+pub fn delay_rand<R: RngCore, D: Delay>(rng: &mut R, delay: &mut D, policy: DelayPolicy) {
+    let time_us = match policy {
+        DelayPolicy::None => { return; },
+        DelayPolicy::Uniform { min_us, max_us } => {
+            if max_us == min_us { min_us } else { rng.next_u32() % (max_us - min_us) + min_us }
+        },
+        DelayPolicy::Exponential { mean_us, max_us } => {
+            core::cmp::min(exponential_us(rng.next_u32(), mean_us), max_us as u64) as u32
+        }
+    };
+    delay.delay_us(time_us);
+}
+
+// Samples an exponential distribution as mean * -ln(rand / 2^32)
+// There is no libm, so log2 is approximated by the leading zero count
+// plus a linear interpolation of the remaining mantissa bits
+fn exponential_us(rand: u32, mean_us: u32) -> u64 {
+    let rand = rand | 1;
+    let leading_zeros = rand.leading_zeros() as u64;
+    let mantissa = ((rand as u64) << (leading_zeros + 1)) & 0xFFFF_FFFF;
+    let log2_fixed = ((31 - leading_zeros) << 16) | (mantissa >> 16);
+    let neg_ln_fixed = (((32u64 << 16) - log2_fixed) * LN_2_FIXED) >> 16;
+    (mean_us as u64 * neg_ln_fixed) >> 16
+}
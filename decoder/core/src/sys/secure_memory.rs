@@ -0,0 +1,327 @@
+//! Flash memory interface
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+
+use zeroize::Zeroizing;
+
+use decoder_types::{RecordError, SecretsHeader, SUBSCRIPTIONS_CAPACITY, SUBSCRIPTION_WINDOWS_CAPACITY, SUBSCRIPTION_RECORD_SIZE, SECRET_RECORD_SIZE};
+use decoder_types::{SEQUENCE_MARKS_CAPACITY, SEQUENCE_MARKS_OFFSET, SEQUENCE_MARK_RECORD_SIZE, SUBSCRIPTIONS_IMAGE_SIZE};
+use decoder_types::{SECRETS_HEADER_SIZE, SECRETS_DECODER_ID_OFFSET};
+
+use crate::platform::{Flash, FlashError, FlashRegion};
+
+use super::replay::replay_floor;
+use super::constant_time::{bool_mask, eq_mask_u32, lt_mask_u64, le_mask_u64, select_u64, select_u32, select_bool, select_bytes};
+
+pub use decoder_types::{Subscription, SequenceMark, SecretType, Secret};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[allow(unused)]
+pub enum SecureMemoryError {
+    InvalidSubscriptionChannel(u32),
+    SubscriptionNotValid(u32),
+    SubscriptionMemoryFull,
+    NoSubscription,
+    NoChannelSecret,
+    NoMasterSecret,
+    RecordError(RecordError),
+    FlashError(FlashError)
+}
+
+// Returns the channel's window holding `timestamp`
+// Failing that, the next window to start after it, else the last one to end before it,
+// so the caller's window checks report why the frame is outside the subscription
+// Constant time: every slot is read and weighed the same way, whatever the channel or where it matches
+pub fn retrieve_subscription<F: Flash>(flc: &F, channel_id: u32, timestamp: u64) -> Result<Subscription, SecureMemoryError> {
+    let mut covering = Subscription::EMPTY;
+    let mut next = Subscription::EMPTY;
+    let mut last = Subscription::EMPTY;
+    let mut covering_found: u64 = 0;
+    let mut next_found: u64 = 0;
+    let mut last_found: u64 = 0;
+    let mut error: Option<SecureMemoryError> = None;
+    for i in 0..SUBSCRIPTION_WINDOWS_CAPACITY {
+        let sub = keep_first_error(read_subscription(flc, i), Subscription::EMPTY, &mut error);
+        let of_channel = bool_mask(sub.valid) & eq_mask_u32(sub.channel_id, channel_id);
+        let covers = of_channel & le_mask_u64(sub.start, timestamp) & le_mask_u64(timestamp, sub.end);
+        let starts_next = of_channel & lt_mask_u64(timestamp, sub.start) & (!next_found | lt_mask_u64(sub.start, next.start));
+        let ends_last = of_channel & lt_mask_u64(sub.end, timestamp) & (!last_found | lt_mask_u64(last.end, sub.end));
+        covering = select_subscription(covers, &sub, &covering);
+        next = select_subscription(starts_next, &sub, &next);
+        last = select_subscription(ends_last, &sub, &last);
+        covering_found |= covers;
+        next_found |= starts_next;
+        last_found |= ends_last;
+    }
+    if let Some(error) = error { return Err(error); }
+    let subscription = select_subscription(next_found & !covering_found, &next, &covering);
+    let subscription = select_subscription(last_found & !next_found & !covering_found, &last, &subscription);
+    match (covering_found | next_found | last_found) != 0 {
+        true => Ok(subscription),
+        false => Err(SecureMemoryError::NoSubscription)
+    }
+}
+
+// Sequence number of the channel's latest accepted update, None if it never accepted one
+// Read from the channel's sequence mark, which outlives its windows
+pub fn retrieve_subscription_sequence<F: Flash>(flc: &F, channel_id: u32) -> Result<Option<u64>, SecureMemoryError> {
+    let mut sequence: u64 = 0;
+    let mut found: u64 = 0;
+    let mut error: Option<SecureMemoryError> = None;
+    for i in 0..SEQUENCE_MARKS_CAPACITY {
+        let mark = keep_first_error(read_sequence_mark(flc, i), SequenceMark::EMPTY, &mut error);
+        let of_channel = bool_mask(mark.valid) & eq_mask_u32(mark.channel_id, channel_id);
+        sequence = select_u64(of_channel, mark.sequence, sequence);
+        found |= of_channel;
+    }
+    if let Some(error) = error { return Err(error); }
+    match found != 0 {
+        true => Ok(Some(sequence)),
+        false => Ok(None)
+    }
+}
+
+fn select_subscription(mask: u64, a: &Subscription, b: &Subscription) -> Subscription {
+    Subscription {
+        channel_id: select_u32(mask, a.channel_id, b.channel_id),
+        valid: select_bool(mask, a.valid, b.valid),
+        end: select_u64(mask, a.end, b.end),
+        start: select_u64(mask, a.start, b.start),
+        sequence: select_u64(mask, a.sequence, b.sequence)
+    }
+}
+
+// Lookups read on past a failed record, standing in `empty` for it,
+// and return the first error once every slot has been read
+fn keep_first_error<T>(record: Result<T, SecureMemoryError>, empty: T, error: &mut Option<SecureMemoryError>) -> T {
+    match record {
+        Ok(record) => record,
+        Err(record_error) => {
+            if error.is_none() { *error = Some(record_error); }
+            empty
+        }
+    }
+}
+
+pub fn retrieve_subscriptions<F: Flash>(flc: &F) -> Result<Vec<Subscription>, SecureMemoryError> {
+    let mut subscriptions = Vec::with_capacity(SUBSCRIPTION_WINDOWS_CAPACITY);
+    for i in 0..SUBSCRIPTION_WINDOWS_CAPACITY {
+        subscriptions.push(read_subscription(flc, i)?);
+    }
+    Ok(subscriptions)
+}
+
+fn read_subscription<F: Flash>(flc: &F, index: usize) -> Result<Subscription, SecureMemoryError> {
+    let record = flc.read_bytes::<SUBSCRIPTION_RECORD_SIZE>(flc.region_address(FlashRegion::Subscriptions) + (index * SUBSCRIPTION_RECORD_SIZE) as u32);
+    if record.is_err() { return Err(SecureMemoryError::FlashError(record.unwrap_err())); }
+    match Subscription::from_bytes(&record.unwrap()) {
+        Ok(subscription) => Ok(subscription),
+        Err(record_error) => Err(SecureMemoryError::RecordError(record_error))
+    }
+}
+
+fn read_sequence_mark<F: Flash>(flc: &F, index: usize) -> Result<SequenceMark, SecureMemoryError> {
+    let record = flc.read_bytes::<SEQUENCE_MARK_RECORD_SIZE>(sequence_mark_address(flc, index));
+    if record.is_err() { return Err(SecureMemoryError::FlashError(record.unwrap_err())); }
+    match SequenceMark::from_bytes(&record.unwrap()) {
+        Ok(mark) => Ok(mark),
+        Err(record_error) => Err(SecureMemoryError::RecordError(record_error))
+    }
+}
+
+fn sequence_mark_address<F: Flash>(flc: &F, index: usize) -> u32 {
+    flc.region_address(FlashRegion::Subscriptions) + (SEQUENCE_MARKS_OFFSET + index * SEQUENCE_MARK_RECORD_SIZE) as u32
+}
+
+// Slot of the channel's sequence mark, else the first empty one
+fn find_sequence_mark<F: Flash>(flc: &F, channel_id: u32) -> Result<usize, SecureMemoryError> {
+    let mut empty: Option<usize> = None;
+    for i in 0..SEQUENCE_MARKS_CAPACITY {
+        let mark = read_sequence_mark(flc, i)?;
+        if mark.valid && mark.channel_id == channel_id { return Ok(i); }
+        if !mark.valid && empty.is_none() { empty = Some(i); }
+    }
+    match empty {
+        Some(i) => Ok(i),
+        None => Err(SecureMemoryError::SubscriptionMemoryFull)
+    }
+}
+
+// Rewrites the whole page, as flash must be erased a page at a time
+// The other channels' sequence marks are copied over as they are, only `mark` goes in slot `mark_index`
+fn write_subscriptions<F: Flash>(flc: &F, subscriptions: &[Subscription], mark_index: usize, mark: SequenceMark) -> Result<(), SecureMemoryError> {
+    let mut data: [u32; SUBSCRIPTIONS_IMAGE_SIZE / 4] = [0; SUBSCRIPTIONS_IMAGE_SIZE / 4];
+    let (window_words, mark_words) = data.split_at_mut(SEQUENCE_MARKS_OFFSET / 4);
+    for (record_words, subscription) in window_words.chunks_exact_mut(SUBSCRIPTION_RECORD_SIZE / 4).zip(subscriptions) {
+        copy_record(record_words, &subscription.to_bytes());
+    }
+    for (i, record_words) in mark_words.chunks_exact_mut(SEQUENCE_MARK_RECORD_SIZE / 4).enumerate() {
+        if i == mark_index {
+            copy_record(record_words, &mark.to_bytes());
+            continue;
+        }
+        let record = flc.read_bytes::<SEQUENCE_MARK_RECORD_SIZE>(sequence_mark_address(flc, i));
+        if record.is_err() { return Err(SecureMemoryError::FlashError(record.unwrap_err())); }
+        copy_record(record_words, &record.unwrap());
+    }
+    let ret = flc.erase_page(flc.region_address(FlashRegion::Subscriptions));
+    if ret.is_err() { return Err(SecureMemoryError::FlashError(ret.unwrap_err())); }
+    match flc.write_u32_slice(flc.region_address(FlashRegion::Subscriptions), &data) {
+        Ok(()) => Ok(()),
+        Err(flash_error) => Err(SecureMemoryError::FlashError(flash_error))
+    }
+}
+
+fn copy_record(words: &mut [u32], bytes: &[u8]) {
+    for (word, record_bytes) in words.iter_mut().zip(bytes.chunks_exact(4)) {
+        *word = u32::from_le_bytes([record_bytes[0], record_bytes[1], record_bytes[2], record_bytes[3]]);
+    }
+}
+
+// The record and the secret are both wiped once dropped
+fn read_secret<F: Flash>(flc: &F, index: usize) -> Result<Zeroizing<Secret>, SecureMemoryError> {
+    let record = flc.read_bytes::<SECRET_RECORD_SIZE>(flc.region_address(FlashRegion::Secrets) + (SECRETS_HEADER_SIZE + index * SECRET_RECORD_SIZE) as u32);
+    if record.is_err() { return Err(SecureMemoryError::FlashError(record.unwrap_err())); }
+    let record = Zeroizing::new(record.unwrap());
+    match Secret::from_bytes(&record) {
+        Ok(secret) => Ok(Zeroizing::new(secret)),
+        Err(record_error) => Err(SecureMemoryError::RecordError(record_error))
+    }
+}
+
+// Adds a window to the channel's subscription
+// Windows of the channel that overlap or touch the new one are merged into it,
+// the rest are kept, so buying a later period never drops an earlier one
+// Every window of the channel takes the update's sequence number, and so does its sequence mark
+// Expired windows are only reclaimed when the page is otherwise full, in the same rewrite
+pub fn merge_subscription<F: Flash>(flc: &F, subscription: Subscription) -> Result<(), SecureMemoryError> {
+    // Validate metadata
+    if subscription.channel_id == 0 { return Err(SecureMemoryError::InvalidSubscriptionChannel(subscription.channel_id)); }
+    if !subscription.valid { return Err(SecureMemoryError::SubscriptionNotValid(subscription.channel_id)); }
+    if subscription.end < subscription.start { return Err(SecureMemoryError::SubscriptionNotValid(subscription.channel_id)); }
+    // Get all subscription data
+    let mut subscriptions = retrieve_subscriptions(flc)?;
+    let mut updated = subscriptions.clone();
+    let mut placed = place_subscription(&mut updated, subscription);
+    if placed == Err(SecureMemoryError::SubscriptionMemoryFull) {
+        reclaim_expired(&mut subscriptions);
+        updated = subscriptions;
+        placed = place_subscription(&mut updated, subscription);
+    }
+    if placed.is_err() { return placed; }
+    let mark_index = find_sequence_mark(flc, subscription.channel_id)?;
+    let mark = SequenceMark { channel_id: subscription.channel_id, valid: true, sequence: subscription.sequence };
+    // Must copy entire subscription data and overwrite it all because we must
+    // erase the entire flash page to write data
+    write_subscriptions(flc, &updated, mark_index, mark)
+}
+
+// Merges `subscription` into the slots in RAM, without touching flash
+fn place_subscription(subscriptions: &mut [Subscription], subscription: Subscription) -> Result<(), SecureMemoryError> {
+    // A new channel needs room among the subscribed channels
+    let mut channels: Vec<u32> = Vec::with_capacity(SUBSCRIPTIONS_CAPACITY);
+    for sub in subscriptions.iter() {
+        if sub.valid && !channels.contains(&sub.channel_id) { channels.push(sub.channel_id); }
+    }
+    if !channels.contains(&subscription.channel_id) && channels.len() >= SUBSCRIPTIONS_CAPACITY { return Err(SecureMemoryError::SubscriptionMemoryFull); }
+    // Absorb every window the new one overlaps or touches, freeing their slots
+    // The channel's windows never touch each other, so one pass finds them all
+    let mut merged = subscription;
+    for sub in subscriptions.iter_mut() {
+        if sub.valid && sub.channel_id == subscription.channel_id {
+            sub.sequence = subscription.sequence;
+            if windows_touch(sub, &subscription) {
+                merged.start = merged.start.min(sub.start);
+                merged.end = merged.end.max(sub.end);
+                *sub = Subscription::EMPTY;
+            }
+        }
+    }
+    // The merged window takes the first empty slot
+    match subscriptions.iter().position(|sub| !sub.valid) {
+        Some(i) => {
+            subscriptions[i] = merged;
+            Ok(())
+        },
+        None => Err(SecureMemoryError::SubscriptionMemoryFull)
+    }
+}
+
+// Empties every window that ended at or before the replay floor
+// Every frame at or below it is rejected, so no later frame can fall in them
+// Sequence marks are left alone, so the channel still refuses its older updates
+fn reclaim_expired(subscriptions: &mut [Subscription]) -> () {
+    let floor = match replay_floor() {
+        Some(floor) => floor,
+        None => { return; }
+    };
+    for sub in subscriptions.iter_mut() {
+        if sub.valid && sub.end <= floor { *sub = Subscription::EMPTY; }
+    }
+}
+
+// Overlapping, or adjacent with no timestamp between them
+fn windows_touch(a: &Subscription, b: &Subscription) -> bool {
+    a.start <= b.end.saturating_add(1) && b.start <= a.end.saturating_add(1)
+}
+
+pub fn retrieve_channel_secret<F: Flash>(flc: &F, channel_id: u32) -> Result<Zeroizing<Secret>, SecureMemoryError> {
+    match retrieve_secret(flc, SecretType::Channel(channel_id))? {
+        Some(channel_secret) => Ok(channel_secret),
+        None => Err(SecureMemoryError::NoChannelSecret)
+    }
+}
+
+pub fn retrieve_master_secret<F: Flash>(flc: &F) -> Result<Zeroizing<Secret>, SecureMemoryError> {
+    match retrieve_secret(flc, SecretType::Master)? {
+        Some(master_secret) => Ok(master_secret),
+        None => Err(SecureMemoryError::NoMasterSecret)
+    }
+}
+
+// Reads the one record the header's index places the secret in
+// Constant time: the slot is computed the same way for any channel, and the record
+// is checked with masks, so only its address depends on the channel asked for
+// A secret missing from the image hashes to another secret's record or an empty one
+fn retrieve_secret<F: Flash>(flc: &F, secret_type: SecretType) -> Result<Option<Zeroizing<Secret>>, SecureMemoryError> {
+    let header = read_secrets_header(flc)?;
+    let sec = read_secret(flc, header.index.slot(secret_type))?;
+    let (tag, channel_id) = secret_type.words();
+    let (sec_tag, sec_channel_id) = sec.secret_type.words();
+    let matches = bool_mask(sec.valid) & eq_mask_u32(sec_tag, tag) & eq_mask_u32(sec_channel_id, channel_id);
+    let mut secret = Zeroizing::new(Secret { secret_type, valid: true, aes_key: [0; 32], aes_iv: [0; 16] });
+    select_bytes(matches, &sec.aes_key, &mut secret.aes_key);
+    select_bytes(matches, &sec.aes_iv, &mut secret.aes_iv);
+    match matches != 0 {
+        true => Ok(Some(secret)),
+        false => Ok(None)
+    }
+}
+
+pub fn retrieve_decoder_id<F: Flash>(flc: &F) -> Result<u32, SecureMemoryError> {
+    let saved_decoder_id = flc.read_32(flc.region_address(FlashRegion::Secrets) + SECRETS_DECODER_ID_OFFSET as u32);
+    if saved_decoder_id.is_err() { return Err(SecureMemoryError::FlashError(saved_decoder_id.unwrap_err())); }
+    Ok(saved_decoder_id.unwrap())
+}
+
+pub fn verify_decoder_id<F: Flash>(flc: &F, decoder_id: u32) -> Result<bool, SecureMemoryError> {
+    let header = read_secrets_header(flc)?;
+    Ok(header.decoder_id == decoder_id)
+}
+
+// Public key of the offline provisioning key, the private half never leaves the host
+pub fn retrieve_verifying_key<F: Flash>(flc: &F) -> Result<[u8; 32], SecureMemoryError> {
+    let header = read_secrets_header(flc)?;
+    Ok(header.verifying_key)
+}
+
+fn read_secrets_header<F: Flash>(flc: &F) -> Result<SecretsHeader, SecureMemoryError> {
+    let header = flc.read_bytes::<SECRETS_HEADER_SIZE>(flc.region_address(FlashRegion::Secrets));
+    if header.is_err() { return Err(SecureMemoryError::FlashError(header.unwrap_err())); }
+    match SecretsHeader::from_bytes(&header.unwrap()) {
+        Ok(header) => Ok(header),
+        Err(record_error) => Err(SecureMemoryError::RecordError(record_error))
+    }
+}
\ No newline at end of file
@@ -0,0 +1,23 @@
+//! Provisioning key signatures, over subscription updates and resyncs
+//! Both are signed offline with the provisioning key and only its public half
+//! is in flash, so the secrets read out of one decoder cannot make either for another
+
+use ed25519_dalek::{Signature, VerifyingKey};
+
+use decoder_types::packet::SUBSCRIPTION_SIGNATURE_SIZE;
+
+// Strict verification, so a signature cannot be altered into a second valid one
+pub fn verify_provisioning_signature(verifying_key: &[u8; 32], message: &[u8], signature: &[u8; SUBSCRIPTION_SIGNATURE_SIZE]) -> bool {
+    match VerifyingKey::from_bytes(verifying_key) {
+        Ok(verifying_key) => verifying_key.verify_strict(message, &Signature::from_bytes(signature)).is_ok(),
+        Err(_) => false
+    }
+}
+
+// Inverse of `verify_provisioning_signature` for hardened checks
+// A public key that is not a curve point verifies nothing
+pub fn provisioning_signature_invalid(verifying_key: &[u8; 32], message: &[u8], signature: &[u8; SUBSCRIPTION_SIGNATURE_SIZE]) -> bool {
+    let verifying_key = VerifyingKey::from_bytes(verifying_key);
+    if verifying_key.is_err() { return true; }
+    verifying_key.unwrap().verify_strict(message, &Signature::from_bytes(signature)).is_err()
+}
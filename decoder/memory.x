@@ -5,6 +5,8 @@ MEMORY {
     START_FLASH   (rx) : ORIGIN = 0x1000E000, LENGTH = 0x0000020C /* start flash to work with bootloader and rust toolchain */
     FLASH         (rx) : ORIGIN = 0x1000E20C, LENGTH = 0x00035E04 /* Location of team firmware, skipping 200 bytes to make it work for this toolchain */
     SUBSCRIPTIONS (r)  : ORIGIN = 0x10036000, LENGTH = 0x00002000 /* Subscriptions */
+    LOCKOUT       (r)  : ORIGIN = 0x10038000, LENGTH = 0x00002000 /* Authentication failure counter */
+    SECRETS       (r)  : ORIGIN = 0x1003A000, LENGTH = 0x00004000 /* Secrets image, placed after linking */
     RESERVED      (rw) : ORIGIN = 0x10046000, LENGTH = 0x00038000 /* Reserved */
     ROM_BL_PAGE   (rw) : ORIGIN = 0x1007E000, LENGTH = 0x00002000 /* Reserved */
     RAM           (rwx): ORIGIN = 0x20000000, LENGTH = 0x00020000 /* 128kB SRAM */
@@ -41,6 +43,22 @@ SECTIONS {
 
         KEEP(*(.subscriptions)) /* Ensure it's not removed */
     } > SUBSCRIPTIONS
+
+    .lockout : {
+        _lockout_page_start = .;
+        *(.lockout)
+        _lockout_page_end = .;
+
+        KEEP(*(.lockout)) /* Ensure it's not removed */
+    } > LOCKOUT
+
+    .secrets : {
+        _secrets_start = .;
+        *(.secrets)
+        _secrets_end = .;
+
+        KEEP(*(.secrets)) /* Replaced by secrets.bin with objcopy --update-section */
+    } > SECRETS
 }
 
 /*https://github.com/mitre-cyber-academy/2025-ectf-insecure-example/blob/release/decoder/firmware.ld#L7*/
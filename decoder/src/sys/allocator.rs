@@ -11,6 +11,7 @@
 
 extern crate alloc;
 
+use decoder_core::platform::HEAP_SIZE;
 use embedded_alloc::LlffHeap as Heap;
 
 // this is the allocator the application will use
@@ -21,7 +22,6 @@ pub fn init_heap() {
     // Initialize the allocator BEFORE you use it
     {
         use core::mem::MaybeUninit;
-        const HEAP_SIZE: usize = 1024;
         static mut HEAP_MEM: [MaybeUninit<u8>; HEAP_SIZE] = [MaybeUninit::uninit(); HEAP_SIZE];
         unsafe { HEAP.init(&raw mut HEAP_MEM as usize, HEAP_SIZE) }
     }
@@ -2,11 +2,9 @@
 //! NOT AN ACTUAL RTOS
 //! Contains:
 //! - Initialization of Flash memory
+//! - Peripheral wrappers for the decoder logic
 //! - Interupts / Handlers
 //! - System Watchdog (his name is Cupcake) (Still awaiting shipment)
 
 pub mod allocator;
-pub mod secure_memory;
-pub mod decrypt;
-pub mod generated_flash;
-pub mod rng;
+pub mod platform;
@@ -0,0 +1,131 @@
+//! MAX78000 peripherals behind the decoder_core platform traits
+//! Also owns the flash pages the decoder logic addresses by region,
+//! and the delay policy, free channels and replay policy chosen at build time
+
+use hal::aes::Aes;
+use hal::flc::Flc;
+use hal::trng::Trng;
+use hal::{gpio::{Af1, Pin}, pac::Uart0, uart::BuiltUartPeripheral};
+
+use decoder_types::{AesBlock, AesKey, RecordArray, SUBSCRIPTIONS_IMAGE_SIZE, SECRETS_IMAGE_SIZE};
+
+use decoder_core::platform;
+use decoder_core::platform::{AesError, FlashError, FlashRegion};
+use decoder_core::sys::lockout::{blank_lockout_page, LOCKOUT_PAGE_WORDS};
+use decoder_core::sys::rng::{DelayConfig, DelayConfigs, DelayPolicy, DelayTrigger, EntropySource};
+use decoder_core::sys::free_channels::{FramePriority, FreeChannel};
+use decoder_core::sys::replay::ReplayPolicy;
+
+include!(concat!(env!("OUT_DIR"), "/delay_policy.rs"));
+include!(concat!(env!("OUT_DIR"), "/free_channels.rs"));
+include!(concat!(env!("OUT_DIR"), "/replay_policy.rs"));
+
+// Erased records read back as empty subscription slots and sequence marks
+#[link_section = ".subscriptions"]
+static SUBSCRIPTIONS: RecordArray<SUBSCRIPTIONS_IMAGE_SIZE> = RecordArray([0xFF; SUBSCRIPTIONS_IMAGE_SIZE]);
+
+#[link_section = ".lockout"]
+static LOCKOUT_PAGE: [u32; LOCKOUT_PAGE_WORDS] = blank_lockout_page();
+
+// Placeholder for the secrets image made by build.rs, which is placed here after linking
+// Only ever read through flash so the erased contents are never assumed
+#[link_section = ".secrets"]
+static SECRETS_IMAGE: RecordArray<SECRETS_IMAGE_SIZE> = RecordArray([0xFF; SECRETS_IMAGE_SIZE]);
+
+pub struct FlashController(pub Flc);
+
+impl platform::Flash for FlashController {
+    fn region_address(&self, region: FlashRegion) -> u32 {
+        match region {
+            FlashRegion::Subscriptions => &SUBSCRIPTIONS as *const _ as u32,
+            FlashRegion::Lockout => &LOCKOUT_PAGE as *const _ as u32,
+            FlashRegion::Secrets => &SECRETS_IMAGE as *const _ as u32
+        }
+    }
+
+    fn read_32(&self, address: u32) -> Result<u32, FlashError> {
+        match self.0.read_32(address) {
+            Ok(word) => Ok(word),
+            Err(_) => Err(FlashError::ReadFailed)
+        }
+    }
+
+    fn read_bytes<const N: usize>(&self, address: u32) -> Result<[u8; N], FlashError> {
+        match self.0.read_t::<[u8; N]>(address) {
+            Ok(bytes) => Ok(bytes),
+            Err(_) => Err(FlashError::ReadFailed)
+        }
+    }
+
+    fn erase_page(&self, address: u32) -> Result<(), FlashError> {
+        match self.0.erase_page(address) {
+            Ok(()) => Ok(()),
+            Err(_) => Err(FlashError::EraseFailed)
+        }
+    }
+
+    fn write_u32_slice(&self, address: u32, data: &[u32]) -> Result<(), FlashError> {
+        match self.0.write_u32_slice(address, data) {
+            Ok(()) => Ok(()),
+            Err(_) => Err(FlashError::WriteFailed)
+        }
+    }
+}
+
+pub struct AesEngine(pub Aes);
+
+impl platform::Aes for AesEngine {
+    fn set_key(&self, key: &AesKey) {
+        self.0.set_key(key);
+    }
+
+    fn clear_key(&self) {
+        self.0.set_key(&[0; 32]);
+    }
+
+    fn decrypt_block(&self, block: AesBlock) -> Result<AesBlock, AesError> {
+        match self.0.decrypt_block(block) {
+            Ok(block) => Ok(block),
+            Err(_) => Err(AesError::BlockFailed)
+        }
+    }
+}
+
+pub struct HostUart(pub BuiltUartPeripheral<Uart0, Pin<0, 0, Af1>, Pin<0, 1, Af1>, (), ()>);
+
+impl platform::Uart for HostUart {
+    fn read_bytes(&self, buffer: &mut [u8]) {
+        self.0.read_bytes(buffer);
+    }
+
+    fn write_bytes(&self, bytes: &[u8]) {
+        self.0.write_bytes(bytes);
+    }
+}
+
+pub struct SysTickDelay(pub cortex_m::delay::Delay);
+
+impl platform::Delay for SysTickDelay {
+    fn delay_us(&mut self, us: u32) {
+        self.0.delay_us(us);
+    }
+
+    fn delay_ms(&mut self, ms: u32) {
+        self.0.delay_ms(ms);
+    }
+}
+
+pub struct TrngSource(pub Trng);
+
+impl EntropySource for TrngSource {
+    fn gen_u32(&mut self) -> u32 {
+        self.0.gen_u32()
+    }
+}
+
+pub const DELAY_CONFIGS: DelayConfigs = DelayConfigs {
+    list: LIST_DELAY,
+    update: UPDATE_DELAY,
+    decode: DECODE_DELAY,
+    receive: RECEIVE_DELAY
+};
@@ -0,0 +1,105 @@
+//! Perfect hash index over the secret records
+//!
+//! Every secret gets a slot of its own, found by hashing its type and channel ID,
+//! so a lookup reads one record instead of all of them. Keys hash into buckets, and
+//! each bucket holds a displacement pair, chosen when the image is built, that
+//! sends all of its keys to free slots:
+//!   slot = (f1 + d0 * f2 + d1) mod SECRETS_CAPACITY
+//! Finding a slot is the same arithmetic for any channel, so it reveals nothing
+//! through timing.
+
+use crate::{Secret, SecretType, SECRETS_CAPACITY};
+
+pub const SECRET_INDEX_BUCKETS: usize = 64;
+
+// Seeds tried before giving up, a seed fails when two keys of a bucket always collide
+const MAX_SEEDS: u32 = 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SecretIndex {
+    pub seed: u32,
+    // [d0, d1] of each bucket
+    pub displacements: [[u8; 2]; SECRET_INDEX_BUCKETS]
+}
+
+impl SecretIndex {
+    pub fn slot(&self, secret_type: SecretType) -> usize {
+        let (bucket, f1, f2) = hashes(self.seed, secret_type);
+        let [d0, d1] = self.displacements[bucket];
+        place(f1, f2, d0, d1)
+    }
+}
+
+// Lays `secrets` out by slot, None if there are too many, a type appears twice, or no seed places them all
+// Larger buckets go first, while most slots are free; a bucket of one always fits,
+// as d1 alone reaches every slot
+pub fn index_secrets(secrets: &[Secret]) -> Option<(SecretIndex, [Secret; SECRETS_CAPACITY])> {
+    if secrets.len() > SECRETS_CAPACITY { return None; }
+    for (i, secret) in secrets.iter().enumerate() {
+        if secrets[..i].iter().any(|other| other.secret_type == secret.secret_type) { return None; }
+    }
+    (0..MAX_SEEDS).find_map(|seed| index_with_seed(seed, secrets))
+}
+
+fn index_with_seed(seed: u32, secrets: &[Secret]) -> Option<(SecretIndex, [Secret; SECRETS_CAPACITY])> {
+    let mut index = SecretIndex { seed, displacements: [[0; 2]; SECRET_INDEX_BUCKETS] };
+    let mut table = [Secret::EMPTY; SECRETS_CAPACITY];
+    let mut taken = [false; SECRETS_CAPACITY];
+    let mut bucket_sizes = [0usize; SECRET_INDEX_BUCKETS];
+    for secret in secrets {
+        bucket_sizes[hashes(seed, secret.secret_type).0] += 1;
+    }
+    let largest = bucket_sizes.iter().copied().max().unwrap_or(0);
+    for size in (1..=largest).rev() {
+        for (bucket, &bucket_size) in bucket_sizes.iter().enumerate() {
+            if bucket_size != size { continue; }
+            index.displacements[bucket] = place_bucket(seed, secrets, bucket, &mut taken, &mut table)?;
+        }
+    }
+    Some((index, table))
+}
+
+// First displacement pair sending every key of the bucket to a distinct free slot
+fn place_bucket(seed: u32, secrets: &[Secret], bucket: usize, taken: &mut [bool; SECRETS_CAPACITY], table: &mut [Secret; SECRETS_CAPACITY]) -> Option<[u8; 2]> {
+    let in_bucket = |secret: &&Secret| hashes(seed, secret.secret_type).0 == bucket;
+    for d0 in 0..SECRETS_CAPACITY as u8 {
+        for d1 in 0..SECRETS_CAPACITY as u8 {
+            let mut claimed = *taken;
+            let fits = secrets.iter().filter(in_bucket).all(|secret| {
+                let (_, f1, f2) = hashes(seed, secret.secret_type);
+                let slot = place(f1, f2, d0, d1);
+                let free = !claimed[slot];
+                claimed[slot] = true;
+                free
+            });
+            if !fits { continue; }
+            for secret in secrets.iter().filter(in_bucket) {
+                let (_, f1, f2) = hashes(seed, secret.secret_type);
+                table[place(f1, f2, d0, d1)] = *secret;
+            }
+            *taken = claimed;
+            return Some([d0, d1]);
+        }
+    }
+    None
+}
+
+// Bucket, then the two slot hashes, f2 odd so d0 * f2 reaches every slot
+fn hashes(seed: u32, secret_type: SecretType) -> (usize, usize, usize) {
+    let (tag, channel_id) = secret_type.words();
+    let hash = mix(mix(seed ^ channel_id) ^ tag);
+    ((hash as usize) % SECRET_INDEX_BUCKETS, ((hash >> 8) as usize) % SECRETS_CAPACITY, (((hash >> 16) as usize) % SECRETS_CAPACITY) | 1)
+}
+
+fn place(f1: usize, f2: usize, d0: u8, d1: u8) -> usize {
+    (f1 + d0 as usize * f2 + d1 as usize) % SECRETS_CAPACITY
+}
+
+// Murmur3 finaliser, every input bit affects every output bit
+fn mix(mut hash: u32) -> u32 {
+    hash ^= hash >> 16;
+    hash = hash.wrapping_mul(0x85eb_ca6b);
+    hash ^= hash >> 13;
+    hash = hash.wrapping_mul(0xc2b2_ae35);
+    hash ^ (hash >> 16)
+}
@@ -0,0 +1,282 @@
+//! On-flash record layouts
+//! Shared by the firmware and its build script, so the generator
+//! and the reader can never disagree on a layout
+//! Also holds the packet layouts and host protocol constants shared with the host tools,
+//! and the index that lays out the secret records
+//!
+//! Every record starts with a version byte and is serialised field by field
+//! in little endian, never by transmuting the in-memory struct.
+//! Record sizes are multiples of the 128-bit flash line.
+
+#![no_std]
+
+use zeroize::Zeroize;
+
+pub mod index;
+pub mod packet;
+pub mod protocol;
+
+use index::{SecretIndex, SECRET_INDEX_BUCKETS};
+
+pub type AesSubBlock = u8;
+pub type AesBlock = [AesSubBlock; 16];
+pub type AesKey = [u8; 32];
+
+// Bump whenever a record layout changes
+pub const RECORD_VERSION: u8 = 3;
+
+// Erased flash reads back as all ones
+const ERASED_BYTE: u8 = 0xFF;
+
+const VALID_MARKER: u8 = 0x01;
+
+// Channels a decoder can be subscribed to at once
+pub const SUBSCRIPTIONS_CAPACITY: usize = 8;
+// Window records in the subscriptions page, shared by every subscribed channel
+pub const SUBSCRIPTION_WINDOWS_CAPACITY: usize = 32;
+pub const SECRETS_CAPACITY: usize = 128;
+// Sequence marks after the windows, enough for every channel an image can hold a secret for
+pub const SEQUENCE_MARKS_CAPACITY: usize = SECRETS_CAPACITY;
+
+const SECRET_TAG_CHANNEL: u8 = 0x00;
+const SECRET_TAG_MASTER: u8 = 0x01;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RecordError {
+    UnknownVersion(u8),
+    UnknownSecretType(u8)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Subscription {
+    pub channel_id: u32,
+    pub valid: bool,
+    pub end: u64,
+    pub start: u64,
+    // Sequence number of the channel's latest accepted update
+    pub sequence: u64
+}
+
+// Highest update sequence a channel has accepted
+// Kept apart from its windows, so reclaiming every one of them does not forget it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SequenceMark {
+    pub channel_id: u32,
+    pub valid: bool,
+    pub sequence: u64
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SecretType {
+    Channel(u32),
+    Master
+}
+
+impl SecretType {
+    // Record tag and channel ID as plain words, to hash or compare without matching on the enum
+    pub fn words(self) -> (u32, u32) {
+        match self {
+            SecretType::Channel(channel_id) => (SECRET_TAG_CHANNEL as u32, channel_id),
+            SecretType::Master => (SECRET_TAG_MASTER as u32, 0)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Secret {
+    pub secret_type: SecretType,
+    pub valid: bool,
+    pub aes_key: AesKey,
+    pub aes_iv: AesBlock
+}
+
+// Subscription record, one per window of a channel
+// [0] version, [1] valid marker, [2..4] reserved,
+// [4..8] channel id, [8..16] start, [16..24] end, [24..32] sequence
+pub const SUBSCRIPTION_RECORD_SIZE: usize = 32;
+
+// Sequence mark record, one per channel ever subscribed
+// [0] version, [1] valid marker, [2..4] reserved, [4..8] channel id, [8..16] sequence
+pub const SEQUENCE_MARK_RECORD_SIZE: usize = 16;
+
+// Subscriptions page: the window records, then the sequence mark records
+pub const SEQUENCE_MARKS_OFFSET: usize = SUBSCRIPTION_WINDOWS_CAPACITY * SUBSCRIPTION_RECORD_SIZE;
+pub const SUBSCRIPTIONS_IMAGE_SIZE: usize = SEQUENCE_MARKS_OFFSET + SEQUENCE_MARKS_CAPACITY * SEQUENCE_MARK_RECORD_SIZE;
+
+// Secret record
+// [0] version, [1] valid marker, [2] secret type tag, [3] reserved,
+// [4..8] channel id, [8..40] aes key, [40..56] aes iv, [56..64] reserved
+pub const SECRET_RECORD_SIZE: usize = 64;
+
+// Secrets image header, followed by SECRETS_CAPACITY secret records placed by `index`
+// [0] version, [1..4] reserved, [4..8] decoder id, [8..12] index seed, [12..16] reserved,
+// [16..48] Ed25519 public key that subscription updates are signed with,
+// [48..176] index displacements, two bytes per bucket
+pub const SECRETS_HEADER_SIZE: usize = 176;
+pub const SECRETS_DECODER_ID_OFFSET: usize = 4;
+pub const SECRETS_INDEX_SEED_OFFSET: usize = 8;
+pub const SECRETS_VERIFYING_KEY_OFFSET: usize = 16;
+pub const SECRETS_INDEX_OFFSET: usize = 48;
+pub const SECRETS_IMAGE_SIZE: usize = SECRETS_HEADER_SIZE + SECRETS_CAPACITY * SECRET_RECORD_SIZE;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SecretsHeader {
+    pub decoder_id: u32,
+    pub verifying_key: [u8; 32],
+    pub index: SecretIndex
+}
+
+// Serialised records as placed in flash, aligned to the flash line
+#[derive(Debug, Clone, Copy)]
+#[repr(C, align(16))]
+pub struct RecordArray<const N: usize>(pub [u8; N]);
+
+impl Subscription {
+    pub const EMPTY: Subscription = Subscription {
+        channel_id: 0,
+        valid: false,
+        end: 0,
+        start: 0,
+        sequence: 0
+    };
+
+    pub fn to_bytes(&self) -> [u8; SUBSCRIPTION_RECORD_SIZE] {
+        let mut bytes = [0u8; SUBSCRIPTION_RECORD_SIZE];
+        bytes[0] = RECORD_VERSION;
+        bytes[1] = if self.valid { VALID_MARKER } else { 0 };
+        bytes[4..8].copy_from_slice(&self.channel_id.to_le_bytes());
+        bytes[8..16].copy_from_slice(&self.start.to_le_bytes());
+        bytes[16..24].copy_from_slice(&self.end.to_le_bytes());
+        bytes[24..32].copy_from_slice(&self.sequence.to_le_bytes());
+        bytes
+    }
+
+    // An erased record is an empty slot
+    pub fn from_bytes(bytes: &[u8; SUBSCRIPTION_RECORD_SIZE]) -> Result<Self, RecordError> {
+        if bytes.iter().all(|byte| *byte == ERASED_BYTE) { return Ok(Subscription::EMPTY); }
+        if bytes[0] != RECORD_VERSION { return Err(RecordError::UnknownVersion(bytes[0])); }
+        Ok(Subscription {
+            channel_id: u32::from_le_bytes(field(bytes, 4)),
+            valid: bytes[1] == VALID_MARKER,
+            end: u64::from_le_bytes(field(bytes, 16)),
+            start: u64::from_le_bytes(field(bytes, 8)),
+            sequence: u64::from_le_bytes(field(bytes, 24))
+        })
+    }
+}
+
+impl SequenceMark {
+    pub const EMPTY: SequenceMark = SequenceMark {
+        channel_id: 0,
+        valid: false,
+        sequence: 0
+    };
+
+    pub fn to_bytes(&self) -> [u8; SEQUENCE_MARK_RECORD_SIZE] {
+        let mut bytes = [0u8; SEQUENCE_MARK_RECORD_SIZE];
+        bytes[0] = RECORD_VERSION;
+        bytes[1] = if self.valid { VALID_MARKER } else { 0 };
+        bytes[4..8].copy_from_slice(&self.channel_id.to_le_bytes());
+        bytes[8..16].copy_from_slice(&self.sequence.to_le_bytes());
+        bytes
+    }
+
+    // An erased record is an empty slot
+    pub fn from_bytes(bytes: &[u8; SEQUENCE_MARK_RECORD_SIZE]) -> Result<Self, RecordError> {
+        if bytes.iter().all(|byte| *byte == ERASED_BYTE) { return Ok(SequenceMark::EMPTY); }
+        if bytes[0] != RECORD_VERSION { return Err(RecordError::UnknownVersion(bytes[0])); }
+        Ok(SequenceMark {
+            channel_id: u32::from_le_bytes(field(bytes, 4)),
+            valid: bytes[1] == VALID_MARKER,
+            sequence: u64::from_le_bytes(field(bytes, 8))
+        })
+    }
+}
+
+impl SecretsHeader {
+    pub fn to_bytes(&self) -> [u8; SECRETS_HEADER_SIZE] {
+        let mut bytes = [0u8; SECRETS_HEADER_SIZE];
+        bytes[0] = RECORD_VERSION;
+        bytes[SECRETS_DECODER_ID_OFFSET..SECRETS_DECODER_ID_OFFSET + 4].copy_from_slice(&self.decoder_id.to_le_bytes());
+        bytes[SECRETS_INDEX_SEED_OFFSET..SECRETS_INDEX_SEED_OFFSET + 4].copy_from_slice(&self.index.seed.to_le_bytes());
+        bytes[SECRETS_VERIFYING_KEY_OFFSET..SECRETS_VERIFYING_KEY_OFFSET + 32].copy_from_slice(&self.verifying_key);
+        for (bucket, displacement) in self.index.displacements.iter().enumerate() {
+            bytes[SECRETS_INDEX_OFFSET + bucket * 2..SECRETS_INDEX_OFFSET + bucket * 2 + 2].copy_from_slice(displacement);
+        }
+        bytes
+    }
+
+    // An erased header means the image was never provisioned, which fails the version check
+    pub fn from_bytes(bytes: &[u8; SECRETS_HEADER_SIZE]) -> Result<Self, RecordError> {
+        if bytes[0] != RECORD_VERSION { return Err(RecordError::UnknownVersion(bytes[0])); }
+        let mut displacements = [[0u8; 2]; SECRET_INDEX_BUCKETS];
+        for (bucket, displacement) in displacements.iter_mut().enumerate() {
+            *displacement = field(bytes, SECRETS_INDEX_OFFSET + bucket * 2);
+        }
+        Ok(SecretsHeader {
+            decoder_id: u32::from_le_bytes(field(bytes, SECRETS_DECODER_ID_OFFSET)),
+            verifying_key: field(bytes, SECRETS_VERIFYING_KEY_OFFSET),
+            index: SecretIndex { seed: u32::from_le_bytes(field(bytes, SECRETS_INDEX_SEED_OFFSET)), displacements }
+        })
+    }
+}
+
+impl Secret {
+    pub const EMPTY: Secret = Secret {
+        secret_type: SecretType::Channel(u32::MAX),
+        valid: false,
+        aes_key: [0xFF; 32],
+        aes_iv: [0xFF; 16]
+    };
+
+    pub fn to_bytes(&self) -> [u8; SECRET_RECORD_SIZE] {
+        let mut bytes = [0u8; SECRET_RECORD_SIZE];
+        bytes[0] = RECORD_VERSION;
+        bytes[1] = if self.valid { VALID_MARKER } else { 0 };
+        let channel_id = match self.secret_type {
+            SecretType::Channel(channel_id) => {
+                bytes[2] = SECRET_TAG_CHANNEL;
+                channel_id
+            },
+            SecretType::Master => {
+                bytes[2] = SECRET_TAG_MASTER;
+                0
+            }
+        };
+        bytes[4..8].copy_from_slice(&channel_id.to_le_bytes());
+        bytes[8..40].copy_from_slice(&self.aes_key);
+        bytes[40..56].copy_from_slice(&self.aes_iv);
+        bytes
+    }
+
+    // An erased record is an empty slot
+    pub fn from_bytes(bytes: &[u8; SECRET_RECORD_SIZE]) -> Result<Self, RecordError> {
+        if bytes.iter().all(|byte| *byte == ERASED_BYTE) { return Ok(Secret::EMPTY); }
+        if bytes[0] != RECORD_VERSION { return Err(RecordError::UnknownVersion(bytes[0])); }
+        let secret_type = match bytes[2] {
+            SECRET_TAG_CHANNEL => SecretType::Channel(u32::from_le_bytes(field(bytes, 4))),
+            SECRET_TAG_MASTER => SecretType::Master,
+            tag => { return Err(RecordError::UnknownSecretType(tag)); }
+        };
+        Ok(Secret {
+            secret_type,
+            valid: bytes[1] == VALID_MARKER,
+            aes_key: field(bytes, 8),
+            aes_iv: field(bytes, 40)
+        })
+    }
+}
+
+// Only the key material is wiped, the secret type and valid marker are not secret
+impl Zeroize for Secret {
+    fn zeroize(&mut self) {
+        self.aes_key.zeroize();
+        self.aes_iv.zeroize();
+    }
+}
+
+fn field<const N: usize>(bytes: &[u8], offset: usize) -> [u8; N] {
+    let mut out = [0u8; N];
+    out.copy_from_slice(&bytes[offset..offset + N]);
+    out
+}
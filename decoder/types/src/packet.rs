@@ -0,0 +1,179 @@
+//! Packet layouts shared by the firmware and the host design tools
+//!
+//! Frame packet, master layer:
+//!   [timestamp u64][channel id u32][frame length u32], then the channel layer
+//! Frame packet, channel layer:
+//!   [company stamp][frame, zero padded to whole blocks][company stamp]
+//! Subscription update, master layer:
+//!   [channel id u32][sequence u64][zero padding][end u64][start u64], then the channel layer
+//! Subscription update, channel layer:
+//!   [decoder id u32, zero padded]
+//! Subscription update, signature:
+//!   [Ed25519 signature of the signed update], sent in the clear after the master layer
+//! Unlock, master layer:
+//!   [decoder id u32][epoch u32][zero padding]
+//! Resync, master layer:
+//!   [decoder id u32][zero padding][sequence u64], [timestamp u64][zero padding]
+//! Resync, signature:
+//!   [Ed25519 signature of the signed resync], sent in the clear after the master layer
+//!
+//! Each layer is encrypted with the anti-CBC scheme: blocks are chained like CBC,
+//! but the encoder runs the block cipher backwards, so the decoder only ever
+//! needs to run it forwards to recover the plaintext.
+
+use core::iter::zip;
+
+use super::AesBlock;
+
+pub const COMPANY_STAMP: AesBlock = *b"HammerIndustries";
+
+pub const MAX_FRAME_LENGTH: usize = 64;
+
+pub fn frame_metadata_block(timestamp: u64, channel_id: u32, frame_length: u32) -> AesBlock {
+    let mut block: AesBlock = [0; 16];
+    block[..8].copy_from_slice(&timestamp.to_le_bytes());
+    block[8..12].copy_from_slice(&channel_id.to_le_bytes());
+    block[12..].copy_from_slice(&frame_length.to_le_bytes());
+    block
+}
+
+pub fn parse_frame_metadata(block: AesBlock) -> (u64, u32, u32) {
+    (u64::from_le_bytes(*block.first_chunk::<8>().unwrap()),
+    u32::from_le_bytes(*block.last_chunk::<8>().unwrap().first_chunk::<4>().unwrap()),
+    u32::from_le_bytes(*block.last_chunk::<4>().unwrap()))
+}
+
+// Zero padding needed to fill the last frame block
+pub fn frame_padding(frame_length: usize) -> usize {
+    (16 - frame_length % 16) % 16
+}
+
+// First block of a subscription update
+// The sequence must grow with every update for the channel, so an older update cannot be replayed
+pub fn subscription_header_block(channel_id: u32, sequence: u64) -> AesBlock {
+    let mut block: AesBlock = [0; 16];
+    block[..4].copy_from_slice(&channel_id.to_le_bytes());
+    block[4..12].copy_from_slice(&sequence.to_le_bytes());
+    block
+}
+
+// Returns (channel id, sequence), or None if the zero padding is not intact
+pub fn parse_subscription_header(block: AesBlock) -> Option<(u32, u64)> {
+    if !block[12..].iter().all(|x| *x == 0) { return None; }
+    Some((u32::from_le_bytes(*block.first_chunk::<4>().unwrap()),
+    u64::from_le_bytes(*block[4..].first_chunk::<8>().unwrap())))
+}
+
+pub const SUBSCRIPTION_SIGNATURE_SIZE: usize = 64;
+pub const SUBSCRIPTION_SIGNED_SIZE: usize = 48;
+
+// What the provisioning key signs for a subscription update
+// [company stamp][decoder id u32][channel id u32][sequence u64][end u64][start u64]
+// Naming the decoder means a signed update is only ever accepted by the decoder it was made for
+pub fn subscription_signed_message(decoder_id: u32, channel_id: u32, sequence: u64, end: u64, start: u64) -> [u8; SUBSCRIPTION_SIGNED_SIZE] {
+    let mut message = [0u8; SUBSCRIPTION_SIGNED_SIZE];
+    message[..16].copy_from_slice(&COMPANY_STAMP);
+    message[16..20].copy_from_slice(&decoder_id.to_le_bytes());
+    message[20..24].copy_from_slice(&channel_id.to_le_bytes());
+    message[24..32].copy_from_slice(&sequence.to_le_bytes());
+    message[32..40].copy_from_slice(&end.to_le_bytes());
+    message[40..48].copy_from_slice(&start.to_le_bytes());
+    message
+}
+
+// Decoder id block of a subscription update
+pub fn padded_u32_block(value: u32) -> AesBlock {
+    let mut block: AesBlock = [0; 16];
+    block[..4].copy_from_slice(&value.to_le_bytes());
+    block
+}
+
+// None if the zero padding is not intact
+pub fn parse_padded_u32(block: AesBlock) -> Option<u32> {
+    if !block[4..].iter().all(|x| *x == 0) { return None; }
+    Some(u32::from_le_bytes(*block.first_chunk::<4>().unwrap()))
+}
+
+pub fn timestamps_block(end: u64, start: u64) -> AesBlock {
+    let mut block: AesBlock = [0; 16];
+    block[..8].copy_from_slice(&end.to_le_bytes());
+    block[8..].copy_from_slice(&start.to_le_bytes());
+    block
+}
+
+// Returns (end, start)
+pub fn parse_timestamps(block: AesBlock) -> (u64, u64) {
+    (u64::from_le_bytes(*block.first_chunk::<8>().unwrap()),
+    u64::from_le_bytes(*block.last_chunk::<8>().unwrap()))
+}
+
+pub fn unlock_block(decoder_id: u32, epoch: u32) -> AesBlock {
+    let mut block: AesBlock = [0; 16];
+    block[..4].copy_from_slice(&decoder_id.to_le_bytes());
+    block[4..8].copy_from_slice(&epoch.to_le_bytes());
+    block
+}
+
+// Returns (decoder id, epoch), or None if the zero padding is not intact
+pub fn parse_unlock(block: AesBlock) -> Option<(u32, u32)> {
+    if !block[8..].iter().all(|x| *x == 0) { return None; }
+    Some((u32::from_le_bytes(*block.first_chunk::<4>().unwrap()),
+    u32::from_le_bytes(*block[4..].first_chunk::<4>().unwrap())))
+}
+
+pub fn resync_blocks(decoder_id: u32, sequence: u64, timestamp: u64) -> [AesBlock; 2] {
+    let mut blocks: [AesBlock; 2] = [[0; 16]; 2];
+    blocks[0][..4].copy_from_slice(&decoder_id.to_le_bytes());
+    blocks[0][8..].copy_from_slice(&sequence.to_le_bytes());
+    blocks[1][..8].copy_from_slice(&timestamp.to_le_bytes());
+    blocks
+}
+
+// Returns (decoder id, sequence, timestamp), or None if the zero padding is not intact
+pub fn parse_resync(blocks: [AesBlock; 2]) -> Option<(u32, u64, u64)> {
+    if !blocks[0][4..8].iter().all(|x| *x == 0) || !blocks[1][8..].iter().all(|x| *x == 0) { return None; }
+    Some((u32::from_le_bytes(*blocks[0].first_chunk::<4>().unwrap()),
+    u64::from_le_bytes(*blocks[0].last_chunk::<8>().unwrap()),
+    u64::from_le_bytes(*blocks[1].first_chunk::<8>().unwrap())))
+}
+
+pub const RESYNC_SIGNED_SIZE: usize = 40;
+
+// What the provisioning key signs for a resync
+// [company stamp][decoder id u32][0xFFFFFFFF][sequence u64][timestamp u64]
+// Shorter than a signed subscription update, so neither signature is accepted for the other
+pub fn resync_signed_message(decoder_id: u32, sequence: u64, timestamp: u64) -> [u8; RESYNC_SIGNED_SIZE] {
+    let mut message = [0u8; RESYNC_SIGNED_SIZE];
+    message[..16].copy_from_slice(&COMPANY_STAMP);
+    message[16..20].copy_from_slice(&decoder_id.to_le_bytes());
+    message[20..24].copy_from_slice(&u32::MAX.to_le_bytes());
+    message[24..32].copy_from_slice(&sequence.to_le_bytes());
+    message[32..40].copy_from_slice(&timestamp.to_le_bytes());
+    message
+}
+
+// Encrypts in place, `decrypt_block` is the raw block cipher run backwards
+pub fn anti_cbc_encrypt(iv: AesBlock, blocks: &mut [AesBlock], mut decrypt_block: impl FnMut(AesBlock) -> AesBlock) {
+    let mut cbc = iv;
+    for block in blocks.iter_mut() {
+        let mut aes_in: AesBlock = [0; 16];
+        for (aes_in_byte, (block_byte, cbc_byte)) in zip(aes_in.iter_mut(), zip(*block, cbc)) {
+            *aes_in_byte = block_byte ^ cbc_byte;
+        }
+        *block = decrypt_block(aes_in);
+        cbc = *block;
+    }
+}
+
+// Decrypts in place, `encrypt_block` is the raw block cipher run forwards
+pub fn anti_cbc_decrypt(iv: AesBlock, blocks: &mut [AesBlock], mut encrypt_block: impl FnMut(AesBlock) -> AesBlock) {
+    let mut cbc = iv;
+    for block in blocks.iter_mut() {
+        let encrypted = *block;
+        let aes_out = encrypt_block(encrypted);
+        for (block_byte, (aes_byte, cbc_byte)) in zip(block.iter_mut(), zip(aes_out, cbc)) {
+            *block_byte = aes_byte ^ cbc_byte;
+        }
+        cbc = encrypted;
+    }
+}
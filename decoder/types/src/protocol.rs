@@ -0,0 +1,68 @@
+//! Host protocol constants shared by the firmware and the host client
+//!
+//! Every message is a header followed by a body:
+//!   [magic][opcode][body length u16]
+//! The receiver ACKs the header, then every body chunk of up to `CHUNK_SIZE` bytes.
+//! ACK and DEBUG messages are never ACKed themselves.
+
+pub const MAGIC_BYTE: u8 = 0x25;
+
+pub const DEBUG_OPCODE: u8 = 0x47;
+pub const LIST_OPCODE: u8 = 0x4C;
+pub const UPDATE_OPCODE: u8 = 0x53;
+pub const DECODE_OPCODE: u8 = 0x44;
+pub const DECODE_BATCH_OPCODE: u8 = 0x42;
+pub const DECODE_EXTENDED_OPCODE: u8 = 0x58;
+pub const UNLOCK_OPCODE: u8 = 0x55;
+pub const RESYNC_OPCODE: u8 = 0x52;
+pub const ACK_OPCODE: u8 = 0x41;
+pub const ERR_OPCODE: u8 = 0x45;
+
+pub const HEADER_SIZE: usize = 4;
+
+// Host sends and expects bodies in chunks of this size, with an ACK after each
+pub const CHUNK_SIZE: usize = 256;
+
+// Bounded so a full batch and its response fit in the heap
+pub const MAX_BATCH_FRAMES: usize = 16;
+
+// Size of one subscription entry in a LIST response, after the u32 count
+// [channel id u32][start u64][end u64]
+pub const LIST_ENTRY_SIZE: usize = 20;
+
+// Verified frame metadata in a DECODE_EXTENDED response, followed by the frame
+// [channel id u32][timestamp u64][priority u8]
+pub const DECODE_EXTENDED_HEADER_SIZE: usize = 13;
+
+const PRIORITY_NORMAL: u8 = 0x00;
+const PRIORITY_EMERGENCY: u8 = 0x01;
+
+// Priority of a decoded frame, sent with it in DECODE_BATCH entries and DECODE_EXTENDED responses
+// Frames on a free channel carry the priority the channel was built with, all others are normal
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FramePriority {
+    Normal,
+    Emergency
+}
+
+impl FramePriority {
+    pub fn to_byte(self) -> u8 {
+        match self {
+            FramePriority::Normal => PRIORITY_NORMAL,
+            FramePriority::Emergency => PRIORITY_EMERGENCY
+        }
+    }
+
+    pub fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            PRIORITY_NORMAL => Some(FramePriority::Normal),
+            PRIORITY_EMERGENCY => Some(FramePriority::Emergency),
+            _ => None
+        }
+    }
+}
+
+pub fn header_bytes(opcode: u8, length: u16) -> [u8; HEADER_SIZE] {
+    let length = length.to_le_bytes();
+    [MAGIC_BYTE, opcode, length[0], length[1]]
+}
@@ -13,6 +13,8 @@ class Encoder:
         if type(secrets) is not bytes:
             raise TypeError("secrets is not a byte-string")
         secrets_data: dict[str, list[str]] = loads(secrets.decode("utf-8"))
+        # Only subscriptions are signed, frames never need the verifying key
+        secrets_data.pop("verifying", None)
         if any((len(secret_list) != 2 for secret_list in secrets_data.values())):
             raise ValueError("Found improper amount of secret pairs for channel")
         self._secrets = {k: (standard_b64decode(v[0]), standard_b64decode(v[1]))
@@ -0,0 +1,195 @@
+from json import loads
+from base64 import standard_b64decode
+from Crypto.Cipher import AES
+from Crypto.PublicKey import ECC
+from Crypto.Signature import eddsa
+from argparse import ArgumentParser, FileType
+from pathlib import Path
+from ectf25_design.gen_secrets import provisioning_key_path
+from time import time_ns
+
+
+# Last sequence number handed out by _issue_sequence
+_last_sequence: int = 0
+
+
+def gen_resync(secrets: bytes, device_id: int, timestamp: int, sequence: int | None = None,
+               provisioning_key: bytes | None = None) -> bytes:
+    # Recover secrets
+    if type(secrets) is not bytes:
+        raise TypeError("secrets is not a byte-string")
+    if provisioning_key is None:
+        raise ValueError("Could not find subscription signing key: no provisioning_key given")
+    if type(provisioning_key) is not bytes:
+        raise TypeError("provisioning_key is not a byte-string")
+    secrets_data: dict[str, list[str]] = loads(secrets.decode("utf-8"))
+    if "verifying" not in secrets_data:
+        raise ValueError("Could not find subscription verifying key")
+    verifying_public: bytes = standard_b64decode(secrets_data.pop("verifying"))
+    provisioning_data: dict[str, list[str]] = loads(provisioning_key.decode("utf-8"))
+    if "signing" not in provisioning_data:
+        raise ValueError("Could not find subscription signing key")
+    signing_data: list[str] = provisioning_data["signing"]
+    if len(signing_data) != 2:
+        raise ValueError("Found improper signing key: expected seed and public key")
+    signing_seed: bytes = standard_b64decode(signing_data[0])
+    signing_public: bytes = standard_b64decode(signing_data[1])
+    if any((len(secret_list) != 2 for secret_list in secrets_data.values())):
+        raise ValueError("Found improper amount of secret pairs for channel")
+    secrets: dict[str, tuple[bytes, bytes]] = {k:
+                                               (standard_b64decode(v[0]), standard_b64decode(v[1]))
+                                               for k, v in secrets_data.items()}
+
+    # Secrets bounds checking
+    if "master" not in secrets:
+        raise ValueError("Could not find master secret pair")
+    if len(secrets["master"][0]) != 32:
+        raise ValueError("Found invalid AES key: not 256 bits")
+    if len(secrets["master"][1]) != 16:
+        raise ValueError("Found invalid CBC IV: not 128 bits")
+    if len(signing_seed) != 32 or len(signing_public) != 32:
+        raise ValueError("Found invalid signing key: not 256 bits")
+    signing_key: ECC.EccKey = eddsa.import_private_key(signing_seed)
+    if signing_key.public_key().export_key(format="raw") != signing_public:
+        raise ValueError("Found invalid signing key: public key does not match seed")
+    if signing_public != verifying_public:
+        raise ValueError("Found invalid signing key: not the one the decoders verify with")
+
+    # Other args bounds checking
+    if type(device_id) is not int:
+        raise TypeError("device_id is not an int")
+    if device_id < 0 or device_id > 2**32 - 1:
+        raise ValueError("device_id is not representable as u32")
+    if type(timestamp) is not int:
+        raise TypeError("timestamp is not an int")
+    if timestamp < 0 or timestamp > 2**64 - 1:
+        raise ValueError("timestamp is not representable as u64")
+    if sequence is None:
+        sequence = _issue_sequence()
+    if type(sequence) is not int:
+        raise TypeError("sequence is not an int")
+    if sequence < 0 or sequence > 2**64 - 1:
+        raise ValueError("sequence is not representable as u64")
+
+    # Encrypt package
+    encoded_resync: bytes = _anti_cbc_encrypt(secrets["master"][0],
+                                              secrets["master"][1],
+                                              (device_id.to_bytes(4, 'little')
+                                               + bytes(4)
+                                               + sequence.to_bytes(8, 'little')
+                                               + timestamp.to_bytes(8, 'little')
+                                               + bytes(8)))
+
+    # Sign for this decoder only, the signature is sent in the clear after the encrypted blocks
+    # The all ones channel keeps it shorter than and distinct from a signed subscription update
+    signed_resync: bytes = ("HammerIndustries".encode("ascii")
+                            + device_id.to_bytes(4, 'little')
+                            + (2**32 - 1).to_bytes(4, 'little')
+                            + sequence.to_bytes(8, 'little')
+                            + timestamp.to_bytes(8, 'little'))
+    signature: bytes = eddsa.new(signing_key, "rfc8032").sign(signed_resync)
+
+    return encoded_resync + signature
+
+
+def _issue_sequence() -> int:
+    # Issue time in nanoseconds, kept increasing within this process
+    # The decoder only accepts resyncs newer than the last one since it booted
+    global _last_sequence
+    _last_sequence = max(time_ns(), _last_sequence + 1)
+    return _last_sequence
+
+
+def _anti_cbc_encrypt(key: bytes, iv: bytes, blocks: bytes) -> bytes:
+    # Bounds checking
+    if type(key) is not bytes:
+        raise TypeError("key is not a byte-string")
+    if len(key) != 32:
+        raise ValueError("key is not 256 bits")
+    if type(iv) is not bytes:
+        raise TypeError("iv is not a byte-string")
+    if len(iv) != 16:
+        raise ValueError("iv is not 128 bits")
+    if type(blocks) is not bytes:
+        raise TypeError("blocks is not a byte-string")
+    if len(blocks) == 0:
+        raise ValueError("blocks is empty")
+    if len(blocks) % 16 != 0:
+        raise ValueError("blocks is not a multiple of 128 bits long")
+
+    # Anti CBC Encrypt
+    cipher: AES.EcbMode = AES.new(key, AES.MODE_ECB)
+    output: bytes = bytes()
+    cbc_intermediate: bytes = iv
+    for block in (blocks[i:i+16] for i in range(0, len(blocks), 16)):
+        aes_in: bytes = bytes((_a ^ _b for _a, _b in zip(block, cbc_intermediate)))
+        aes_out: bytes = cipher.decrypt(aes_in)
+        cbc_intermediate = aes_out
+        output += aes_out
+    return output
+
+
+def parse_args():
+    parser = ArgumentParser()
+    parser.add_argument(
+        "--force",
+        "-f",
+        action="store_true",
+        help="Force creation of resync file, overwriting existing file"
+    )
+    parser.add_argument(
+        "--sequence",
+        "-s",
+        type=lambda x: int(x, 0),
+        default=None,
+        help="Resync sequence number, must exceed the last one the decoder accepted since booting "
+             "(default: issue time in nanoseconds)"
+    )
+    parser.add_argument(
+        "--provisioning-key",
+        "-k",
+        type=Path,
+        default=None,
+        help="Path to the provisioning key file created by ectf25_design.gen_secrets "
+             "(default: the secrets file with a .provisioning.key suffix)"
+    )
+    parser.add_argument(
+        "secrets_file",
+        type=FileType("rb"),
+        help="Path to the secrets file created by ectf25_design.gen_secrets"
+    )
+    parser.add_argument(
+        "resync_file",
+        type=Path,
+        help="Path to the resync file to be generated"
+    )
+    parser.add_argument(
+        "device_id",
+        type=lambda x: int(x, 0),
+        help="Device ID of the decoder to resync"
+    )
+    parser.add_argument(
+        "timestamp",
+        type=int,
+        help="Timestamp the decoder's replay protection is moved to"
+    )
+    return parser.parse_args()
+
+
+def main():
+    args = parse_args()
+    if args.provisioning_key is None:
+        args.provisioning_key = provisioning_key_path(Path(args.secrets_file.name))
+    with open(args.provisioning_key, "rb") as f:
+        provisioning_key = f.read()
+    resync = gen_resync(args.secrets_file.read(),
+                        args.device_id,
+                        args.timestamp,
+                        args.sequence,
+                        provisioning_key)
+    with open(args.resync_file, "wb" if args.force else "xb") as f:
+        f.write(resync)
+
+
+if __name__ == "__main__":
+    main()
@@ -1,14 +1,33 @@
 from secrets import token_bytes
-from base64 import standard_b64encode
-from json import dumps
+from base64 import standard_b64encode, standard_b64decode
+from Crypto.PublicKey import ECC
+from Crypto.Signature import eddsa
+from json import dumps, loads
 from argparse import ArgumentParser
 from pathlib import Path
 
 
-def gen_secrets(channels: list[int]) -> bytes:
+def gen_secrets(channels: list[int], provisioning_key: bytes | None = None) -> bytes:
     # Bounds checking
     if type(channels) is not list:
         raise TypeError("channels is not a list")
+    if provisioning_key is None:
+        # A throwaway key, so these secrets can encode frames but nothing can sign updates for them
+        provisioning_key = gen_provisioning_key()
+    if type(provisioning_key) is not bytes:
+        raise TypeError("provisioning_key is not a byte-string")
+    provisioning_data: dict[str, list[str]] = loads(provisioning_key.decode("utf-8"))
+    if "signing" not in provisioning_data:
+        raise ValueError("Could not find subscription signing key")
+    signing_data: list[str] = provisioning_data["signing"]
+    if len(signing_data) != 2:
+        raise ValueError("Found improper signing key: expected seed and public key")
+    signing_seed: bytes = standard_b64decode(signing_data[0])
+    signing_public: bytes = standard_b64decode(signing_data[1])
+    if len(signing_seed) != 32 or len(signing_public) != 32:
+        raise ValueError("Found invalid signing key: not 256 bits")
+    if eddsa.import_private_key(signing_seed).public_key().export_key(format="raw") != signing_public:
+        raise ValueError("Found invalid signing key: public key does not match seed")
     channels: set[int] = set(channels)
     channels.add(0)
     if any((type(channel_id) is not int for channel_id in channels)):
@@ -36,10 +55,30 @@ def gen_secrets(channels: list[int]) -> bytes:
     for i, channel_num in enumerate(channels):
         secrets[str(channel_num)] = (secret_nums[2*i+2], secret_nums[2*i+3])
 
+    # The secrets file, which decoders are built from, only gets the public half of the provisioning key
+    secrets["verifying"] = standard_b64encode(signing_public).decode("ascii")
+
     # Encode secrets as utf-8 json
     return dumps(secrets).encode("utf-8")
 
 
+def gen_provisioning_key() -> bytes:
+    # Ed25519 key that subscriptions and resyncs are signed with
+    # Its seed stays with the update signer, apart from the secrets file
+    signing_key: ECC.EccKey = ECC.generate(curve="Ed25519")
+    signing_public: str = standard_b64encode(signing_key.public_key().export_key(format="raw")).decode("ascii")
+    provisioning_key: dict[str, tuple[str, str]] = dict()
+    provisioning_key["signing"] = (standard_b64encode(signing_key.seed).decode("ascii"), signing_public)
+
+    # Encode provisioning key as utf-8 json
+    return dumps(provisioning_key).encode("utf-8")
+
+
+def provisioning_key_path(secrets_file: Path) -> Path:
+    # Where the CLIs keep the provisioning key unless given one, beside the secrets file
+    return secrets_file.with_suffix(".provisioning.key")
+
+
 def parse_args():
     parser = ArgumentParser()
     parser.add_argument(
@@ -48,6 +87,14 @@ def parse_args():
         action="store_true",
         help="Force creation of secrets file, overwriting existing file"
     )
+    parser.add_argument(
+        "--provisioning-key",
+        "-k",
+        type=Path,
+        default=None,
+        help="Path to the provisioning key file to be created, keep it off the decoder build host "
+             "(default: the secrets file with a .provisioning.key suffix)"
+    )
     parser.add_argument(
         "secrets_file",
         type=Path,
@@ -65,9 +112,14 @@ def parse_args():
 
 def main():
     args = parse_args()
-    secrets = gen_secrets(args.channels)
+    provisioning_key = gen_provisioning_key()
+    secrets = gen_secrets(args.channels, provisioning_key)
+    if args.provisioning_key is None:
+        args.provisioning_key = provisioning_key_path(args.secrets_file)
     with open(args.secrets_file, "wb" if args.force else "xb") as f:
         f.write(secrets)
+    with open(args.provisioning_key, "wb" if args.force else "xb") as f:
+        f.write(provisioning_key)
 
 
 if __name__ == "__main__":
@@ -1,15 +1,39 @@
 from json import loads
 from base64 import standard_b64decode
 from Crypto.Cipher import AES
+from Crypto.PublicKey import ECC
+from Crypto.Signature import eddsa
 from argparse import ArgumentParser, FileType
 from pathlib import Path
+from ectf25_design.gen_secrets import provisioning_key_path
+from time import time_ns
 
 
-def gen_subscription(secrets: bytes, device_id: int, start: int, end: int, channel: int) -> bytes:
+# Last sequence number handed out by _issue_sequence
+_last_sequence: int = 0
+
+
+def gen_subscription(secrets: bytes, device_id: int, start: int, end: int, channel: int,
+                     sequence: int | None = None, provisioning_key: bytes | None = None) -> bytes:
     # Recover secrets
     if type(secrets) is not bytes:
         raise TypeError("secrets is not a byte-string")
+    if provisioning_key is None:
+        raise ValueError("Could not find subscription signing key: no provisioning_key given")
+    if type(provisioning_key) is not bytes:
+        raise TypeError("provisioning_key is not a byte-string")
     secrets_data: dict[str, list[str]] = loads(secrets.decode("utf-8"))
+    if "verifying" not in secrets_data:
+        raise ValueError("Could not find subscription verifying key")
+    verifying_public: bytes = standard_b64decode(secrets_data.pop("verifying"))
+    provisioning_data: dict[str, list[str]] = loads(provisioning_key.decode("utf-8"))
+    if "signing" not in provisioning_data:
+        raise ValueError("Could not find subscription signing key")
+    signing_data: list[str] = provisioning_data["signing"]
+    if len(signing_data) != 2:
+        raise ValueError("Found improper signing key: expected seed and public key")
+    signing_seed: bytes = standard_b64decode(signing_data[0])
+    signing_public: bytes = standard_b64decode(signing_data[1])
     if any((len(secret_list) != 2 for secret_list in secrets_data.values())):
         raise ValueError("Found improper amount of secret pairs for channel")
     secrets: dict[str, tuple[bytes, bytes]] = {k:
@@ -26,6 +50,13 @@ def gen_subscription(secrets: bytes, device_id: int, start: int, end: int, chann
         raise ValueError("Found invalid AES key: not 256 bits")
     if any((len(secret[1]) != 16 for secret in secrets.values())):
         raise ValueError("Found invalid CBC IV: not 128 bits")
+    if len(signing_seed) != 32 or len(signing_public) != 32:
+        raise ValueError("Found invalid signing key: not 256 bits")
+    signing_key: ECC.EccKey = eddsa.import_private_key(signing_seed)
+    if signing_key.public_key().export_key(format="raw") != signing_public:
+        raise ValueError("Found invalid signing key: public key does not match seed")
+    if signing_public != verifying_public:
+        raise ValueError("Found invalid signing key: not the one the decoders verify with")
 
     # Other args bounds checking
     if type(device_id) is not int:
@@ -48,6 +79,12 @@ def gen_subscription(secrets: bytes, device_id: int, start: int, end: int, chann
         raise ValueError("Cannot generate subscription for channel 0")
     if str(channel) not in secrets:
         raise ValueError("Could not find secret for channel:", channel)
+    if sequence is None:
+        sequence = _issue_sequence()
+    if type(sequence) is not int:
+        raise TypeError("sequence is not an int")
+    if sequence < 0 or sequence > 2**64 - 1:
+        raise ValueError("sequence is not representable as u64")
 
     # Encrypt package
     encoded_device_id: bytes = _anti_cbc_encrypt(secrets[str(channel)][0],
@@ -55,12 +92,31 @@ def gen_subscription(secrets: bytes, device_id: int, start: int, end: int, chann
                                                  device_id.to_bytes(16, 'little'))
     encoded_update: bytes = _anti_cbc_encrypt(secrets["master"][0],
                                               secrets["master"][1],
-                                              (channel.to_bytes(16, 'little')
+                                              (channel.to_bytes(4, 'little')
+                                               + sequence.to_bytes(8, 'little')
+                                               + bytes(4)
                                                + end.to_bytes(8, 'little')
                                                + start.to_bytes(8, 'little')
                                                + encoded_device_id))
 
-    return encoded_update
+    # Sign for this decoder only, the signature is sent in the clear after the encrypted blocks
+    signed_update: bytes = ("HammerIndustries".encode("ascii")
+                            + device_id.to_bytes(4, 'little')
+                            + channel.to_bytes(4, 'little')
+                            + sequence.to_bytes(8, 'little')
+                            + end.to_bytes(8, 'little')
+                            + start.to_bytes(8, 'little'))
+    signature: bytes = eddsa.new(signing_key, "rfc8032").sign(signed_update)
+
+    return encoded_update + signature
+
+
+def _issue_sequence() -> int:
+    # Issue time in nanoseconds, kept increasing within this process
+    # The decoder only accepts updates newer than the channel's last one
+    global _last_sequence
+    _last_sequence = max(time_ns(), _last_sequence + 1)
+    return _last_sequence
 
 
 def _anti_cbc_encrypt(key: bytes, iv: bytes, blocks: bytes) -> bytes:
@@ -100,6 +156,22 @@ def parse_args():
         action="store_true",
         help="Force creation of subscription file, overwriting existing file"
     )
+    parser.add_argument(
+        "--sequence",
+        "-s",
+        type=lambda x: int(x, 0),
+        default=None,
+        help="Update sequence number, must exceed the last one sent for this channel "
+             "(default: issue time in nanoseconds)"
+    )
+    parser.add_argument(
+        "--provisioning-key",
+        "-k",
+        type=Path,
+        default=None,
+        help="Path to the provisioning key file created by ectf25_design.gen_secrets "
+             "(default: the secrets file with a .provisioning.key suffix)"
+    )
     parser.add_argument(
         "secrets_file",
         type=FileType("rb"),
@@ -135,11 +207,17 @@ def parse_args():
 
 def main():
     args = parse_args()
+    if args.provisioning_key is None:
+        args.provisioning_key = provisioning_key_path(Path(args.secrets_file.name))
+    with open(args.provisioning_key, "rb") as f:
+        provisioning_key = f.read()
     subscription = gen_subscription(args.secrets_file.read(),
                                     args.device_id,
                                     args.start,
                                     args.end,
-                                    args.channel)
+                                    args.channel,
+                                    args.sequence,
+                                    provisioning_key)
     with open(args.subscription_file, "wb" if args.force else "xb") as f:
         f.write(subscription)
 
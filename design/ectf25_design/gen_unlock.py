@@ -0,0 +1,119 @@
+from json import loads
+from base64 import standard_b64decode
+from Crypto.Cipher import AES
+from argparse import ArgumentParser, FileType
+from pathlib import Path
+
+
+def gen_unlock(secrets: bytes, device_id: int, epoch: int) -> bytes:
+    # Recover secrets
+    if type(secrets) is not bytes:
+        raise TypeError("secrets is not a byte-string")
+    secrets_data: dict[str, list[str]] = loads(secrets.decode("utf-8"))
+    # Unlocks are not signed, they never need the verifying key
+    secrets_data.pop("verifying", None)
+    if any((len(secret_list) != 2 for secret_list in secrets_data.values())):
+        raise ValueError("Found improper amount of secret pairs for channel")
+    secrets: dict[str, tuple[bytes, bytes]] = {k:
+                                               (standard_b64decode(v[0]), standard_b64decode(v[1]))
+                                               for k, v in secrets_data.items()}
+
+    # Secrets bounds checking
+    if "master" not in secrets:
+        raise ValueError("Could not find master secret pair")
+    if len(secrets["master"][0]) != 32:
+        raise ValueError("Found invalid AES key: not 256 bits")
+    if len(secrets["master"][1]) != 16:
+        raise ValueError("Found invalid CBC IV: not 128 bits")
+
+    # Other args bounds checking
+    if type(device_id) is not int:
+        raise TypeError("device_id is not an int")
+    if device_id < 0 or device_id > 2**32 - 1:
+        raise ValueError("device_id is not representable as u32")
+    if type(epoch) is not int:
+        raise TypeError("epoch is not an int")
+    if epoch < 0 or epoch > 2**32 - 1:
+        raise ValueError("epoch is not representable as u32")
+
+    # Encrypt package
+    encoded_unlock: bytes = _anti_cbc_encrypt(secrets["master"][0],
+                                              secrets["master"][1],
+                                              (device_id.to_bytes(4, 'little')
+                                               + epoch.to_bytes(4, 'little')
+                                               + (b'\x00' * 8)))
+
+    return encoded_unlock
+
+
+def _anti_cbc_encrypt(key: bytes, iv: bytes, blocks: bytes) -> bytes:
+    # Bounds checking
+    if type(key) is not bytes:
+        raise TypeError("key is not a byte-string")
+    if len(key) != 32:
+        raise ValueError("key is not 256 bits")
+    if type(iv) is not bytes:
+        raise TypeError("iv is not a byte-string")
+    if len(iv) != 16:
+        raise ValueError("iv is not 128 bits")
+    if type(blocks) is not bytes:
+        raise TypeError("blocks is not a byte-string")
+    if len(blocks) == 0:
+        raise ValueError("blocks is empty")
+    if len(blocks) % 16 != 0:
+        raise ValueError("blocks is not a multiple of 128 bits long")
+
+    # Anti CBC Encrypt
+    cipher: AES.EcbMode = AES.new(key, AES.MODE_ECB)
+    output: bytes = bytes()
+    cbc_intermediate: bytes = iv
+    for block in (blocks[i:i+16] for i in range(0, len(blocks), 16)):
+        aes_in: bytes = bytes((_a ^ _b for _a, _b in zip(block, cbc_intermediate)))
+        aes_out: bytes = cipher.decrypt(aes_in)
+        cbc_intermediate = aes_out
+        output += aes_out
+    return output
+
+
+def parse_args():
+    parser = ArgumentParser()
+    parser.add_argument(
+        "--force",
+        "-f",
+        action="store_true",
+        help="Force creation of unlock file, overwriting existing file"
+    )
+    parser.add_argument(
+        "secrets_file",
+        type=FileType("rb"),
+        help="Path to the secrets file created by ectf25_design.gen_secrets"
+    )
+    parser.add_argument(
+        "unlock_file",
+        type=Path,
+        help="Path to the unlock file to be generated"
+    )
+    parser.add_argument(
+        "device_id",
+        type=lambda x: int(x, 0),
+        help="Device ID of the decoder to unlock"
+    )
+    parser.add_argument(
+        "epoch",
+        type=int,
+        help="Number of unlocks the decoder has already accepted"
+    )
+    return parser.parse_args()
+
+
+def main():
+    args = parse_args()
+    unlock = gen_unlock(args.secrets_file.read(),
+                        args.device_id,
+                        args.epoch)
+    with open(args.unlock_file, "wb" if args.force else "xb") as f:
+        f.write(unlock)
+
+
+if __name__ == "__main__":
+    main()
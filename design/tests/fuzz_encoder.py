@@ -60,6 +60,7 @@ def output_verifier(encode_output: bytes,
                     expected_timestamp: int):
     # Recover secrets
     secrets_data: dict[str, list[str]] = loads(secrets.decode("utf-8"))
+    secrets_data.pop("verifying")
     secrets = {k: (standard_b64decode(v[0]), standard_b64decode(v[1]))
                for k, v in secrets_data.items()}
 
@@ -0,0 +1,138 @@
+# flake8: noqa
+from pythonfuzz.main import PythonFuzz
+from json import loads
+from base64 import standard_b64decode
+from Crypto.Cipher import AES
+from Crypto.Signature import eddsa
+import sys, os
+sys.path.append(os.path.join(os.path.dirname(__file__), '..'))
+from ectf25_design.gen_resync import gen_resync
+from ectf25_design.gen_secrets import gen_secrets, gen_provisioning_key
+
+
+EXPECTED_TYPE_ERRORS = {
+    "secrets is not a byte-string",
+    "provisioning_key is not a byte-string",
+    "device_id is not an int",
+    "timestamp is not an int",
+    "sequence is not an int"
+}
+
+EXPECTED_VALUE_ERRORS = {
+    "Found improper amount of secret pairs for channel",
+    "Could not find master secret pair",
+    "Found invalid AES key: not 256 bits",
+    "Found invalid CBC IV: not 128 bits",
+    "device_id is not representable as u32",
+    "timestamp is not representable as u64",
+    "sequence is not representable as u64"
+}
+
+
+def input_transformer(buf: bytes) -> tuple[bytes, int, int, int, bytes]:
+    secrets: bytes
+    provisioning_key: bytes
+    device_id: int
+    timestamp: int
+    sequence: int
+    if len(buf) < 20:
+        raise ValueError("Insufficient buffer length")
+    device_id = int.from_bytes(buf[0:4], 'little')
+    timestamp = int.from_bytes(buf[4:12], 'little')
+    sequence = int.from_bytes(buf[12:20], 'little')
+    provisioning_key = gen_provisioning_key()
+    secrets = gen_secrets([], provisioning_key)
+    return (secrets, device_id, timestamp, sequence, provisioning_key)
+
+
+def output_verifier(gen_resync_output: bytes,
+                    secrets: bytes,
+                    expected_device_id: int,
+                    expected_timestamp: int,
+                    expected_sequence: int,
+                    provisioning_key: bytes):
+    # Recover secrets
+    secrets_data: dict[str, list[str]] = loads(secrets.decode("utf-8"))
+    signing_public: bytes = standard_b64decode(secrets_data.pop("verifying"))
+    secrets: dict[str, tuple[bytes, bytes]] = {k:
+                                               (standard_b64decode(v[0]), standard_b64decode(v[1]))
+                                               for k, v in secrets_data.items()}
+
+    # Decrypt package master layer
+    decoded_resync: bytes = anti_cbc_decrypt(secrets["master"][0],
+                                             secrets["master"][1],
+                                             gen_resync_output[:32])
+    device_id: int = int.from_bytes(decoded_resync[0:4], 'little')
+    sequence: int = int.from_bytes(decoded_resync[8:16], 'little')
+    timestamp: int = int.from_bytes(decoded_resync[16:24], 'little')
+
+    assert device_id == expected_device_id, "Decoded wrong device_id"
+    assert decoded_resync[4:8] == bytes(4), "Zero padding not intact"
+    assert sequence == expected_sequence, "Decoded wrong sequence"
+    assert timestamp == expected_timestamp, "Decoded wrong timestamp"
+    assert decoded_resync[24:32] == bytes(8), "Zero padding not intact"
+
+    # Verify signature over the plaintext, as the decoder does
+    assert len(gen_resync_output) == 96, "Wrong resync length"
+    signed_resync: bytes = ("HammerIndustries".encode("ascii")
+                            + expected_device_id.to_bytes(4, 'little')
+                            + (2**32 - 1).to_bytes(4, 'little')
+                            + expected_sequence.to_bytes(8, 'little')
+                            + expected_timestamp.to_bytes(8, 'little'))
+    eddsa.new(eddsa.import_public_key(signing_public), "rfc8032").verify(signed_resync, gen_resync_output[32:])
+
+
+def anti_cbc_decrypt(key: bytes, iv: bytes, blocks: bytes) -> bytes:
+    # Bounds checking
+    if type(key) is not bytes:
+        raise TypeError("key is not a byte-string")
+    if len(key) != 32:
+        raise ValueError("key is not 256 bits")
+    if type(iv) is not bytes:
+        raise TypeError("iv is not a byte-string")
+    if len(iv) != 16:
+        raise ValueError("iv is not 128 bits")
+    if type(blocks) is not bytes:
+        raise TypeError("blocks is not a byte-string")
+    if len(blocks) == 0:
+        raise ValueError("blocks is empty")
+    if len(blocks) % 16 != 0:
+        raise ValueError("blocks is not a multiple of 128 bits long")
+
+    # Anti CBC Decrypt
+    cipher: AES.EcbMode = AES.new(key, AES.MODE_ECB)
+    output: bytes = bytes()
+    cbc_intermediate: bytes = iv
+    for block in (blocks[i:i+16] for i in range(0, len(blocks), 16)):
+        aes_out: bytes = cipher.encrypt(block)
+        output += bytes((_a ^ _b for _a, _b in zip(aes_out, cbc_intermediate)))
+        cbc_intermediate = block
+    return output
+
+
+def fuzz(buf: bytes):
+    inputs = None
+    try:
+        inputs = input_transformer(buf)
+    except ValueError as e:
+        if e.args[0] != "Insufficient buffer length":
+            raise e
+    else:
+        gen_resync_output = None
+        try:
+            gen_resync_output = gen_resync(*inputs)
+        except TypeError as e:
+            if e.args[0] not in EXPECTED_TYPE_ERRORS:
+                raise e
+        except ValueError as e:
+            if e.args[0] not in EXPECTED_VALUE_ERRORS:
+                raise e
+        else:
+            output_verifier(gen_resync_output, *inputs)
+
+
+fuzz_exec = PythonFuzz(fuzz)
+
+
+if __name__ == '__main__':
+    fuzz_exec()
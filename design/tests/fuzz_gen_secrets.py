@@ -2,9 +2,10 @@
 from pythonfuzz.main import PythonFuzz
 from json import loads
 from base64 import standard_b64decode
+from Crypto.Signature import eddsa
 import sys, os
 sys.path.append(os.path.join(os.path.dirname(__file__), '..'))
-from ectf25_design.gen_secrets import gen_secrets
+from ectf25_design.gen_secrets import gen_secrets, gen_provisioning_key
 
 
 EXPECTED_TYPE_ERRORS = {
@@ -17,7 +18,7 @@ EXPECTED_VALUE_ERRORS = {
 }
 
 
-def input_transformer(buf: bytes) -> tuple[list[int]]:
+def input_transformer(buf: bytes) -> tuple[list[int], bytes]:
     channels: list[int] = list()
     while len(buf) > 0:
         if len(buf) < 4:
@@ -26,14 +27,24 @@ def input_transformer(buf: bytes) -> tuple[list[int]]:
         else:
             channels.append(int.from_bytes(buf[0:4], 'little'))
             buf = buf[4:]
-    return (channels,)
+    return (channels, gen_provisioning_key())
 
 
-def output_verifier(gen_secrets_output: bytes):
+def output_verifier(gen_secrets_output: bytes, channels: list[int], provisioning_key: bytes):
     # Recover secrets
     if type(gen_secrets_output) is not bytes:
         raise TypeError("secrets is not a byte-string")
     secrets_data: dict[str, list[str]] = loads(gen_secrets_output.decode("utf-8"))
+    provisioning_data: dict[str, list[str]] = loads(provisioning_key.decode("utf-8"))
+    assert list(provisioning_data) == ["signing"], "Provisioning key holds more than the signing key"
+    assert "signing" not in secrets_data, "Signing seed leaked into the secrets"
+    verifying_public: bytes = standard_b64decode(secrets_data.pop("verifying"))
+    signing_data: list[str] = provisioning_data["signing"]
+    signing_seed: bytes = standard_b64decode(signing_data[0])
+    signing_public: bytes = standard_b64decode(signing_data[1])
+    assert eddsa.import_private_key(signing_seed).public_key().export_key(format="raw") == signing_public, \
+        "Signing public key does not match seed"
+    assert verifying_public == signing_public, "Verifying key does not match the signing key"
     if any((len(secret_list) != 2 for secret_list in secrets_data.values())):
         raise ValueError("Found improper amount of secret pairs for channel")
     secrets = {k: (standard_b64decode(v[0]), standard_b64decode(v[1]))
@@ -43,7 +54,7 @@ def output_verifier(gen_secrets_output: bytes):
     if "master" not in secrets or "0" not in secrets:
         raise ValueError("Could not find master secret pair or channel 0 secret pair")
     if any(((int(channel_num) < 0 or int(channel_num) > 2**32 - 1)
-            for channel_num in gen_secrets_output if channel_num != 'master')):
+            for channel_num in secrets if channel_num != 'master')):
         raise ValueError("Found invalid channel numbers in secrets")
     if any((len(secret[0]) != 32 for secret in secrets.values())):
         raise ValueError("Found invalid AES key: not 256 bits")
@@ -63,7 +74,7 @@ def fuzz(buf: bytes):
         if e.args[0] not in EXPECTED_VALUE_ERRORS:
             raise e
     else:
-        output_verifier(gen_secrets_output)
+        output_verifier(gen_secrets_output, *inputs)
 
 
 fuzz_exec = PythonFuzz(fuzz)
@@ -3,18 +3,21 @@ from pythonfuzz.main import PythonFuzz
 from json import loads
 from base64 import standard_b64decode
 from Crypto.Cipher import AES
+from Crypto.Signature import eddsa
 import sys, os
 sys.path.append(os.path.join(os.path.dirname(__file__), '..'))
 from ectf25_design.gen_subscription import gen_subscription
-from ectf25_design.gen_secrets import gen_secrets
+from ectf25_design.gen_secrets import gen_secrets, gen_provisioning_key
 
 
 EXPECTED_TYPE_ERRORS = {
     "secrets is not a byte-string",
+    "provisioning_key is not a byte-string",
     "device_id is not an int",
     "start timestamp is not an int",
     "end timestamp is not an int",
-    "channel is not an int"
+    "channel is not an int",
+    "sequence is not an int"
 }
 
 EXPECTED_VALUE_ERRORS = {
@@ -28,24 +31,29 @@ EXPECTED_VALUE_ERRORS = {
     "end is less than start",
     "channel is not representable as u32",
     "Cannot generate subscription for channel 0",
-    "Could not find secret for channel:"
+    "Could not find secret for channel:",
+    "sequence is not representable as u64"
 }
 
 
-def input_transformer(buf: bytes) -> tuple[bytes, int, int, int, int]:
+def input_transformer(buf: bytes) -> tuple[bytes, int, int, int, int, int, bytes]:
     secrets: bytes
+    provisioning_key: bytes
     device_id: int
     start: int
     end: int
     channel: int
-    if len(buf) < 24:
+    sequence: int
+    if len(buf) < 32:
         raise ValueError("Insufficient buffer length")
     device_id = int.from_bytes(buf[0:4], 'little')
     start = int.from_bytes(buf[4:12], 'little')
     end = int.from_bytes(buf[12:20], 'little')
     channel = int.from_bytes(buf[20:24], 'little')
-    secrets = gen_secrets([channel])
-    return (secrets, device_id, start, end, channel)
+    sequence = int.from_bytes(buf[24:32], 'little')
+    provisioning_key = gen_provisioning_key()
+    secrets = gen_secrets([channel], provisioning_key)
+    return (secrets, device_id, start, end, channel, sequence, provisioning_key)
 
 
 def output_verifier(gen_subscription_output: bytes,
@@ -53,9 +61,12 @@ def output_verifier(gen_subscription_output: bytes,
                     expected_device_id: int,
                     expected_start: int,
                     expected_end: int,
-                    expected_channel: int):
+                    expected_channel: int,
+                    expected_sequence: int,
+                    provisioning_key: bytes):
     # Recover secrets
     secrets_data: dict[str, list[str]] = loads(secrets.decode("utf-8"))
+    signing_public: bytes = standard_b64decode(secrets_data.pop("verifying"))
     secrets: dict[str, tuple[bytes, bytes]] = {k:
                                                (standard_b64decode(v[0]), standard_b64decode(v[1]))
                                                for k, v in secrets_data.items()}
@@ -63,13 +74,17 @@ def output_verifier(gen_subscription_output: bytes,
     # Decrypt package master layer
     decoded_update: bytes = anti_cbc_decrypt(secrets["master"][0],
                                              secrets["master"][1],
-                                             gen_subscription_output)
-    channel: int = int.from_bytes(decoded_update[0:16], 'little')
+                                             gen_subscription_output[:48])
+    channel: int = int.from_bytes(decoded_update[0:4], 'little')
+    sequence: int = int.from_bytes(decoded_update[4:12], 'little')
+    padding: bytes = decoded_update[12:16]
     end: int = int.from_bytes(decoded_update[16:24], 'little')
     start: int = int.from_bytes(decoded_update[24:32], 'little')
     encoded_device_id: bytes = decoded_update[32:48]
 
     assert channel == expected_channel, "Decoded wrong channel"
+    assert sequence == expected_sequence, "Decoded wrong sequence"
+    assert padding == bytes(4), "Zero padding not intact"
     assert end == expected_end, "Decoded wrong end"
     assert start == expected_start, "Decoded wrong start"
 
@@ -81,6 +96,16 @@ def output_verifier(gen_subscription_output: bytes,
 
     assert device_id == expected_device_id, "Decoded wrong device_id"
 
+    # Verify signature over the plaintext, as the decoder does
+    assert len(gen_subscription_output) == 112, "Wrong subscription length"
+    signed_update: bytes = ("HammerIndustries".encode("ascii")
+                            + expected_device_id.to_bytes(4, 'little')
+                            + expected_channel.to_bytes(4, 'little')
+                            + expected_sequence.to_bytes(8, 'little')
+                            + expected_end.to_bytes(8, 'little')
+                            + expected_start.to_bytes(8, 'little'))
+    eddsa.new(eddsa.import_public_key(signing_public), "rfc8032").verify(signed_update, gen_subscription_output[48:])
+
 
 def anti_cbc_decrypt(key: bytes, iv: bytes, blocks: bytes) -> bytes:
     # Bounds checking
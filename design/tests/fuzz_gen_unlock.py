@@ -0,0 +1,116 @@
+# flake8: noqa
+from pythonfuzz.main import PythonFuzz
+from json import loads
+from base64 import standard_b64decode
+from Crypto.Cipher import AES
+import sys, os
+sys.path.append(os.path.join(os.path.dirname(__file__), '..'))
+from ectf25_design.gen_unlock import gen_unlock
+from ectf25_design.gen_secrets import gen_secrets
+
+
+EXPECTED_TYPE_ERRORS = {
+    "secrets is not a byte-string",
+    "device_id is not an int",
+    "epoch is not an int"
+}
+
+EXPECTED_VALUE_ERRORS = {
+    "Found improper amount of secret pairs for channel",
+    "Could not find master secret pair",
+    "Found invalid AES key: not 256 bits",
+    "Found invalid CBC IV: not 128 bits",
+    "device_id is not representable as u32",
+    "epoch is not representable as u32"
+}
+
+
+def input_transformer(buf: bytes) -> tuple[bytes, int, int]:
+    secrets: bytes
+    device_id: int
+    epoch: int
+    if len(buf) < 8:
+        raise ValueError("Insufficient buffer length")
+    device_id = int.from_bytes(buf[0:4], 'little')
+    epoch = int.from_bytes(buf[4:8], 'little')
+    secrets = gen_secrets([])
+    return (secrets, device_id, epoch)
+
+
+def output_verifier(gen_unlock_output: bytes,
+                    secrets: bytes,
+                    expected_device_id: int,
+                    expected_epoch: int):
+    # Recover secrets
+    secrets_data: dict[str, list[str]] = loads(secrets.decode("utf-8"))
+    secrets_data.pop("verifying")
+    secrets: dict[str, tuple[bytes, bytes]] = {k:
+                                               (standard_b64decode(v[0]), standard_b64decode(v[1]))
+                                               for k, v in secrets_data.items()}
+
+    # Decrypt package master layer
+    decoded_unlock: bytes = anti_cbc_decrypt(secrets["master"][0],
+                                             secrets["master"][1],
+                                             gen_unlock_output)
+    device_id: int = int.from_bytes(decoded_unlock[0:4], 'little')
+    epoch: int = int.from_bytes(decoded_unlock[4:8], 'little')
+
+    assert device_id == expected_device_id, "Decoded wrong device_id"
+    assert epoch == expected_epoch, "Decoded wrong epoch"
+    assert decoded_unlock[8:16] == b'\x00' * 8, "Decoded wrong padding"
+
+
+def anti_cbc_decrypt(key: bytes, iv: bytes, blocks: bytes) -> bytes:
+    # Bounds checking
+    if type(key) is not bytes:
+        raise TypeError("key is not a byte-string")
+    if len(key) != 32:
+        raise ValueError("key is not 256 bits")
+    if type(iv) is not bytes:
+        raise TypeError("iv is not a byte-string")
+    if len(iv) != 16:
+        raise ValueError("iv is not 128 bits")
+    if type(blocks) is not bytes:
+        raise TypeError("blocks is not a byte-string")
+    if len(blocks) == 0:
+        raise ValueError("blocks is empty")
+    if len(blocks) % 16 != 0:
+        raise ValueError("blocks is not a multiple of 128 bits long")
+
+    # Anti CBC Decrypt
+    cipher: AES.EcbMode = AES.new(key, AES.MODE_ECB)
+    output: bytes = bytes()
+    cbc_intermediate: bytes = iv
+    for block in (blocks[i:i+16] for i in range(0, len(blocks), 16)):
+        aes_out: bytes = cipher.encrypt(block)
+        output += bytes((_a ^ _b for _a, _b in zip(aes_out, cbc_intermediate)))
+        cbc_intermediate = block
+    return output
+
+
+def fuzz(buf: bytes):
+    inputs = None
+    try:
+        inputs = input_transformer(buf)
+    except ValueError as e:
+        if e.args[0] != "Insufficient buffer length":
+            raise e
+    else:
+        gen_unlock_output = None
+        try:
+            gen_unlock_output = gen_unlock(*inputs)
+        except TypeError as e:
+            if e.args[0] not in EXPECTED_TYPE_ERRORS:
+                raise e
+        except ValueError as e:
+            if e.args[0] not in EXPECTED_VALUE_ERRORS:
+                raise e
+        else:
+            output_verifier(gen_unlock_output, *inputs)
+
+
+fuzz_exec = PythonFuzz(fuzz)
+
+
+if __name__ == '__main__':
+    fuzz_exec()
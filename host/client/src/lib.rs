@@ -0,0 +1,303 @@
+//! Host client for the decoder
+//! Speaks the same protocol as the firmware's `message` module, with the
+//! constants from `decoder_types::protocol`, over a serial port or any `Read + Write`
+//! Contains:
+//! - Message framing, chunking, and ACKs
+//! - Typed list, subscribe, decode, extended decode, decode batch, unlock, and resync commands
+//! - Parsing of the decoder's ERR messages
+//! - Frame priorities from free channels
+
+use std::fmt;
+use std::io;
+use std::io::{Read, Write};
+
+use decoder_types::protocol::{header_bytes, HEADER_SIZE, CHUNK_SIZE, LIST_ENTRY_SIZE, DECODE_EXTENDED_HEADER_SIZE, MAX_BATCH_FRAMES, MAGIC_BYTE};
+use decoder_types::protocol::{ACK_OPCODE, DEBUG_OPCODE, DECODE_BATCH_OPCODE, DECODE_EXTENDED_OPCODE, DECODE_OPCODE, ERR_OPCODE, LIST_OPCODE, RESYNC_OPCODE, UNLOCK_OPCODE, UPDATE_OPCODE};
+
+pub use decoder_types::protocol::FramePriority;
+
+#[cfg(feature = "serial")]
+pub mod serial;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SubscriptionInfo {
+    pub channel_id: u32,
+    pub start: u64,
+    pub end: u64
+}
+
+// A frame decoded in a batch, with the priority of the channel it was on
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodedFrame {
+    pub frame: Vec<u8>,
+    pub priority: FramePriority
+}
+
+// A frame with the channel and timestamp the decoder verified it was encoded with
+#[derive(Debug, Clone, PartialEq)]
+pub struct VerifiedFrame {
+    pub channel_id: u32,
+    pub timestamp: u64,
+    pub priority: FramePriority,
+    pub frame: Vec<u8>
+}
+
+// An ERR message, the decoder sends its error's Debug form
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecoderError {
+    pub message: String
+}
+
+impl DecoderError {
+    // Variant names from the outermost error inwards,
+    // e.g. ["SecureMemoryError", "NoSubscription"] for "SecureMemoryError(NoSubscription)"
+    pub fn variants(&self) -> Vec<&str> {
+        let mut variants = Vec::new();
+        let mut rest = self.message.as_str();
+        loop {
+            let name_length = rest.find(|c: char| !c.is_ascii_alphanumeric() && c != '_').unwrap_or(rest.len());
+            let name = &rest[..name_length];
+            if !name.starts_with(|c: char| c.is_ascii_uppercase()) { break; }
+            variants.push(name);
+            match rest[name_length..].strip_prefix('(') {
+                Some(inner) => rest = inner,
+                None => break
+            }
+        }
+        variants
+    }
+
+    pub fn is(&self, variant: &str) -> bool {
+        self.variants().contains(&variant)
+    }
+}
+
+impl fmt::Display for DecoderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Decoder returned ERROR: {}", self.message)
+    }
+}
+
+#[derive(Debug)]
+pub enum ClientError {
+    Io(io::Error),
+    Decoder(DecoderError),
+    UnexpectedOpcode { expected: u8, received: u8 },
+    MalformedResponse(&'static str),
+    BodyTooLong(usize)
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientError::Io(error) => write!(f, "{}", error),
+            ClientError::Decoder(error) => write!(f, "{}", error),
+            ClientError::UnexpectedOpcode { expected, received } => write!(f, "Expected opcode {:#04x}, got {:#04x}", expected, received),
+            ClientError::MalformedResponse(reason) => write!(f, "Bad response: {}", reason),
+            ClientError::BodyTooLong(length) => write!(f, "Message body of {} bytes does not fit the u16 length", length)
+        }
+    }
+}
+
+impl std::error::Error for ClientError {}
+
+impl From<io::Error> for ClientError {
+    fn from(error: io::Error) -> Self {
+        ClientError::Io(error)
+    }
+}
+
+pub struct DecoderClient<T: Read + Write> {
+    transport: T
+}
+
+impl<T: Read + Write> DecoderClient<T> {
+    pub fn new(transport: T) -> Self {
+        DecoderClient { transport }
+    }
+
+    pub fn into_inner(self) -> T {
+        self.transport
+    }
+
+    pub fn list(&mut self) -> Result<Vec<SubscriptionInfo>, ClientError> {
+        self.send_message(LIST_OPCODE, &[])?;
+        let body = self.receive_response(LIST_OPCODE)?;
+        if body.len() < 4 { return Err(ClientError::MalformedResponse("list body is missing the subscription count")); }
+        let count = u32::from_le_bytes([body[0], body[1], body[2], body[3]]) as usize;
+        let entries = &body[4..];
+        if entries.len() != count * LIST_ENTRY_SIZE { return Err(ClientError::MalformedResponse("list body length does not match the subscription count")); }
+        Ok(entries.chunks_exact(LIST_ENTRY_SIZE).map(|entry| SubscriptionInfo {
+            channel_id: u32::from_le_bytes(entry[0..4].try_into().unwrap()),
+            start: u64::from_le_bytes(entry[4..12].try_into().unwrap()),
+            end: u64::from_le_bytes(entry[12..20].try_into().unwrap())
+        }).collect())
+    }
+
+    // `subscription` is a subscription update package from gen_subscription
+    pub fn subscribe(&mut self, subscription: &[u8]) -> Result<(), ClientError> {
+        self.send_message(UPDATE_OPCODE, subscription)?;
+        self.receive_empty_response(UPDATE_OPCODE)
+    }
+
+    // Returns the decoded frame
+    pub fn decode(&mut self, frame: &[u8]) -> Result<Vec<u8>, ClientError> {
+        self.send_message(DECODE_OPCODE, frame)?;
+        self.receive_response(DECODE_OPCODE)
+    }
+
+    // Like decode, but also returns the frame's metadata as authenticated by the decoder,
+    // so frames can be ordered and routed by it rather than by what was sent
+    pub fn decode_extended(&mut self, frame: &[u8]) -> Result<VerifiedFrame, ClientError> {
+        self.send_message(DECODE_EXTENDED_OPCODE, frame)?;
+        let body = self.receive_response(DECODE_EXTENDED_OPCODE)?;
+        if body.len() < DECODE_EXTENDED_HEADER_SIZE { return Err(ClientError::MalformedResponse("extended decode body is missing the frame metadata")); }
+        let priority = match FramePriority::from_byte(body[12]) {
+            Some(priority) => priority,
+            None => return Err(ClientError::MalformedResponse("extended decode body has an unknown priority"))
+        };
+        Ok(VerifiedFrame {
+            channel_id: u32::from_le_bytes(body[0..4].try_into().unwrap()),
+            timestamp: u64::from_le_bytes(body[4..12].try_into().unwrap()),
+            priority,
+            frame: body[DECODE_EXTENDED_HEADER_SIZE..].to_vec()
+        })
+    }
+
+    // Decodes up to MAX_BATCH_FRAMES frames in one exchange, each succeeding or failing on its own
+    pub fn decode_batch(&mut self, frames: &[&[u8]]) -> Result<Vec<Result<DecodedFrame, DecoderError>>, ClientError> {
+        if frames.len() > MAX_BATCH_FRAMES { return Err(ClientError::BodyTooLong(frames.len())); }
+        let mut body: Vec<u8> = Vec::new();
+        body.extend_from_slice(&(frames.len() as u32).to_le_bytes());
+        for frame in frames {
+            let length: u16 = match frame.len().try_into() {
+                Ok(length) => length,
+                Err(_) => return Err(ClientError::BodyTooLong(frame.len()))
+            };
+            body.extend_from_slice(&length.to_le_bytes());
+            body.extend_from_slice(frame);
+        }
+        self.send_message(DECODE_BATCH_OPCODE, &body)?;
+        let body = self.receive_response(DECODE_BATCH_OPCODE)?;
+        parse_decode_batch(&body)
+    }
+
+    // `unlock` is an unlock package from gen_unlock
+    pub fn unlock(&mut self, unlock: &[u8]) -> Result<(), ClientError> {
+        self.send_message(UNLOCK_OPCODE, unlock)?;
+        self.receive_empty_response(UNLOCK_OPCODE)
+    }
+
+    // `resync` is a resync package from gen_resync
+    pub fn resync(&mut self, resync: &[u8]) -> Result<(), ClientError> {
+        self.send_message(RESYNC_OPCODE, resync)?;
+        self.receive_empty_response(RESYNC_OPCODE)
+    }
+
+    // Sends the header and every chunk, waiting for the ACK after each
+    pub fn send_message(&mut self, opcode: u8, body: &[u8]) -> Result<(), ClientError> {
+        let length: u16 = match body.len().try_into() {
+            Ok(length) => length,
+            Err(_) => return Err(ClientError::BodyTooLong(body.len()))
+        };
+        self.write(&header_bytes(opcode, length))?;
+        self.receive_ack()?;
+        for chunk in body.chunks(CHUNK_SIZE) {
+            self.write(chunk)?;
+            self.receive_ack()?;
+        }
+        Ok(())
+    }
+
+    // Receives the next message that is not DEBUG, with ERR turned into an error
+    pub fn receive_message(&mut self) -> Result<(u8, Vec<u8>), ClientError> {
+        loop {
+            let (opcode, body) = self.receive_raw_message()?;
+            match opcode {
+                // Debug output is only ever logged by the reference tools
+                DEBUG_OPCODE => continue,
+                ERR_OPCODE => return Err(ClientError::Decoder(DecoderError { message: String::from_utf8_lossy(&body).into_owned() })),
+                _ => return Ok((opcode, body))
+            }
+        }
+    }
+
+    fn receive_response(&mut self, opcode: u8) -> Result<Vec<u8>, ClientError> {
+        let (received, body) = self.receive_message()?;
+        if received != opcode { return Err(ClientError::UnexpectedOpcode { expected: opcode, received }); }
+        Ok(body)
+    }
+
+    fn receive_empty_response(&mut self, opcode: u8) -> Result<(), ClientError> {
+        let body = self.receive_response(opcode)?;
+        if !body.is_empty() { return Err(ClientError::MalformedResponse("expected an empty body")); }
+        Ok(())
+    }
+
+    fn receive_ack(&mut self) -> Result<(), ClientError> {
+        let (opcode, body) = self.receive_message()?;
+        if opcode != ACK_OPCODE { return Err(ClientError::UnexpectedOpcode { expected: ACK_OPCODE, received: opcode }); }
+        if !body.is_empty() { return Err(ClientError::MalformedResponse("ACK with a body")); }
+        Ok(())
+    }
+
+    // ACKs the header and every chunk, except for ACK and DEBUG messages
+    fn receive_raw_message(&mut self) -> Result<(u8, Vec<u8>), ClientError> {
+        let (opcode, length) = self.receive_header()?;
+        let acknowledged = opcode != ACK_OPCODE && opcode != DEBUG_OPCODE;
+        if acknowledged { self.send_ack()?; }
+        let mut body = vec![0u8; length as usize];
+        for chunk in body.chunks_mut(CHUNK_SIZE) {
+            self.transport.read_exact(chunk)?;
+            if acknowledged { self.send_ack()?; }
+        }
+        Ok((opcode, body))
+    }
+
+    // Skips anything before the magic byte, like the reference tools
+    fn receive_header(&mut self) -> Result<(u8, u16), ClientError> {
+        let mut header = [0u8; HEADER_SIZE];
+        while header[0] != MAGIC_BYTE {
+            self.transport.read_exact(&mut header[..1])?;
+        }
+        self.transport.read_exact(&mut header[1..])?;
+        Ok((header[1], u16::from_le_bytes([header[2], header[3]])))
+    }
+
+    fn send_ack(&mut self) -> Result<(), ClientError> {
+        self.write(&header_bytes(ACK_OPCODE, 0))
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> Result<(), ClientError> {
+        self.transport.write_all(bytes)?;
+        self.transport.flush()?;
+        Ok(())
+    }
+}
+
+// u32 frame count, then per frame a status opcode, priority, u16 length, and the frame or error message
+fn parse_decode_batch(body: &[u8]) -> Result<Vec<Result<DecodedFrame, DecoderError>>, ClientError> {
+    if body.len() < 4 { return Err(ClientError::MalformedResponse("batch body is missing the frame count")); }
+    let count = u32::from_le_bytes([body[0], body[1], body[2], body[3]]) as usize;
+    let mut frames = Vec::with_capacity(count);
+    let mut rest = &body[4..];
+    for _ in 0..count {
+        if rest.len() < 4 { return Err(ClientError::MalformedResponse("batch entry is truncated")); }
+        let (status, priority, length) = (rest[0], rest[1], u16::from_le_bytes([rest[2], rest[3]]) as usize);
+        rest = &rest[4..];
+        let priority = match FramePriority::from_byte(priority) {
+            Some(priority) => priority,
+            None => return Err(ClientError::MalformedResponse("batch entry has an unknown priority"))
+        };
+        if rest.len() < length { return Err(ClientError::MalformedResponse("batch entry is truncated")); }
+        let data = rest[..length].to_vec();
+        rest = &rest[length..];
+        match status {
+            DECODE_OPCODE => frames.push(Ok(DecodedFrame { frame: data, priority })),
+            ERR_OPCODE => frames.push(Err(DecoderError { message: String::from_utf8_lossy(&data).into_owned() })),
+            _ => return Err(ClientError::MalformedResponse("batch entry has an unknown status"))
+        }
+    }
+    if !rest.is_empty() { return Err(ClientError::MalformedResponse("batch body is longer than its frames")); }
+    Ok(frames)
+}
@@ -0,0 +1,25 @@
+//! Serial port transport, with the settings the decoder's UART is built with
+
+use std::time::Duration;
+
+use serialport::SerialPort;
+
+use crate::{ClientError, DecoderClient};
+
+// 115200 8N1, as configured in the firmware's main
+pub const BAUD_RATE: u32 = 115200;
+
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+impl DecoderClient<Box<dyn SerialPort>> {
+    pub fn open_serial(path: &str) -> Result<Self, ClientError> {
+        DecoderClient::open_serial_with_timeout(path, DEFAULT_TIMEOUT)
+    }
+
+    pub fn open_serial_with_timeout(path: &str, timeout: Duration) -> Result<Self, ClientError> {
+        match serialport::new(path, BAUD_RATE).timeout(timeout).open() {
+            Ok(port) => Ok(DecoderClient::new(port)),
+            Err(error) => Err(ClientError::Io(error.into()))
+        }
+    }
+}
@@ -0,0 +1,453 @@
+//! Client tests against the host simulator
+//! Packages come from the Rust design tools, so these run the full
+//! encoder -> client -> decoder logic path without a board.
+
+use std::collections::VecDeque;
+use std::io;
+use std::io::{Read, Write};
+
+use decoder_client::{ClientError, DecodedFrame, DecoderClient, FramePriority, SubscriptionInfo, VerifiedFrame};
+use decoder_sim::{DelayConfig, DelayConfigs, DelayPolicy, DelayTrigger, FreeChannel, Simulator};
+use decoder_types::protocol::{header_bytes, ACK_OPCODE, DEBUG_OPCODE, LIST_OPCODE};
+use decoder_types::SUBSCRIPTIONS_CAPACITY;
+use ectf25_design::{gen_secrets, gen_subscription, gen_subscription_with_sequence, gen_unlock, Encoder};
+
+const DECODER_ID: u32 = 0xdeadbeef;
+
+// No random delays, so `elapsed_us` only adds up lockout back-off
+const NO_DELAYS: DelayConfigs = DelayConfigs {
+    list: DelayConfig { policy: DelayPolicy::None, trigger: DelayTrigger::Always },
+    update: DelayConfig { policy: DelayPolicy::None, trigger: DelayTrigger::Always },
+    decode: DelayConfig { policy: DelayPolicy::None, trigger: DelayTrigger::Always },
+    receive: DelayConfig { policy: DelayPolicy::None, trigger: DelayTrigger::Always }
+};
+
+// Secrets, provisioning key and a decoder built from them
+fn setup(channels: &[u32]) -> (Vec<u8>, Vec<u8>, Simulator) {
+    let (secrets, provisioning_key) = gen_secrets(channels).unwrap();
+    let simulator = Simulator::new(&secrets, DECODER_ID).unwrap();
+    (secrets, provisioning_key, simulator)
+}
+
+#[test]
+fn list_starts_empty() {
+    let (_, _, simulator) = setup(&[1]);
+    let mut client = DecoderClient::new(simulator.power_on());
+    assert_eq!(client.list().unwrap(), vec![]);
+}
+
+#[test]
+fn subscribe_then_list_and_decode() {
+    let (secrets, provisioning_key, simulator) = setup(&[1, 2]);
+    let encoder = Encoder::new(&secrets).unwrap();
+    let mut client = DecoderClient::new(simulator.power_on());
+
+    client.subscribe(&gen_subscription(&secrets, &provisioning_key, DECODER_ID, 10, 1000, 2).unwrap()).unwrap();
+    client.subscribe(&gen_subscription(&secrets, &provisioning_key, DECODER_ID, 0, u64::MAX, 1).unwrap()).unwrap();
+    // Listed by channel, whatever the order of the updates
+    assert_eq!(client.list().unwrap(), vec![
+        SubscriptionInfo { channel_id: 1, start: 0, end: u64::MAX },
+        SubscriptionInfo { channel_id: 2, start: 10, end: 1000 }
+    ]);
+
+    for (timestamp, length) in [(10u64, 1usize), (11, 16), (12, 17), (500, 63), (1000, 64)] {
+        let frame: Vec<u8> = (0..length).map(|i| (i as u8).wrapping_mul(31).wrapping_add(timestamp as u8)).collect();
+        assert_eq!(client.decode(&encoder.encode(2, &frame, timestamp).unwrap()).unwrap(), frame);
+    }
+}
+
+#[test]
+fn decodes_emergency_channel_without_subscription() {
+    let (secrets, _, simulator) = setup(&[1]);
+    let encoder = Encoder::new(&secrets).unwrap();
+    let mut client = DecoderClient::new(simulator.power_on());
+    assert_eq!(client.decode(&encoder.encode(0, b"emergency", 0).unwrap()).unwrap(), b"emergency");
+}
+
+#[test]
+fn extended_decode_returns_verified_metadata() {
+    let (secrets, provisioning_key, simulator) = setup(&[1]);
+    let encoder = Encoder::new(&secrets).unwrap();
+    let mut client = DecoderClient::new(simulator.power_on());
+    client.subscribe(&gen_subscription(&secrets, &provisioning_key, DECODER_ID, 0, u64::MAX, 1).unwrap()).unwrap();
+
+    assert_eq!(client.decode_extended(&encoder.encode(1, b"subscribed", 1 << 40).unwrap()).unwrap(), VerifiedFrame {
+        channel_id: 1,
+        timestamp: 1 << 40,
+        priority: FramePriority::Normal,
+        frame: b"subscribed".to_vec()
+    });
+    assert_eq!(client.decode_extended(&encoder.encode(0, &[0xAA; 64], u64::MAX).unwrap()).unwrap(), VerifiedFrame {
+        channel_id: 0,
+        timestamp: u64::MAX,
+        priority: FramePriority::Emergency,
+        frame: vec![0xAA; 64]
+    });
+    // Errors are the same as for a plain decode
+    match client.decode_extended(&encoder.encode(1, b"replayed", 5).unwrap()) {
+        Err(ClientError::Decoder(error)) => assert!(error.is("FramePast"), "{}", error),
+        other => panic!("expected a decoder error, got {:?}", other)
+    }
+}
+
+#[test]
+fn free_channels_decode_with_their_priority() {
+    let (secrets, provisioning_key, simulator) = setup(&[1, 2, 3]);
+    let simulator = simulator.with_free_channels(&[
+        FreeChannel { channel_id: 0, priority: FramePriority::Emergency },
+        FreeChannel { channel_id: 2, priority: FramePriority::Normal },
+        FreeChannel { channel_id: 3, priority: FramePriority::Emergency }
+    ]);
+    let encoder = Encoder::new(&secrets).unwrap();
+    let mut client = DecoderClient::new(simulator.power_on());
+    client.subscribe(&gen_subscription(&secrets, &provisioning_key, DECODER_ID, 0, 100, 1).unwrap()).unwrap();
+
+    let frames: Vec<Vec<u8>> = (0..4u32).map(|channel| encoder.encode(channel, format!("channel {}", channel).as_bytes(), channel as u64).unwrap()).collect();
+    let batch: Vec<&[u8]> = frames.iter().map(|frame| frame.as_slice()).collect();
+    let decoded: Vec<DecodedFrame> = client.decode_batch(&batch).unwrap().into_iter().map(|frame| frame.unwrap()).collect();
+    assert_eq!(decoded.iter().map(|frame| frame.priority).collect::<Vec<_>>(), vec![FramePriority::Emergency, FramePriority::Normal, FramePriority::Normal, FramePriority::Emergency]);
+    assert_eq!(decoded[3].frame, b"channel 3");
+
+    // A subscription to a free channel would never be used
+    match client.subscribe(&gen_subscription(&secrets, &provisioning_key, DECODER_ID, 0, 100, 3).unwrap()) {
+        Err(ClientError::Decoder(error)) => assert_eq!(error.variants(), vec!["InvalidSubscriptionChannel"]),
+        other => panic!("expected a decoder error, got {:?}", other)
+    }
+    assert_eq!(client.list().unwrap().len(), 1);
+}
+
+#[test]
+fn decoder_errors_are_parsed() {
+    let (secrets, provisioning_key, simulator) = setup(&[1, 2]);
+    let encoder = Encoder::new(&secrets).unwrap();
+    let mut client = DecoderClient::new(simulator.power_on());
+
+    let error = match client.decode(&encoder.encode(1, b"frame", 5).unwrap()) {
+        Err(ClientError::Decoder(error)) => error,
+        other => panic!("expected a decoder error, got {:?}", other)
+    };
+    assert_eq!(error.variants(), vec!["SecureMemoryError", "NoSubscription"]);
+
+    client.subscribe(&gen_subscription(&secrets, &provisioning_key, DECODER_ID, 100, 200, 1).unwrap()).unwrap();
+    match client.decode(&encoder.encode(1, b"frame", 50).unwrap()) {
+        Err(ClientError::Decoder(error)) => assert!(error.is("SubscriptionFuture"), "{}", error),
+        other => panic!("expected a decoder error, got {:?}", other)
+    }
+
+    // Rejected before the header is ACKed, on length alone
+    match client.decode(&[0; 65]) {
+        Err(ClientError::Decoder(error)) => assert_eq!(error.variants(), vec!["InvalidLength"]),
+        other => panic!("expected a decoder error, got {:?}", other)
+    }
+
+    // The client is still in step with the decoder after every error
+    assert_eq!(client.list().unwrap().len(), 1);
+}
+
+#[test]
+fn rejects_subscription_for_another_decoder() {
+    let (secrets, provisioning_key, simulator) = setup(&[1]);
+    let mut client = DecoderClient::new(simulator.power_on());
+    match client.subscribe(&gen_subscription(&secrets, &provisioning_key, DECODER_ID + 1, 0, 100, 1).unwrap()) {
+        Err(ClientError::Decoder(error)) => assert!(error.is("InvalidDecoderID"), "{}", error),
+        other => panic!("expected a decoder error, got {:?}", other)
+    }
+    assert_eq!(client.list().unwrap(), vec![]);
+}
+
+#[test]
+fn decode_batch_spans_several_chunks() {
+    let (secrets, provisioning_key, simulator) = setup(&[1]);
+    let encoder = Encoder::new(&secrets).unwrap();
+    let mut client = DecoderClient::new(simulator.power_on());
+    client.subscribe(&gen_subscription(&secrets, &provisioning_key, DECODER_ID, 0, 100, 1).unwrap()).unwrap();
+
+    // Sixteen 112-byte frames need eight chunks each way
+    let frames: Vec<Vec<u8>> = (0..16u64).map(|timestamp| encoder.encode(1, &[timestamp as u8; 64], timestamp).unwrap()).collect();
+    let mut batch: Vec<&[u8]> = frames.iter().map(|frame| frame.as_slice()).collect();
+    // A replayed frame fails on its own without failing the batch
+    batch[15] = &frames[3];
+    let decoded = client.decode_batch(&batch).unwrap();
+    assert_eq!(decoded.len(), 16);
+    for (timestamp, frame) in decoded.iter().take(15).enumerate() {
+        assert_eq!(frame.as_ref().unwrap().frame, vec![timestamp as u8; 64]);
+    }
+    assert!(decoded[15].as_ref().unwrap_err().is("FramePast"));
+}
+
+#[test]
+fn lists_every_window_and_merges_touching_ones() {
+    let (secrets, provisioning_key, simulator) = setup(&[1]);
+    let encoder = Encoder::new(&secrets).unwrap();
+    let mut client = DecoderClient::new(simulator.power_on());
+
+    // Twenty disjoint windows, listed in more than one chunk
+    for window in (0..20u64).rev() {
+        client.subscribe(&gen_subscription(&secrets, &provisioning_key, DECODER_ID, window * 100, window * 100 + 9, 1).unwrap()).unwrap();
+    }
+    // Bridges the first two, and extends the third up to the fourth
+    client.subscribe(&gen_subscription(&secrets, &provisioning_key, DECODER_ID, 5, 105, 1).unwrap()).unwrap();
+    client.subscribe(&gen_subscription(&secrets, &provisioning_key, DECODER_ID, 210, 299, 1).unwrap()).unwrap();
+    let mut expected = vec![
+        SubscriptionInfo { channel_id: 1, start: 0, end: 109 },
+        SubscriptionInfo { channel_id: 1, start: 200, end: 309 }
+    ];
+    expected.extend((4..20u64).map(|window| SubscriptionInfo { channel_id: 1, start: window * 100, end: window * 100 + 9 }));
+    assert_eq!(client.list().unwrap(), expected);
+
+    // Every window is checked, and a gap is reported against the next one
+    assert_eq!(client.decode(&encoder.encode(1, b"first", 50).unwrap()).unwrap(), b"first");
+    assert!(client.decode(&encoder.encode(1, b"gap", 350).unwrap()).unwrap_err().to_string().contains("SubscriptionFuture(1, 400)"));
+    assert_eq!(client.decode(&encoder.encode(1, b"later", 1905).unwrap()).unwrap(), b"later");
+    assert!(client.decode(&encoder.encode(1, b"past", 1910).unwrap()).unwrap_err().to_string().contains("SubscriptionPast(1, 1909)"));
+}
+
+#[test]
+fn rejects_updates_that_are_not_newer() {
+    let (secrets, provisioning_key, simulator) = setup(&[1, 2]);
+    let mut client = DecoderClient::new(simulator.power_on());
+    let update = |start: u64, end: u64, channel: u32, sequence: u64| gen_subscription_with_sequence(&secrets, &provisioning_key, DECODER_ID, start, end, channel, sequence).unwrap();
+
+    let first = update(0, 100, 1, 10);
+    client.subscribe(&first).unwrap();
+    // Replaying the same update, or an older one, cannot bring back its window
+    assert!(client.subscribe(&first).unwrap_err().to_string().contains("SubscriptionSequenceStale(1, 10)"));
+    assert!(client.subscribe(&update(500, 600, 1, 9)).unwrap_err().to_string().contains("SubscriptionSequenceStale(1, 10)"));
+    // Genuinely signed, so resending it is no authentication failure and earns no lockout
+    for _ in 0..8 {
+        assert!(client.subscribe(&first).unwrap_err().to_string().contains("SubscriptionSequenceStale(1, 10)"));
+    }
+    assert!(simulator.elapsed_us() < 100_000, "{} us of delays", simulator.elapsed_us());
+    client.subscribe(&update(200, 300, 1, 11)).unwrap();
+    // Sequence numbers are per channel
+    client.subscribe(&update(0, 100, 2, 1)).unwrap();
+    assert_eq!(client.list().unwrap(), vec![
+        SubscriptionInfo { channel_id: 1, start: 0, end: 100 },
+        SubscriptionInfo { channel_id: 1, start: 200, end: 300 },
+        SubscriptionInfo { channel_id: 2, start: 0, end: 100 }
+    ]);
+}
+
+#[test]
+fn reclaimed_channels_still_reject_older_updates() {
+    let full = SUBSCRIPTIONS_CAPACITY as u32;
+    let (secrets, provisioning_key, simulator) = setup(&(1..=full + 1).collect::<Vec<u32>>());
+    let encoder = Encoder::new(&secrets).unwrap();
+    let update = |start: u64, end: u64, channel: u32, sequence: u64| gen_subscription_with_sequence(&secrets, &provisioning_key, DECODER_ID, start, end, channel, sequence).unwrap();
+    let first = update(0, 10, 1, 5);
+    {
+        let mut client = DecoderClient::new(simulator.power_on());
+        client.subscribe(&first).unwrap();
+        for channel in 2..=full {
+            client.subscribe(&update(0, 1000, channel, 1)).unwrap();
+        }
+        // Channel 1 expires, and a new channel takes its room
+        client.decode(&encoder.encode(2, b"frame", 100).unwrap()).unwrap();
+        client.subscribe(&update(0, 1000, full + 1, 1)).unwrap();
+        assert!(client.list().unwrap().iter().all(|info| info.channel_id != 1));
+
+        // Without a window left, its old update is still stale
+        assert!(client.subscribe(&first).unwrap_err().to_string().contains("SubscriptionSequenceStale(1, 5)"));
+        assert!(client.subscribe(&update(0, 1000, 1, 4)).unwrap_err().to_string().contains("SubscriptionSequenceStale(1, 5)"));
+    }
+    // Even across a power cycle
+    let mut client = DecoderClient::new(simulator.power_on());
+    assert!(client.subscribe(&first).unwrap_err().to_string().contains("SubscriptionSequenceStale(1, 5)"));
+}
+
+#[test]
+fn rejects_updates_not_signed_by_the_provisioning_key() {
+    let (secrets, provisioning_key, simulator) = setup(&[1]);
+    let mut client = DecoderClient::new(simulator.power_on());
+    let signature_invalid = |result: Result<(), ClientError>| match result {
+        Err(ClientError::Decoder(error)) => assert!(error.is("SubscriptionSignatureInvalid"), "{}", error),
+        other => panic!("expected a decoder error, got {:?}", other)
+    };
+
+    // Every AES key read out of a decoder, but a signing key of the attacker's own
+    let (other_secrets, other_provisioning_key) = gen_secrets(&[1]).unwrap();
+    let secrets_text = String::from_utf8(secrets.clone()).unwrap();
+    let other_text = String::from_utf8(other_secrets).unwrap();
+    let forged_secrets = format!("{}{}", &secrets_text[..secrets_text.find("\"verifying\"").unwrap()], &other_text[other_text.find("\"verifying\"").unwrap()..]);
+    signature_invalid(client.subscribe(&gen_subscription(forged_secrets.as_bytes(), &other_provisioning_key, DECODER_ID, 0, 100, 1).unwrap()));
+
+    // A genuine update with its signature altered
+    let mut tampered = gen_subscription(&secrets, &provisioning_key, DECODER_ID, 0, 100, 1).unwrap();
+    *tampered.last_mut().unwrap() ^= 1;
+    signature_invalid(client.subscribe(&tampered));
+    assert_eq!(client.list().unwrap(), vec![]);
+
+    client.subscribe(&gen_subscription(&secrets, &provisioning_key, DECODER_ID, 0, 100, 1).unwrap()).unwrap();
+    assert_eq!(client.list().unwrap(), vec![SubscriptionInfo { channel_id: 1, start: 0, end: 100 }]);
+}
+
+#[test]
+fn subscriptions_survive_power_cycles() {
+    let (secrets, provisioning_key, simulator) = setup(&[1]);
+    let encoder = Encoder::new(&secrets).unwrap();
+    {
+        let mut client = DecoderClient::new(simulator.power_on());
+        client.subscribe(&gen_subscription(&secrets, &provisioning_key, DECODER_ID, 0, 100, 1).unwrap()).unwrap();
+        client.decode(&encoder.encode(1, b"frame", 50).unwrap()).unwrap();
+    }
+    let mut client = DecoderClient::new(simulator.power_on());
+    assert_eq!(client.list().unwrap(), vec![SubscriptionInfo { channel_id: 1, start: 0, end: 100 }]);
+    // The last timestamp is RAM state and starts over after reset
+    client.decode(&encoder.encode(1, b"frame", 10).unwrap()).unwrap();
+}
+
+#[test]
+fn unlock_is_accepted_once() {
+    let (secrets, _, simulator) = setup(&[1]);
+    let mut client = DecoderClient::new(simulator.power_on());
+    let unlock = gen_unlock(&secrets, DECODER_ID, 0).unwrap();
+    client.unlock(&unlock).unwrap();
+    match client.unlock(&unlock) {
+        Err(ClientError::Decoder(error)) => assert!(error.is("UnlockEpochIncorrect"), "{}", error),
+        other => panic!("expected a decoder error, got {:?}", other)
+    }
+    client.unlock(&gen_unlock(&secrets, DECODER_ID, 1).unwrap()).unwrap();
+}
+
+// A frame with its company stamp corrupted, which only a forger would send
+fn forged_frame(secrets: &[u8]) -> Vec<u8> {
+    let mut frame = Encoder::new(secrets).unwrap().encode(0, b"frame", 1).unwrap();
+    *frame.last_mut().unwrap() ^= 1;
+    frame
+}
+
+// Lockout delay served for one more authentication failure, in microseconds
+fn failure_back_off<T: Read + Write>(client: &mut DecoderClient<T>, simulator: &Simulator, forged: &[u8]) -> u64 {
+    let before = simulator.elapsed_us();
+    match client.decode(forged) {
+        Err(ClientError::Decoder(error)) => assert!(error.is("FrameCompanyStampIncorrect"), "{}", error),
+        other => panic!("expected a decoder error, got {:?}", other)
+    }
+    simulator.elapsed_us() - before
+}
+
+#[test]
+fn lockout_back_off_grows_with_failures() {
+    let (secrets, _, simulator) = setup(&[1]);
+    let simulator = simulator.with_delays(NO_DELAYS);
+    let forged = forged_frame(&secrets);
+    let mut client = DecoderClient::new(simulator.power_on());
+
+    // Four free failures, then doubling from 250 ms up to 32 s
+    let back_offs: Vec<u64> = (0..13).map(|_| failure_back_off(&mut client, &simulator, &forged) / 1000).collect();
+    assert_eq!(back_offs, vec![0, 0, 0, 0, 250, 500, 1000, 2000, 4000, 8000, 16000, 32000, 32000]);
+    // Genuine messages are never delayed
+    let before = simulator.elapsed_us();
+    assert_eq!(client.decode(&Encoder::new(&secrets).unwrap().encode(0, b"frame", 1).unwrap()).unwrap(), b"frame");
+    assert_eq!(client.list().unwrap(), vec![]);
+    assert_eq!(simulator.elapsed_us(), before);
+}
+
+#[test]
+fn lockout_survives_power_cycles() {
+    let (secrets, _, simulator) = setup(&[1]);
+    let simulator = simulator.with_delays(NO_DELAYS);
+    let forged = forged_frame(&secrets);
+    {
+        let mut client = DecoderClient::new(simulator.power_on());
+        for _ in 0..6 {
+            failure_back_off(&mut client, &simulator, &forged);
+        }
+    }
+
+    // The back-off of the last failure is served again on boot, before any message
+    let before = simulator.elapsed_us();
+    let mut client = DecoderClient::new(simulator.power_on());
+    assert_eq!(client.list().unwrap(), vec![]);
+    assert_eq!(simulator.elapsed_us() - before, 500_000);
+    // And the count carries on from where it was
+    assert_eq!(failure_back_off(&mut client, &simulator, &forged), 1_000_000);
+}
+
+#[test]
+fn unlock_resets_lockout() {
+    let (secrets, _, simulator) = setup(&[1]);
+    let simulator = simulator.with_delays(NO_DELAYS);
+    let forged = forged_frame(&secrets);
+    {
+        let mut client = DecoderClient::new(simulator.power_on());
+        for _ in 0..6 {
+            failure_back_off(&mut client, &simulator, &forged);
+        }
+        client.unlock(&gen_unlock(&secrets, DECODER_ID, 0).unwrap()).unwrap();
+        // The count starts over, with its free failures
+        for _ in 0..4 {
+            assert_eq!(failure_back_off(&mut client, &simulator, &forged), 0);
+        }
+        assert_eq!(failure_back_off(&mut client, &simulator, &forged), 250_000);
+        client.unlock(&gen_unlock(&secrets, DECODER_ID, 1).unwrap()).unwrap();
+    }
+
+    // Nothing left to serve on boot either
+    let before = simulator.elapsed_us();
+    let mut client = DecoderClient::new(simulator.power_on());
+    assert_eq!(client.list().unwrap(), vec![]);
+    assert_eq!(simulator.elapsed_us(), before);
+    // A replayed unlock is itself an authentication failure and resets nothing
+    match client.unlock(&gen_unlock(&secrets, DECODER_ID, 0).unwrap()) {
+        Err(ClientError::Decoder(error)) => assert!(error.is("UnlockEpochIncorrect"), "{}", error),
+        other => panic!("expected a decoder error, got {:?}", other)
+    }
+    assert_eq!(failure_back_off(&mut client, &simulator, &forged), 0);
+}
+
+// Replays a fixed decoder side, for what the firmware itself never sends
+struct ScriptedTransport {
+    input: VecDeque<u8>,
+    output: Vec<u8>
+}
+
+impl Read for ScriptedTransport {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        let count = buffer.len().min(self.input.len());
+        for (byte, input_byte) in buffer.iter_mut().zip(self.input.drain(..count)) {
+            *byte = input_byte;
+        }
+        Ok(count)
+    }
+}
+
+impl Write for ScriptedTransport {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        self.output.extend_from_slice(bytes);
+        Ok(bytes.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn skips_noise_and_debug_messages() {
+    let mut input: Vec<u8> = Vec::new();
+    input.extend_from_slice(&header_bytes(ACK_OPCODE, 0));
+    input.extend_from_slice(b"boot noise");
+    input.extend_from_slice(&header_bytes(DEBUG_OPCODE, 5));
+    input.extend_from_slice(b"hello");
+    input.extend_from_slice(&header_bytes(LIST_OPCODE, 4));
+    input.extend_from_slice(&0u32.to_le_bytes());
+    let mut client = DecoderClient::new(ScriptedTransport { input: input.into(), output: Vec::new() });
+
+    assert_eq!(client.list().unwrap(), vec![]);
+    // LIST header, then ACKs for the response header and body but never for DEBUG
+    let transport = client.into_inner();
+    let mut expected: Vec<u8> = header_bytes(LIST_OPCODE, 0).to_vec();
+    expected.extend_from_slice(&header_bytes(ACK_OPCODE, 0));
+    expected.extend_from_slice(&header_bytes(ACK_OPCODE, 0));
+    assert_eq!(transport.output, expected);
+}
+
+#[test]
+fn reports_a_closed_transport() {
+    let mut client = DecoderClient::new(ScriptedTransport { input: VecDeque::new(), output: Vec::new() });
+    match client.list() {
+        Err(ClientError::Io(error)) => assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof),
+        other => panic!("expected an I/O error, got {:?}", other)
+    }
+}
@@ -0,0 +1,41 @@
+//! Anti-CBC encryption over AES-256
+//! The chaining lives in `decoder_types::packet`, shared with the decoder
+
+use aes::cipher::{BlockDecrypt, BlockEncrypt, KeyInit};
+use aes::Aes256;
+
+use decoder_types::packet;
+use decoder_types::AesBlock;
+
+use crate::secrets::SecretPair;
+
+pub fn anti_cbc_encrypt(secret: &SecretPair, blocks: &mut [AesBlock]) {
+    let cipher = Aes256::new(&secret.key.into());
+    packet::anti_cbc_encrypt(secret.iv, blocks, |block| {
+        let mut block = block.into();
+        cipher.decrypt_block(&mut block);
+        block.into()
+    });
+}
+
+// What the decoder does to recover the plaintext
+pub fn anti_cbc_decrypt(secret: &SecretPair, blocks: &mut [AesBlock]) {
+    let cipher = Aes256::new(&secret.key.into());
+    packet::anti_cbc_decrypt(secret.iv, blocks, |block| {
+        let mut block = block.into();
+        cipher.encrypt_block(&mut block);
+        block.into()
+    });
+}
+
+pub fn to_blocks(bytes: &[u8]) -> Vec<AesBlock> {
+    bytes.chunks(16).map(|chunk| {
+        let mut block: AesBlock = [0; 16];
+        block[..chunk.len()].copy_from_slice(chunk);
+        block
+    }).collect()
+}
+
+pub fn from_blocks(blocks: &[AesBlock]) -> Vec<u8> {
+    blocks.iter().flatten().copied().collect()
+}
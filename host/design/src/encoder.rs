@@ -0,0 +1,48 @@
+//! Frame encoding
+
+use decoder_types::packet::{frame_metadata_block, frame_padding, COMPANY_STAMP, MAX_FRAME_LENGTH};
+
+use crate::crypto::{anti_cbc_encrypt, from_blocks, to_blocks};
+use crate::secrets::{SecretPair, Secrets};
+use crate::DesignError;
+
+#[derive(Debug, Clone)]
+pub struct Encoder {
+    secrets: Secrets
+}
+
+impl Encoder {
+    pub fn new(secrets: &[u8]) -> Result<Self, DesignError> {
+        let secrets = Secrets::from_json(secrets)?;
+        secrets.require_master_and_channel_0()?;
+        Ok(Encoder { secrets })
+    }
+
+    pub fn encode(&self, channel: u32, frame: &[u8], timestamp: u64) -> Result<Vec<u8>, DesignError> {
+        let channel_secret = self.secrets.channel(channel)?;
+        if frame.is_empty() { return Err(DesignError::InvalidArgument("Cannot encode empty frame")); }
+        if frame.len() > MAX_FRAME_LENGTH { return Err(DesignError::InvalidArgument("Cannot encode frame bigger than 64 bytes")); }
+        let padding = [0; 16];
+        Ok(build_frame(self.secrets.master()?, channel_secret, channel, frame, &padding[..frame_padding(frame.len())], timestamp))
+    }
+}
+
+// Lays out and encrypts a frame from explicit parts without checking them, so tests
+// can build frames `Encoder` never would, such as one under another channel's key
+// or with padding that is not zero. `padding` must fill the frame to a whole block
+pub fn build_frame(master: &SecretPair, channel_secret: &SecretPair, channel: u32, frame: &[u8], padding: &[u8], timestamp: u64) -> Vec<u8> {
+    // Inner layer: company stamp, padded frame, company stamp
+    let mut inner: Vec<u8> = Vec::with_capacity(frame.len() + 48);
+    inner.extend_from_slice(&COMPANY_STAMP);
+    inner.extend_from_slice(frame);
+    inner.extend_from_slice(padding);
+    inner.extend_from_slice(&COMPANY_STAMP);
+    let mut inner_blocks = to_blocks(&inner);
+    anti_cbc_encrypt(channel_secret, &mut inner_blocks);
+
+    // Outer layer: metadata, then the inner layer
+    let mut blocks = vec![frame_metadata_block(timestamp, channel, frame.len() as u32)];
+    blocks.extend(inner_blocks);
+    anti_cbc_encrypt(master, &mut blocks);
+    from_blocks(&blocks)
+}
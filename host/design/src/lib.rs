@@ -0,0 +1,55 @@
+//! Encoder design tools
+//! Rust port of the `ectf25_design` Python package
+//! Contains:
+//! - Secrets generation and parsing
+//! - Subscription update, unlock and clock resync generation
+//! - Frame encoding
+//!
+//! Packet layouts come from `decoder_types::packet`, the same definitions the
+//! decoder firmware parses with, and every output is byte-for-byte identical
+//! to the Python tools.
+
+use std::fmt;
+
+pub mod crypto;
+pub mod encoder;
+pub mod repr;
+pub mod resync;
+pub mod secrets;
+pub mod subscription;
+pub mod unlock;
+
+pub use encoder::Encoder;
+pub use resync::{gen_resync, gen_resync_with_sequence};
+pub use secrets::{gen_secrets, provisioning_key_path, SecretPair, Secrets, SigningPair};
+pub use subscription::{gen_subscription, gen_subscription_with_sequence};
+pub use unlock::gen_unlock;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DesignError {
+    InvalidSecrets(String),
+    NoMasterSecret,
+    NoMasterOrChannel0Secret,
+    NoChannelSecret(u32),
+    NoVerifyingKey,
+    InvalidProvisioningKey(String),
+    NoSigningKey,
+    InvalidArgument(&'static str)
+}
+
+impl fmt::Display for DesignError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DesignError::InvalidSecrets(reason) => write!(f, "Invalid secrets: {}", reason),
+            DesignError::NoMasterSecret => write!(f, "Could not find master secret pair"),
+            DesignError::NoMasterOrChannel0Secret => write!(f, "Could not find master secret pair or channel 0 secret pair"),
+            DesignError::NoChannelSecret(channel) => write!(f, "Could not find secret for channel: {}", channel),
+            DesignError::NoVerifyingKey => write!(f, "Could not find subscription verifying key"),
+            DesignError::InvalidProvisioningKey(reason) => write!(f, "Invalid provisioning key: {}", reason),
+            DesignError::NoSigningKey => write!(f, "Could not find subscription signing key"),
+            DesignError::InvalidArgument(reason) => write!(f, "{}", reason)
+        }
+    }
+}
+
+impl std::error::Error for DesignError {}
@@ -0,0 +1,195 @@
+//! Command line for the encoder design tools
+//! Mirrors the `ectf25_design` Python modules' arguments and output
+
+use std::fs;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::num::ParseIntError;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use clap::{Parser, Subcommand};
+
+use ectf25_design::repr::python_bytes_repr;
+use ectf25_design::subscription::issue_sequence;
+use ectf25_design::{gen_resync_with_sequence, gen_secrets, gen_subscription_with_sequence, gen_unlock, provisioning_key_path, Encoder};
+
+#[derive(Parser)]
+#[command(name = "ectf25-design", version, about = "Hammer Industries encoder design tools")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Generate AES secrets for a deployment
+    GenSecrets {
+        /// Force creation of secrets file, overwriting existing file
+        #[arg(short, long)]
+        force: bool,
+        /// Path to the provisioning key file to be created, keep it off the decoder build host (default: the secrets file with a .provisioning.key suffix)
+        #[arg(short = 'k', long)]
+        provisioning_key: Option<PathBuf>,
+        /// Path to the secrets file to be created
+        secrets_file: PathBuf,
+        /// Channel list for this deployment. Channel 0 is always valid and should not be entered here.
+        #[arg(required = true)]
+        channels: Vec<u32>
+    },
+    /// Generate a subscription update package
+    GenSubscription {
+        /// Force creation of subscription file, overwriting existing file
+        #[arg(short, long)]
+        force: bool,
+        /// Path to the provisioning key file created by gen-secrets (default: the secrets file with a .provisioning.key suffix)
+        #[arg(short = 'k', long)]
+        provisioning_key: Option<PathBuf>,
+        /// Path to the secrets file created by gen-secrets
+        secrets_file: PathBuf,
+        /// Path to the subscription file to be generated
+        subscription_file: PathBuf,
+        /// Device ID of the update recipient
+        #[arg(value_parser = parse_device_id)]
+        device_id: u32,
+        /// Subscription start timestamp
+        start: u64,
+        /// Subscription end timestamp
+        end: u64,
+        /// Channel to subscribe to
+        channel: u32,
+        /// Update sequence number, must exceed the last one sent for this channel (default: issue time in nanoseconds)
+        #[arg(short, long, value_parser = parse_u64)]
+        sequence: Option<u64>
+    },
+    /// Generate a lockout unlock package
+    GenUnlock {
+        /// Force creation of unlock file, overwriting existing file
+        #[arg(short, long)]
+        force: bool,
+        /// Path to the secrets file created by gen-secrets
+        secrets_file: PathBuf,
+        /// Path to the unlock file to be generated
+        unlock_file: PathBuf,
+        /// Device ID of the decoder to unlock
+        #[arg(value_parser = parse_device_id)]
+        device_id: u32,
+        /// Number of unlocks the decoder has already accepted
+        epoch: u32
+    },
+    /// Generate a clock resync package
+    GenResync {
+        /// Force creation of resync file, overwriting existing file
+        #[arg(short, long)]
+        force: bool,
+        /// Path to the provisioning key file created by gen-secrets (default: the secrets file with a .provisioning.key suffix)
+        #[arg(short = 'k', long)]
+        provisioning_key: Option<PathBuf>,
+        /// Path to the secrets file created by gen-secrets
+        secrets_file: PathBuf,
+        /// Path to the resync file to be generated
+        resync_file: PathBuf,
+        /// Device ID of the decoder to resync
+        #[arg(value_parser = parse_device_id)]
+        device_id: u32,
+        /// Timestamp the decoder's replay protection is moved to
+        timestamp: u64,
+        /// Resync sequence number, must exceed the last one the decoder accepted since booting (default: issue time in nanoseconds)
+        #[arg(short, long, value_parser = parse_u64)]
+        sequence: Option<u64>
+    },
+    /// Encode a frame and print it as a Python byte string
+    Encode {
+        /// Path to the secrets file generated by gen-secrets
+        secrets_file: PathBuf,
+        /// Channel to encode for
+        channel: u32,
+        /// Contents of the frame
+        frame: String,
+        /// Timestamp of the frame
+        timestamp: u64
+    }
+}
+
+fn parse_device_id(value: &str) -> Result<u32, String> {
+    parse_literal(value, u32::from_str_radix).map_err(|error| format!("device_id is not representable as u32 ({})", error))
+}
+
+fn parse_u64(value: &str) -> Result<u64, String> {
+    parse_literal(value, u64::from_str_radix).map_err(|error| format!("sequence is not representable as u64 ({})", error))
+}
+
+// Same forms as Python's int(x, 0)
+fn parse_literal<T>(value: &str, from_str_radix: fn(&str, u32) -> Result<T, ParseIntError>) -> Result<T, ParseIntError> {
+    let digits = value.replace('_', "");
+    let lower = digits.to_ascii_lowercase();
+    if let Some(hex) = lower.strip_prefix("0x") {
+        from_str_radix(hex, 16)
+    } else if let Some(octal) = lower.strip_prefix("0o") {
+        from_str_radix(octal, 8)
+    } else if let Some(binary) = lower.strip_prefix("0b") {
+        from_str_radix(binary, 2)
+    } else {
+        from_str_radix(&lower, 10)
+    }
+}
+
+// Python's "xb" mode unless forced
+fn write_output(path: &Path, force: bool, data: &[u8]) -> Result<(), String> {
+    let mut options = OpenOptions::new();
+    options.write(true);
+    if force { options.create(true).truncate(true); } else { options.create_new(true); }
+    let mut file = options.open(path).map_err(|error| format!("{}: {}", path.display(), error))?;
+    file.write_all(data).map_err(|error| format!("{}: {}", path.display(), error))
+}
+
+fn read_secrets(path: &Path) -> Result<Vec<u8>, String> {
+    fs::read(path).map_err(|error| format!("{}: {}", path.display(), error))
+}
+
+fn run(cli: Cli) -> Result<(), String> {
+    match cli.command {
+        Command::GenSecrets { force, provisioning_key: provisioning_key_file, secrets_file, channels } => {
+            let provisioning_key_file = provisioning_key_file.unwrap_or_else(|| provisioning_key_path(&secrets_file));
+            let (secrets, provisioning_key) = gen_secrets(&channels).map_err(|error| error.to_string())?;
+            write_output(&secrets_file, force, &secrets)?;
+            write_output(&provisioning_key_file, force, &provisioning_key)
+        },
+        Command::GenSubscription { force, provisioning_key: provisioning_key_file, secrets_file, subscription_file, device_id, start, end, channel, sequence } => {
+            let secrets = read_secrets(&secrets_file)?;
+            let provisioning_key = read_secrets(&provisioning_key_file.unwrap_or_else(|| provisioning_key_path(&secrets_file)))?;
+            let sequence = sequence.unwrap_or_else(issue_sequence);
+            let subscription = gen_subscription_with_sequence(&secrets, &provisioning_key, device_id, start, end, channel, sequence).map_err(|error| error.to_string())?;
+            write_output(&subscription_file, force, &subscription)
+        },
+        Command::GenUnlock { force, secrets_file, unlock_file, device_id, epoch } => {
+            let secrets = read_secrets(&secrets_file)?;
+            let unlock = gen_unlock(&secrets, device_id, epoch).map_err(|error| error.to_string())?;
+            write_output(&unlock_file, force, &unlock)
+        },
+        Command::GenResync { force, provisioning_key: provisioning_key_file, secrets_file, resync_file, device_id, timestamp, sequence } => {
+            let secrets = read_secrets(&secrets_file)?;
+            let provisioning_key = read_secrets(&provisioning_key_file.unwrap_or_else(|| provisioning_key_path(&secrets_file)))?;
+            let sequence = sequence.unwrap_or_else(issue_sequence);
+            let resync = gen_resync_with_sequence(&secrets, &provisioning_key, device_id, timestamp, sequence).map_err(|error| error.to_string())?;
+            write_output(&resync_file, force, &resync)
+        },
+        Command::Encode { secrets_file, channel, frame, timestamp } => {
+            let secrets = read_secrets(&secrets_file)?;
+            let encoder = Encoder::new(&secrets).map_err(|error| error.to_string())?;
+            let encoded = encoder.encode(channel, frame.as_bytes(), timestamp).map_err(|error| error.to_string())?;
+            println!("{}", python_bytes_repr(&encoded));
+            Ok(())
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("error: {}", error);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,25 @@
+//! Python `repr()` of a byte string, which is what `ectf25_design.encoder` prints
+
+pub fn python_bytes_repr(bytes: &[u8]) -> String {
+    // Python prefers single quotes unless only double quotes avoid escaping
+    let quote = if bytes.contains(&b'\'') && !bytes.contains(&b'"') { '"' } else { '\'' };
+    let mut repr = String::with_capacity(bytes.len() + 3);
+    repr.push('b');
+    repr.push(quote);
+    for &byte in bytes {
+        match byte {
+            b'\\' => repr.push_str("\\\\"),
+            b'\t' => repr.push_str("\\t"),
+            b'\n' => repr.push_str("\\n"),
+            b'\r' => repr.push_str("\\r"),
+            _ if byte as char == quote => {
+                repr.push('\\');
+                repr.push(quote);
+            },
+            0x20..=0x7E => repr.push(byte as char),
+            _ => repr.push_str(&format!("\\x{:02x}", byte))
+        }
+    }
+    repr.push(quote);
+    repr
+}
@@ -0,0 +1,32 @@
+//! Clock resync generation
+
+use ed25519_dalek::{Signer, SigningKey};
+
+use decoder_types::packet::{resync_blocks, resync_signed_message};
+
+use crate::crypto::{anti_cbc_encrypt, from_blocks};
+use crate::secrets::Secrets;
+use crate::subscription::issue_sequence;
+use crate::DesignError;
+
+// Sequenced by issue time, see gen_resync_with_sequence
+pub fn gen_resync(secrets: &[u8], provisioning_key: &[u8], device_id: u32, timestamp: u64) -> Result<Vec<u8>, DesignError> {
+    gen_resync_with_sequence(secrets, provisioning_key, device_id, timestamp, issue_sequence())
+}
+
+// The decoder only accepts a resync whose sequence exceeds the last one it accepted
+// since booting, so a captured resync cannot be replayed to rewind its clock again
+pub fn gen_resync_with_sequence(secrets: &[u8], provisioning_key: &[u8], device_id: u32, timestamp: u64, sequence: u64) -> Result<Vec<u8>, DesignError> {
+    let secrets = Secrets::from_json(secrets)?;
+    let signing = secrets.signing_key(provisioning_key)?;
+
+    // Master layer: decoder id and sequence, then the timestamp
+    let mut blocks = resync_blocks(device_id, sequence, timestamp);
+    anti_cbc_encrypt(secrets.master()?, &mut blocks);
+
+    // Signed for this decoder only, the signature follows the encrypted blocks in the clear
+    let signature = SigningKey::from_bytes(&signing.seed).sign(&resync_signed_message(device_id, sequence, timestamp));
+    let mut resync = from_blocks(&blocks);
+    resync.extend_from_slice(&signature.to_bytes());
+    Ok(resync)
+}
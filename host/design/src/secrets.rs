@@ -0,0 +1,253 @@
+//! Secrets and provisioning key generation and parsing
+//! The secrets file is JSON mapping "master" and each channel ID to a base64 [key, iv] pair,
+//! and "verifying" to the base64 public key of the Ed25519 subscription signing key.
+//! The provisioning key is JSON mapping "signing" to the base64 [seed, public key] of that key,
+//! kept apart so the decoder build never sees the seed
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use base64::prelude::*;
+use ed25519_dalek::SigningKey;
+use serde_json::{Map, Value};
+
+use decoder_types::{AesBlock, AesKey};
+
+use crate::DesignError;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SecretPair {
+    pub key: AesKey,
+    pub iv: AesBlock
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SigningPair {
+    pub seed: [u8; 32],
+    pub public_key: [u8; 32]
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Secrets {
+    master: Option<SecretPair>,
+    channels: BTreeMap<u32, SecretPair>,
+    verifying_key: Option<[u8; 32]>
+}
+
+impl Secrets {
+    pub fn from_json(secrets: &[u8]) -> Result<Self, DesignError> {
+        let secrets_map = match parse_object(secrets) {
+            Ok(secrets_map) => secrets_map,
+            Err(reason) => return Err(DesignError::InvalidSecrets(reason))
+        };
+
+        let mut master: Option<SecretPair> = None;
+        let mut channels: BTreeMap<u32, SecretPair> = BTreeMap::new();
+        let mut verifying_key: Option<[u8; 32]> = None;
+        for (id, value) in secrets_map {
+            if id == "signing" {
+                return Err(DesignError::InvalidSecrets(String::from("Found signing seed: it belongs in the provisioning key")));
+            }
+            if id == "verifying" {
+                verifying_key = Some(parse_verifying_key(value)?);
+                continue;
+            }
+            let pair = parse_secret_pair(value)?;
+            if id == "master" {
+                master = Some(pair);
+                continue;
+            }
+            match id.parse::<u32>() {
+                Ok(channel) if id.bytes().all(|byte| byte.is_ascii_digit()) => { channels.insert(channel, pair); },
+                _ => return Err(DesignError::InvalidSecrets(String::from("Found invalid channel numbers in secrets")))
+            }
+        }
+        Ok(Secrets { master, channels, verifying_key })
+    }
+
+    pub fn master(&self) -> Result<&SecretPair, DesignError> {
+        match &self.master {
+            Some(master) => Ok(master),
+            None => Err(DesignError::NoMasterSecret)
+        }
+    }
+
+    pub fn channel(&self, channel: u32) -> Result<&SecretPair, DesignError> {
+        match self.channels.get(&channel) {
+            Some(pair) => Ok(pair),
+            None => Err(DesignError::NoChannelSecret(channel))
+        }
+    }
+
+    pub fn verifying_key(&self) -> Result<&[u8; 32], DesignError> {
+        match &self.verifying_key {
+            Some(verifying_key) => Ok(verifying_key),
+            None => Err(DesignError::NoVerifyingKey)
+        }
+    }
+
+    // The provisioning key must be the one the decoders built from these secrets verify with
+    pub fn signing_key(&self, provisioning_key: &[u8]) -> Result<SigningPair, DesignError> {
+        let verifying_key = self.verifying_key()?;
+        let signing = SigningPair::from_json(provisioning_key)?;
+        if signing.public_key != *verifying_key {
+            return Err(DesignError::InvalidProvisioningKey(String::from("Found invalid signing key: not the one the decoders verify with")));
+        }
+        Ok(signing)
+    }
+
+    // Frames and subscriptions both need the master secret and the emergency channel
+    pub fn require_master_and_channel_0(&self) -> Result<(), DesignError> {
+        if self.master.is_none() || !self.channels.contains_key(&0) { return Err(DesignError::NoMasterOrChannel0Secret); }
+        Ok(())
+    }
+
+    pub fn channels(&self) -> impl Iterator<Item = u32> + '_ {
+        self.channels.keys().copied()
+    }
+}
+
+impl SigningPair {
+    pub fn from_json(provisioning_key: &[u8]) -> Result<Self, DesignError> {
+        let mut provisioning_map = match parse_object(provisioning_key) {
+            Ok(provisioning_map) => provisioning_map,
+            Err(reason) => return Err(DesignError::InvalidProvisioningKey(reason))
+        };
+        match provisioning_map.remove("signing") {
+            Some(value) => parse_signing_pair(value),
+            None => Err(DesignError::NoSigningKey)
+        }
+    }
+}
+
+fn parse_object(data: &[u8]) -> Result<Map<String, Value>, String> {
+    match serde_json::from_slice(data) {
+        Ok(Value::Object(map)) => Ok(map),
+        Ok(_) => Err(String::from("not a JSON object")),
+        Err(error) => Err(error.to_string())
+    }
+}
+
+fn parse_secret_pair(value: Value) -> Result<SecretPair, DesignError> {
+    let secret_list = match value {
+        Value::Array(secret_list) if secret_list.len() == 2 => secret_list,
+        _ => return Err(DesignError::InvalidSecrets(String::from("Found improper amount of secret pairs for channel")))
+    };
+    let (key, iv) = match (decode_field(&secret_list[0]), decode_field(&secret_list[1])) {
+        (Ok(key), Ok(iv)) => (key, iv),
+        (Err(reason), _) | (_, Err(reason)) => return Err(DesignError::InvalidSecrets(reason))
+    };
+    let key: AesKey = match key.try_into() {
+        Ok(key) => key,
+        Err(_) => return Err(DesignError::InvalidSecrets(String::from("Found invalid AES key: not 256 bits")))
+    };
+    let iv: AesBlock = match iv.try_into() {
+        Ok(iv) => iv,
+        Err(_) => return Err(DesignError::InvalidSecrets(String::from("Found invalid CBC IV: not 128 bits")))
+    };
+    Ok(SecretPair { key, iv })
+}
+
+fn parse_verifying_key(value: Value) -> Result<[u8; 32], DesignError> {
+    let verifying_key = match decode_field(&value) {
+        Ok(verifying_key) => verifying_key,
+        Err(reason) => return Err(DesignError::InvalidSecrets(reason))
+    };
+    match verifying_key.try_into() {
+        Ok(verifying_key) => Ok(verifying_key),
+        Err(_) => Err(DesignError::InvalidSecrets(String::from("Found invalid verifying key: not 256 bits")))
+    }
+}
+
+// The public key must be the one the seed derives, as only it goes into the decoder
+fn parse_signing_pair(value: Value) -> Result<SigningPair, DesignError> {
+    let signing_list = match value {
+        Value::Array(signing_list) if signing_list.len() == 2 => signing_list,
+        _ => return Err(DesignError::InvalidProvisioningKey(String::from("Found improper signing key: expected seed and public key")))
+    };
+    let (seed, public_key) = match (decode_field(&signing_list[0]), decode_field(&signing_list[1])) {
+        (Ok(seed), Ok(public_key)) => (seed, public_key),
+        (Err(reason), _) | (_, Err(reason)) => return Err(DesignError::InvalidProvisioningKey(reason))
+    };
+    let seed: [u8; 32] = match seed.try_into() {
+        Ok(seed) => seed,
+        Err(_) => return Err(DesignError::InvalidProvisioningKey(String::from("Found invalid signing key: not 256 bits")))
+    };
+    let public_key: [u8; 32] = match public_key.try_into() {
+        Ok(public_key) => public_key,
+        Err(_) => return Err(DesignError::InvalidProvisioningKey(String::from("Found invalid signing key: not 256 bits")))
+    };
+    if SigningKey::from_bytes(&seed).verifying_key().to_bytes() != public_key {
+        return Err(DesignError::InvalidProvisioningKey(String::from("Found invalid signing key: public key does not match seed")));
+    }
+    Ok(SigningPair { seed, public_key })
+}
+
+fn decode_field(field: &Value) -> Result<Vec<u8>, String> {
+    let encoded = match field {
+        Value::String(encoded) => encoded,
+        _ => return Err(String::from("secret is not a base64 string"))
+    };
+    match BASE64_STANDARD.decode(encoded) {
+        Ok(decoded) => Ok(decoded),
+        Err(error) => Err(error.to_string())
+    }
+}
+
+// Channel 0 is always added
+// Returns the secrets and the provisioning key, both in the same JSON form as Python's
+// `json.dumps`, with the master secret first, channels in ascending order and the verifying key last
+pub fn gen_secrets(channels: &[u32]) -> Result<(Vec<u8>, Vec<u8>), DesignError> {
+    let mut channels: Vec<u32> = channels.to_vec();
+    channels.push(0);
+    channels.sort_unstable();
+    channels.dedup();
+
+    // Generate 256-bit AES keys and 128-bit CBC IVs, until none are duplicated
+    let mut pairs = random_pairs(channels.len() + 1)?;
+    while has_duplicates(&pairs) {
+        pairs = random_pairs(channels.len() + 1)?;
+    }
+
+    let mut entries: Vec<String> = Vec::with_capacity(pairs.len());
+    let ids = std::iter::once(String::from("master")).chain(channels.iter().map(|channel| channel.to_string()));
+    for (id, pair) in ids.zip(pairs) {
+        entries.push(format!("\"{}\": [\"{}\", \"{}\"]", id, BASE64_STANDARD.encode(pair.key), BASE64_STANDARD.encode(pair.iv)));
+    }
+
+    // The secrets file, which decoders are built from, only gets the public half
+    let mut seed = [0u8; 32];
+    if getrandom::getrandom(&mut seed).is_err() {
+        return Err(DesignError::InvalidArgument("Operating system random number generator failed"));
+    }
+    let public_key = SigningKey::from_bytes(&seed).verifying_key().to_bytes();
+    entries.push(format!("\"verifying\": \"{}\"", BASE64_STANDARD.encode(public_key)));
+    let secrets = format!("{{{}}}", entries.join(", ")).into_bytes();
+    let provisioning_key = format!("{{\"signing\": [\"{}\", \"{}\"]}}", BASE64_STANDARD.encode(seed), BASE64_STANDARD.encode(public_key)).into_bytes();
+    Ok((secrets, provisioning_key))
+}
+
+// Where the command line tools keep the provisioning key unless given one, beside the secrets file
+pub fn provisioning_key_path(secrets_file: &Path) -> PathBuf {
+    secrets_file.with_extension("provisioning.key")
+}
+
+fn random_pairs(count: usize) -> Result<Vec<SecretPair>, DesignError> {
+    let mut pairs: Vec<SecretPair> = Vec::with_capacity(count);
+    for _ in 0..count {
+        let mut pair = SecretPair { key: [0; 32], iv: [0; 16] };
+        if getrandom::getrandom(&mut pair.key).is_err() || getrandom::getrandom(&mut pair.iv).is_err() {
+            return Err(DesignError::InvalidArgument("Operating system random number generator failed"));
+        }
+        pairs.push(pair);
+    }
+    Ok(pairs)
+}
+
+fn has_duplicates(pairs: &[SecretPair]) -> bool {
+    let mut values: Vec<&[u8]> = pairs.iter().flat_map(|pair| [&pair.key[..], &pair.iv[..]]).collect();
+    let count = values.len();
+    values.sort_unstable();
+    values.dedup();
+    values.len() < count
+}
@@ -0,0 +1,58 @@
+//! Subscription update generation
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use ed25519_dalek::{Signer, SigningKey};
+
+use decoder_types::packet::{padded_u32_block, subscription_header_block, subscription_signed_message, timestamps_block};
+
+use crate::crypto::{anti_cbc_encrypt, from_blocks};
+use crate::secrets::Secrets;
+use crate::DesignError;
+
+// Last sequence number handed out by issue_sequence
+static LAST_SEQUENCE: AtomicU64 = AtomicU64::new(0);
+
+// Sequenced by issue time, see gen_subscription_with_sequence
+pub fn gen_subscription(secrets: &[u8], provisioning_key: &[u8], device_id: u32, start: u64, end: u64, channel: u32) -> Result<Vec<u8>, DesignError> {
+    gen_subscription_with_sequence(secrets, provisioning_key, device_id, start, end, channel, issue_sequence())
+}
+
+// The decoder only accepts an update whose sequence exceeds the last one it
+// accepted for the channel, so older updates cannot be replayed to roll it back
+pub fn gen_subscription_with_sequence(secrets: &[u8], provisioning_key: &[u8], device_id: u32, start: u64, end: u64, channel: u32, sequence: u64) -> Result<Vec<u8>, DesignError> {
+    let secrets = Secrets::from_json(secrets)?;
+    secrets.require_master_and_channel_0()?;
+    if end < start { return Err(DesignError::InvalidArgument("end is less than start")); }
+    if channel == 0 { return Err(DesignError::InvalidArgument("Cannot generate subscription for channel 0")); }
+    let channel_secret = secrets.channel(channel)?;
+    let signing = secrets.signing_key(provisioning_key)?;
+
+    // Inner layer: decoder id
+    let mut inner_blocks = [padded_u32_block(device_id)];
+    anti_cbc_encrypt(channel_secret, &mut inner_blocks);
+
+    // Outer layer: channel id and sequence, timestamps, then the inner layer
+    let mut blocks = [subscription_header_block(channel, sequence), timestamps_block(end, start), inner_blocks[0]];
+    anti_cbc_encrypt(secrets.master()?, &mut blocks);
+
+    // Signed for this decoder only, the signature follows the encrypted blocks in the clear
+    let signature = SigningKey::from_bytes(&signing.seed).sign(&subscription_signed_message(device_id, channel, sequence, end, start));
+    let mut subscription = from_blocks(&blocks);
+    subscription.extend_from_slice(&signature.to_bytes());
+    Ok(subscription)
+}
+
+// Issue time in nanoseconds, kept increasing within this process like the Python tool
+pub fn issue_sequence() -> u64 {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|elapsed| elapsed.as_nanos() as u64).unwrap_or(0);
+    let mut last = LAST_SEQUENCE.load(Ordering::Relaxed);
+    loop {
+        let sequence = now.max(last + 1);
+        match LAST_SEQUENCE.compare_exchange_weak(last, sequence, Ordering::Relaxed, Ordering::Relaxed) {
+            Ok(_) => return sequence,
+            Err(current) => last = current
+        }
+    }
+}
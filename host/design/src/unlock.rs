@@ -0,0 +1,14 @@
+//! Lockout unlock generation
+
+use decoder_types::packet::unlock_block;
+
+use crate::crypto::{anti_cbc_encrypt, from_blocks};
+use crate::secrets::Secrets;
+use crate::DesignError;
+
+pub fn gen_unlock(secrets: &[u8], device_id: u32, epoch: u32) -> Result<Vec<u8>, DesignError> {
+    let secrets = Secrets::from_json(secrets)?;
+    let mut blocks = [unlock_block(device_id, epoch)];
+    anti_cbc_encrypt(secrets.master()?, &mut blocks);
+    Ok(from_blocks(&blocks))
+}
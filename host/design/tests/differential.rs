@@ -0,0 +1,281 @@
+//! Differential tests against the Python `ectf25_design` tools
+//! Both implementations get the same inputs and must produce the same bytes.
+//! Ignored by default since they need Python with PyCryptodome, run them with `--ignored`.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use ectf25_design::{gen_resync_with_sequence, gen_secrets, gen_subscription, gen_subscription_with_sequence, gen_unlock, Encoder, Secrets};
+
+fn design_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("../../design")
+}
+
+fn python_command() -> Command {
+    let mut command = Command::new(std::env::var("PYTHON").unwrap_or(String::from("python3")));
+    let mut python_path = design_dir().into_os_string();
+    if let Some(existing) = std::env::var_os("PYTHONPATH") {
+        python_path.push(":");
+        python_path.push(existing);
+    }
+    command.env("PYTHONPATH", python_path);
+    command
+}
+
+fn python(script: &str) -> String {
+    let output = python_command().args(["-c", script]).output().expect("python3 is not installed, set PYTHON to use another interpreter");
+    assert!(output.status.success(), "python failed: {}", String::from_utf8_lossy(&output.stderr));
+    String::from_utf8(output.stdout).unwrap()
+}
+
+// Secrets and provisioning key
+fn python_secrets(channels: &[u32]) -> (Vec<u8>, Vec<u8>) {
+    let hex = python(&format!(
+        "from ectf25_design.gen_secrets import gen_secrets, gen_provisioning_key\n\
+         provisioning_key = gen_provisioning_key()\n\
+         print(gen_secrets({:?}, provisioning_key).hex(), provisioning_key.hex())",
+        channels));
+    let (secrets, provisioning_key) = hex.trim().split_once(' ').unwrap();
+    (decode_hex(secrets), decode_hex(provisioning_key))
+}
+
+fn decode_hex(hex: &str) -> Vec<u8> {
+    (0..hex.len()).step_by(2).map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap()).collect()
+}
+
+fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+// Deterministic frame contents covering every byte value
+fn test_frame(length: usize, seed: u8) -> Vec<u8> {
+    (0..length).map(|i| (i as u8).wrapping_mul(37).wrapping_add(seed)).collect()
+}
+
+#[test]
+#[ignore = "needs python3 with pycryptodome"]
+fn encode_matches_python() {
+    let (secrets, _) = python_secrets(&[1, 2, u32::MAX]);
+    let encoder = Encoder::new(&secrets).unwrap();
+
+    let mut cases: Vec<(u32, Vec<u8>, u64)> = Vec::new();
+    for length in 1..=64 {
+        for (channel, timestamp) in [(0, 0), (1, length as u64), (u32::MAX, u64::MAX)] {
+            cases.push((channel, test_frame(length, channel as u8), timestamp));
+        }
+    }
+    let python_cases: Vec<String> = cases.iter()
+        .map(|(channel, frame, timestamp)| format!("({}, bytes.fromhex('{}'), {})", channel, encode_hex(frame), timestamp))
+        .collect();
+    let expected = python(&format!(
+        "from ectf25_design.encoder import Encoder\n\
+         encoder = Encoder(bytes.fromhex('{}'))\n\
+         for channel, frame, timestamp in [{}]:\n    print(encoder.encode(channel, frame, timestamp).hex())",
+        encode_hex(&secrets), python_cases.join(", ")));
+
+    for ((channel, frame, timestamp), expected) in cases.iter().zip(expected.lines()) {
+        let encoded = encoder.encode(*channel, frame, *timestamp).unwrap();
+        assert_eq!(encode_hex(&encoded), expected, "channel {} frame length {}", channel, frame.len());
+    }
+    assert_eq!(expected.lines().count(), cases.len());
+}
+
+#[test]
+#[ignore = "needs python3 with pycryptodome"]
+fn gen_subscription_matches_python() {
+    let (secrets, provisioning_key) = python_secrets(&[1, 7, u32::MAX]);
+    let cases: [(u32, u64, u64, u32, u64); 5] = [
+        (0xdeadbeef, 0, 10000, 1, 1),
+        (0, 0, 0, 7, 0),
+        (u32::MAX, 5, u64::MAX, u32::MAX, u64::MAX),
+        (1, u64::MAX, u64::MAX, 1, 1 << 63),
+        (0x12345678, 1 << 40, 1 << 50, 7, 1_700_000_000_000_000_000)
+    ];
+    let python_cases: Vec<String> = cases.iter()
+        .map(|(device_id, start, end, channel, sequence)| format!("({}, {}, {}, {}, {})", device_id, start, end, channel, sequence))
+        .collect();
+    let expected = python(&format!(
+        "from ectf25_design.gen_subscription import gen_subscription\n\
+         secrets = bytes.fromhex('{}')\n\
+         provisioning_key = bytes.fromhex('{}')\n\
+         for device_id, start, end, channel, sequence in [{}]:\n    print(gen_subscription(secrets, device_id, start, end, channel, sequence, provisioning_key).hex())",
+        encode_hex(&secrets), encode_hex(&provisioning_key), python_cases.join(", ")));
+
+    for ((device_id, start, end, channel, sequence), expected) in cases.iter().zip(expected.lines()) {
+        let subscription = gen_subscription_with_sequence(&secrets, &provisioning_key, *device_id, *start, *end, *channel, *sequence).unwrap();
+        assert_eq!(encode_hex(&subscription), expected);
+    }
+    assert_eq!(expected.lines().count(), cases.len());
+}
+
+#[test]
+#[ignore = "needs python3 with pycryptodome"]
+fn gen_unlock_matches_python() {
+    let (secrets, _) = python_secrets(&[1]);
+    let cases: [(u32, u32); 4] = [(0xdeadbeef, 0), (0, 1), (u32::MAX, u32::MAX), (42, 7)];
+    let python_cases: Vec<String> = cases.iter().map(|(device_id, epoch)| format!("({}, {})", device_id, epoch)).collect();
+    let expected = python(&format!(
+        "from ectf25_design.gen_unlock import gen_unlock\n\
+         secrets = bytes.fromhex('{}')\n\
+         for device_id, epoch in [{}]:\n    print(gen_unlock(secrets, device_id, epoch).hex())",
+        encode_hex(&secrets), python_cases.join(", ")));
+
+    for ((device_id, epoch), expected) in cases.iter().zip(expected.lines()) {
+        assert_eq!(encode_hex(&gen_unlock(&secrets, *device_id, *epoch).unwrap()), expected);
+    }
+    assert_eq!(expected.lines().count(), cases.len());
+}
+
+#[test]
+#[ignore = "needs python3 with pycryptodome"]
+fn gen_resync_matches_python() {
+    let (secrets, provisioning_key) = python_secrets(&[1]);
+    let cases: [(u32, u64, u64); 4] = [(0xdeadbeef, 0, 1), (0, u64::MAX, 0), (u32::MAX, 1 << 40, u64::MAX), (42, 7, 1_700_000_000_000_000_000)];
+    let python_cases: Vec<String> = cases.iter().map(|(device_id, timestamp, sequence)| format!("({}, {}, {})", device_id, timestamp, sequence)).collect();
+    let expected = python(&format!(
+        "from ectf25_design.gen_resync import gen_resync\n\
+         secrets = bytes.fromhex('{}')\n\
+         provisioning_key = bytes.fromhex('{}')\n\
+         for device_id, timestamp, sequence in [{}]:\n    print(gen_resync(secrets, device_id, timestamp, sequence, provisioning_key).hex())",
+        encode_hex(&secrets), encode_hex(&provisioning_key), python_cases.join(", ")));
+
+    for ((device_id, timestamp, sequence), expected) in cases.iter().zip(expected.lines()) {
+        assert_eq!(encode_hex(&gen_resync_with_sequence(&secrets, &provisioning_key, *device_id, *timestamp, *sequence).unwrap()), expected);
+    }
+    assert_eq!(expected.lines().count(), cases.len());
+}
+
+#[test]
+#[ignore = "needs python3 with pycryptodome"]
+fn gen_secrets_is_read_by_python() {
+    let (secrets, provisioning_key) = gen_secrets(&[3, 1, 1, 2]).unwrap();
+    // Same JSON text Python would write for these secrets and provisioning key, and usable by the Python tools
+    let expected = python(&format!(
+        "import json\n\
+         from ectf25_design.encoder import Encoder\n\
+         from ectf25_design.gen_subscription import gen_subscription\n\
+         secrets = bytes.fromhex('{}')\n\
+         provisioning_key = bytes.fromhex('{}')\n\
+         print(json.dumps(json.loads(secrets)))\n\
+         print(json.dumps(json.loads(provisioning_key)))\n\
+         print(Encoder(secrets).encode(2, b'frame', 99).hex())\n\
+         print(gen_subscription(secrets, 1, 0, 5, 3, 1, provisioning_key).hex())",
+        encode_hex(&secrets), encode_hex(&provisioning_key)));
+    let mut lines = expected.lines();
+    assert_eq!(lines.next().unwrap().as_bytes(), &secrets[..]);
+    assert_eq!(lines.next().unwrap().as_bytes(), &provisioning_key[..]);
+    let encoder = Encoder::new(&secrets).unwrap();
+    assert_eq!(encode_hex(&encoder.encode(2, b"frame", 99).unwrap()), lines.next().unwrap());
+    assert_eq!(encode_hex(&gen_subscription_with_sequence(&secrets, &provisioning_key, 1, 0, 5, 3, 1).unwrap()), lines.next().unwrap());
+
+    let parsed = Secrets::from_json(&secrets).unwrap();
+    assert_eq!(parsed.channels().collect::<Vec<u32>>(), vec![0, 1, 2, 3]);
+}
+
+#[test]
+#[ignore = "needs python3 with pycryptodome"]
+fn encode_cli_matches_python_cli() {
+    let directory = tempfile::tempdir().unwrap();
+    let secrets_file = directory.path().join("global.secrets");
+    std::fs::write(&secrets_file, python_secrets(&[1]).0).unwrap();
+
+    // Frames that exercise every quoting and escaping rule of Python's bytes repr
+    for frame in ["frame", "it's", "say \"hi\"", "both ' and \"", "back\\slash", "tab\there\nnewline\r", "\u{7f}\u{e9}"] {
+        let rust = Command::new(env!("CARGO_BIN_EXE_ectf25-design"))
+            .args(["encode", secrets_file.to_str().unwrap(), "1", frame, "12345"])
+            .output().unwrap();
+        let python = python_command()
+            .args(["-m", "ectf25_design.encoder", secrets_file.to_str().unwrap(), "1", frame, "12345"])
+            .output().unwrap();
+        assert!(rust.status.success() && python.status.success());
+        assert_eq!(String::from_utf8(rust.stdout).unwrap(), String::from_utf8(python.stdout).unwrap(), "frame {:?}", frame);
+    }
+}
+
+#[test]
+#[ignore = "needs python3 with pycryptodome"]
+fn gen_subscription_cli_matches_python_cli() {
+    let directory = tempfile::tempdir().unwrap();
+    let secrets_file = directory.path().join("global.secrets");
+    // Found beside the secrets file without --provisioning-key
+    let provisioning_key_file = directory.path().join("global.provisioning.key");
+    let (secrets, provisioning_key) = python_secrets(&[1, 2]);
+    std::fs::write(&secrets_file, secrets).unwrap();
+    std::fs::write(&provisioning_key_file, provisioning_key).unwrap();
+    let rust_file = directory.path().join("rust.bin");
+    let python_file = directory.path().join("python.bin");
+
+    let args = |output: &Path| vec![secrets_file.to_str().unwrap().to_string(), output.to_str().unwrap().to_string(),
+        String::from("0xdeadbeef"), String::from("0"), String::from("10000"), String::from("2"), String::from("--sequence"), String::from("0x1234")];
+    let rust = Command::new(env!("CARGO_BIN_EXE_ectf25-design")).arg("gen-subscription").args(args(&rust_file)).status().unwrap();
+    let python = python_command().args(["-m", "ectf25_design.gen_subscription"]).args(args(&python_file)).status().unwrap();
+    assert!(rust.success() && python.success());
+    assert_eq!(std::fs::read(rust_file).unwrap(), std::fs::read(python_file).unwrap());
+}
+
+#[test]
+#[ignore = "needs python3 with pycryptodome"]
+fn gen_secrets_cli_matches_python_cli() {
+    let directory = tempfile::tempdir().unwrap();
+    for (name, mut command) in [("rust", Command::new(env!("CARGO_BIN_EXE_ectf25-design"))), ("python", python_command())] {
+        let secrets_file = directory.path().join(format!("{}.secrets", name));
+        if name == "rust" { command.arg("gen-secrets"); } else { command.args(["-m", "ectf25_design.gen_secrets"]); }
+        assert!(command.args([secrets_file.to_str().unwrap(), "1", "3", "4"]).status().unwrap().success());
+
+        // The provisioning key is written beside the secrets and signs for them
+        let secrets = std::fs::read(&secrets_file).unwrap();
+        let provisioning_key = std::fs::read(directory.path().join(format!("{}.provisioning.key", name))).unwrap();
+        assert_eq!(Secrets::from_json(&secrets).unwrap().channels().collect::<Vec<u32>>(), vec![0, 1, 3, 4]);
+        assert!(gen_subscription(&secrets, &provisioning_key, 1, 0, 5, 4).is_ok());
+    }
+}
+
+#[test]
+#[ignore = "needs python3 with pycryptodome"]
+fn rejects_what_python_rejects() {
+    let (secrets, provisioning_key) = python_secrets(&[1]);
+    let encoder = Encoder::new(&secrets).unwrap();
+    // The same secrets without a key to verify subscriptions with
+    let mut unsigned: serde_json::Map<String, serde_json::Value> = serde_json::from_slice(&secrets).unwrap();
+    unsigned.remove("verifying");
+    let unsigned = serde_json::to_vec(&unsigned).unwrap();
+    // Another deployment's provisioning key, and secrets still holding the seed
+    let (_, foreign_key) = python_secrets(&[1]);
+    let mut seeded: serde_json::Map<String, serde_json::Value> = serde_json::from_slice(&secrets).unwrap();
+    seeded.append(&mut serde_json::from_slice(&provisioning_key).unwrap());
+    let seeded = serde_json::to_vec(&seeded).unwrap();
+    let rejected = python(&format!(
+        "from ectf25_design.encoder import Encoder\n\
+         from ectf25_design.gen_subscription import gen_subscription\n\
+         secrets = bytes.fromhex('{}')\n\
+         provisioning_key = bytes.fromhex('{}')\n\
+         unsigned = bytes.fromhex('{}')\n\
+         foreign_key = bytes.fromhex('{}')\n\
+         seeded = bytes.fromhex('{}')\n\
+         for call in [lambda: Encoder(secrets).encode(1, b'', 0), lambda: Encoder(secrets).encode(1, bytes(65), 0),\n\
+                      lambda: Encoder(secrets).encode(2, b'x', 0), lambda: gen_subscription(secrets, 1, 10, 5, 1, provisioning_key=provisioning_key),\n\
+                      lambda: gen_subscription(secrets, 1, 0, 5, 0, provisioning_key=provisioning_key),\n\
+                      lambda: gen_subscription(secrets, 1, 0, 5, 2, provisioning_key=provisioning_key),\n\
+                      lambda: gen_subscription(unsigned, 1, 0, 5, 1, provisioning_key=provisioning_key),\n\
+                      lambda: gen_subscription(secrets, 1, 0, 5, 1, provisioning_key=foreign_key),\n\
+                      lambda: gen_subscription(seeded, 1, 0, 5, 1, provisioning_key=provisioning_key),\n\
+                      lambda: gen_subscription(secrets, 1, 0, 5, 1)]:\n\
+         \x20   try:\n\
+         \x20       call()\n\
+         \x20       print('accepted')\n\
+         \x20   except ValueError:\n\
+         \x20       print('rejected')",
+        encode_hex(&secrets), encode_hex(&provisioning_key), encode_hex(&unsigned), encode_hex(&foreign_key), encode_hex(&seeded)));
+    assert!(rejected.lines().all(|line| line == "rejected"), "{}", rejected);
+
+    assert!(encoder.encode(1, b"", 0).is_err());
+    assert!(encoder.encode(1, &[0; 65], 0).is_err());
+    assert!(encoder.encode(2, b"x", 0).is_err());
+    assert!(gen_subscription(&secrets, &provisioning_key, 1, 10, 5, 1).is_err());
+    assert!(gen_subscription(&secrets, &provisioning_key, 1, 0, 5, 0).is_err());
+    assert!(gen_subscription(&secrets, &provisioning_key, 1, 0, 5, 2).is_err());
+    assert!(gen_subscription(&unsigned, &provisioning_key, 1, 0, 5, 1).is_err());
+    assert!(gen_subscription(&secrets, &foreign_key, 1, 0, 5, 1).is_err());
+    assert!(gen_subscription(&seeded, &provisioning_key, 1, 0, 5, 1).is_err());
+    assert!(gen_subscription(&secrets, &provisioning_key, 1, 0, 5, 1).is_ok());
+}
@@ -0,0 +1,20 @@
+//! Receives and executes every message in the input, without responding
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+use decoder_core::commands::execute_command;
+use decoder_core::message::receive::receive_message;
+use decoder_fuzz::{Decoder, FuzzUart};
+use decoder_sim::DEFAULT_FREE_CHANNELS;
+
+fuzz_target!(|input: &[u8]| {
+    let mut decoder = Decoder::power_on();
+    let uart = FuzzUart::new(input);
+    while !uart.is_empty() {
+        if let Ok(host_message) = receive_message(&decoder.flc, &uart, &decoder.aes) {
+            let _ = execute_command(&decoder.flc, &decoder.aes, &mut decoder.rng, DEFAULT_FREE_CHANNELS, host_message);
+        }
+    }
+});
@@ -0,0 +1,18 @@
+//! Runs the firmware's main loop over the input, including lockout and responses
+//! The input also carries the host's ACKs for each response
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+use decoder_core::serve_message;
+use decoder_fuzz::{Decoder, FuzzUart};
+use decoder_sim::{DEFAULT_DELAYS, DEFAULT_FREE_CHANNELS};
+
+fuzz_target!(|input: &[u8]| {
+    let mut decoder = Decoder::power_on();
+    let uart = FuzzUart::new(input);
+    while !uart.is_empty() {
+        serve_message(&decoder.flc, &decoder.aes, &uart, &mut decoder.delay, &mut decoder.rng, &DEFAULT_DELAYS, DEFAULT_FREE_CHANNELS);
+    }
+});
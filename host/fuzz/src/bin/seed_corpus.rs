@@ -0,0 +1,116 @@
+//! Writes the seed corpus for every fuzz target into corpus/
+//! Each frames/*.json file becomes one seed that subscribes to its channels,
+//! lists, and decodes every frame, and one that decodes its first frames as a batch.
+//! serve_message seeds are recorded from the client driving the simulator, so
+//! they carry the host's ACKs at the right places.
+
+use std::fs;
+use std::io;
+use std::io::{Read, Write};
+use std::path::Path;
+
+use decoder_client::DecoderClient;
+use decoder_fuzz::{DECODER_ID, PROVISIONING_KEY, SECRETS};
+use decoder_sim::Simulator;
+use decoder_types::protocol::{header_bytes, DECODE_BATCH_OPCODE, DECODE_EXTENDED_OPCODE, DECODE_OPCODE, LIST_OPCODE, MAX_BATCH_FRAMES, RESYNC_OPCODE, UNLOCK_OPCODE, UPDATE_OPCODE};
+use ectf25_design::{gen_resync, gen_subscription, gen_unlock, Encoder};
+
+type Message = (u8, Vec<u8>);
+
+// Keeps a copy of everything the client sends
+struct Recorder<T: Read + Write> {
+    inner: T,
+    sent: Vec<u8>
+}
+
+impl<T: Read + Write> Read for Recorder<T> {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        self.inner.read(buffer)
+    }
+}
+
+impl<T: Read + Write> Write for Recorder<T> {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        let count = self.inner.write(bytes)?;
+        self.sent.extend_from_slice(&bytes[..count]);
+        Ok(count)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+fn main() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let encoder = Encoder::new(SECRETS).unwrap();
+    let mut seeds: Vec<(String, Vec<Message>)> = vec![
+        ("list".to_string(), vec![(LIST_OPCODE, Vec::new())]),
+        ("unlock".to_string(), vec![(UNLOCK_OPCODE, gen_unlock(SECRETS, DECODER_ID, 0).unwrap())]),
+        ("resync".to_string(), vec![(RESYNC_OPCODE, gen_resync(SECRETS, PROVISIONING_KEY, DECODER_ID, 0).unwrap())])
+    ];
+
+    let mut frame_files: Vec<_> = fs::read_dir(root.join("../../frames")).unwrap().map(|entry| entry.unwrap().path()).collect();
+    frame_files.sort();
+    for path in frame_files {
+        if path.extension().is_none_or(|extension| extension != "json") { continue; }
+        let name = path.file_stem().unwrap().to_string_lossy().into_owned();
+        let frames = read_frames(&path);
+
+        let mut channels: Vec<u32> = frames.iter().map(|(channel, _, _)| *channel).filter(|channel| *channel != 0).collect();
+        channels.sort();
+        channels.dedup();
+        let subscriptions: Vec<Message> = channels.iter().map(|channel| (UPDATE_OPCODE, gen_subscription(SECRETS, PROVISIONING_KEY, DECODER_ID, 0, u64::MAX, *channel).unwrap())).collect();
+        let encoded: Vec<Vec<u8>> = frames.iter().map(|(channel, frame, timestamp)| encoder.encode(*channel, frame, *timestamp).unwrap()).collect();
+
+        let mut messages = subscriptions.clone();
+        messages.push((LIST_OPCODE, Vec::new()));
+        messages.extend(encoded.iter().map(|frame| (DECODE_OPCODE, frame.clone())));
+        seeds.push((name.clone(), messages));
+
+        let mut messages = subscriptions.clone();
+        messages.extend(encoded.iter().map(|frame| (DECODE_EXTENDED_OPCODE, frame.clone())));
+        seeds.push((format!("{}_extended", name), messages));
+
+        let mut messages = subscriptions;
+        messages.push((DECODE_BATCH_OPCODE, batch_body(&encoded[..encoded.len().min(MAX_BATCH_FRAMES)])));
+        seeds.push((format!("{}_batch", name), messages));
+    }
+
+    for (name, messages) in seeds {
+        write_seed(root, "receive_command", &name, &messages.iter().flat_map(|(opcode, body)| [header_bytes(*opcode, body.len() as u16).to_vec(), body.clone()].concat()).collect::<Vec<u8>>());
+        write_seed(root, "serve_message", &name, &record(&messages));
+    }
+}
+
+// [[channel, frame, timestamp], ...], as read by the MITRE tester
+fn read_frames(path: &Path) -> Vec<(u32, Vec<u8>, u64)> {
+    let frames: Vec<(u32, String, u64)> = serde_json::from_slice(&fs::read(path).unwrap()).unwrap();
+    frames.into_iter().map(|(channel, frame, timestamp)| (channel, frame.into_bytes(), timestamp)).collect()
+}
+
+fn batch_body(frames: &[Vec<u8>]) -> Vec<u8> {
+    let mut body = (frames.len() as u32).to_le_bytes().to_vec();
+    for frame in frames {
+        body.extend_from_slice(&(frame.len() as u16).to_le_bytes());
+        body.extend_from_slice(frame);
+    }
+    body
+}
+
+// What the client sends a simulated decoder for `messages`, ACKs included
+fn record(messages: &[Message]) -> Vec<u8> {
+    let simulator = Simulator::new(SECRETS, DECODER_ID).unwrap();
+    let mut client = DecoderClient::new(Recorder { inner: simulator.power_on(), sent: Vec::new() });
+    for (opcode, body) in messages {
+        client.send_message(*opcode, body).unwrap();
+        client.receive_message().unwrap();
+    }
+    client.into_inner().sent
+}
+
+fn write_seed(root: &Path, target: &str, name: &str, seed: &[u8]) {
+    let directory = root.join("corpus").join(target);
+    fs::create_dir_all(&directory).unwrap();
+    fs::write(directory.join(name), seed).unwrap();
+}
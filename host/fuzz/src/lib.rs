@@ -0,0 +1,121 @@
+//! Shared setup for the fuzz targets
+//! Each input is everything a host sends to a freshly flashed decoder, played
+//! through the `decoder_core` logic against the simulator's flash and AES engine
+//! and a UART that reads from the input.
+//! Contains:
+//! - The fixed secrets, provisioning key and decoder ID every target and seed uses
+//! - A UART over the fuzz input
+//! - A global allocator holding the decoder to the board's heap
+//! - Decoder power on
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+
+use decoder_core::boot;
+use decoder_core::platform::{Uart, HEAP_SIZE};
+use decoder_core::sys::rng::{new_rng, ReseedingRng};
+use decoder_sim::flash::{FlashMemory, SimFlash};
+use decoder_sim::peripherals::{SimAes, SimDelay, SimEntropy};
+use decoder_sim::{secrets_image, ReplayPolicy};
+
+// Secrets for channels 1 to 3, fixed so the seed corpus stays valid
+pub const SECRETS: &[u8] = include_bytes!("../fuzz.secrets");
+pub const PROVISIONING_KEY: &[u8] = include_bytes!("../fuzz.provisioning.key");
+pub const DECODER_ID: u32 = 0xdeadbeef;
+
+const ENTROPY_SEED: u64 = 0x4655_5A5A;
+
+static FLASHED: OnceLock<FlashMemory> = OnceLock::new();
+
+// Counts live heap bytes and panics once the decoder holds more than the board's
+// `HEAP_SIZE`. Only what is allocated after `Decoder::power_on` arms it counts,
+// since the simulated flash and the fuzzer's own buffers are not on the board
+pub struct HeapLimit {
+    live: AtomicUsize,
+    baseline: AtomicUsize,
+    armed: AtomicBool
+}
+
+impl HeapLimit {
+    // Starts counting from what is live now
+    fn arm(&self) {
+        self.baseline.store(self.live.load(Ordering::SeqCst), Ordering::SeqCst);
+        self.armed.store(true, Ordering::SeqCst);
+    }
+}
+
+unsafe impl GlobalAlloc for HeapLimit {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let live = self.live.fetch_add(layout.size(), Ordering::SeqCst) + layout.size();
+        // Frees of blocks from before arming can take `live` below the baseline
+        let used = live.wrapping_sub(self.baseline.load(Ordering::SeqCst)) as isize;
+        if used > HEAP_SIZE as isize && self.armed.swap(false, Ordering::SeqCst) {
+            panic!("Decoder holds {} bytes on the heap, the board has {}", used, HEAP_SIZE);
+        }
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.live.fetch_sub(layout.size(), Ordering::SeqCst);
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+pub static HEAP: HeapLimit = HeapLimit { live: AtomicUsize::new(0), baseline: AtomicUsize::new(0), armed: AtomicBool::new(false) };
+
+// The host side of the UART, read from the fuzz input
+// Past the end of the input the host sends zeros, which are never a valid
+// header, so every message ends once the input runs out
+pub struct FuzzUart<'a> {
+    input: Cell<&'a [u8]>
+}
+
+impl<'a> FuzzUart<'a> {
+    pub fn new(input: &'a [u8]) -> Self {
+        FuzzUart { input: Cell::new(input) }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.input.get().is_empty()
+    }
+}
+
+impl Uart for FuzzUart<'_> {
+    fn read_bytes(&self, buffer: &mut [u8]) {
+        let input = self.input.get();
+        let count = buffer.len().min(input.len());
+        buffer[..count].copy_from_slice(&input[..count]);
+        buffer[count..].fill(0);
+        self.input.set(&input[count..]);
+    }
+
+    // Responses are not checked, only that producing them does not panic
+    fn write_bytes(&self, _bytes: &[u8]) {}
+}
+
+pub struct Decoder {
+    pub flc: SimFlash,
+    pub aes: SimAes,
+    pub delay: SimDelay,
+    pub rng: ReseedingRng<SimEntropy>
+}
+
+impl Decoder {
+    // Boots a decoder with flash exactly as programmed, so inputs never see each other's state
+    pub fn power_on() -> Self {
+        let flashed = FLASHED.get_or_init(|| FlashMemory::new(&secrets_image(SECRETS, DECODER_ID).unwrap()));
+        let mut decoder = Decoder {
+            flc: SimFlash::new(Arc::new(Mutex::new(flashed.clone()))),
+            aes: SimAes::new(),
+            delay: SimDelay::new(Arc::new(AtomicU64::new(0))),
+            rng: new_rng(SimEntropy::new(ENTROPY_SEED))
+        };
+        // Heap use is counted from here, when the firmware would start to run
+        HEAP.arm();
+        boot(&decoder.flc, &mut decoder.delay, ReplayPolicy::STRICT);
+        decoder
+    }
+}
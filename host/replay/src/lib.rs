@@ -0,0 +1,178 @@
+//! Replays `frames/*.json` fixtures through a decoder
+//! Frames are encoded up front, then streamed to the decoder one at a time or
+//! in batches, so the timings cover only the decoder and the link to it.
+//! Contains:
+//! - Fixture loading, in the MITRE tester's `[[channel, frame, timestamp], ...]` format
+//! - Subscriptions covering every frame of a fixture
+//! - Replay with per-frame results, and latency and throughput statistics
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{Read, Write};
+use std::time::{Duration, Instant};
+
+use decoder_client::{ClientError, DecoderClient, DecoderError};
+use ectf25_design::{gen_subscription, DesignError, Encoder};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    pub channel: u32,
+    pub data: Vec<u8>,
+    pub timestamp: u64
+}
+
+#[derive(Debug)]
+pub enum ReplayError {
+    InvalidFixture(String),
+    Design(DesignError),
+    Client(ClientError)
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplayError::InvalidFixture(reason) => write!(f, "Invalid frames file: {}", reason),
+            ReplayError::Design(error) => write!(f, "{}", error),
+            ReplayError::Client(error) => write!(f, "{}", error)
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+impl From<DesignError> for ReplayError {
+    fn from(error: DesignError) -> Self {
+        ReplayError::Design(error)
+    }
+}
+
+impl From<ClientError> for ReplayError {
+    fn from(error: ClientError) -> Self {
+        ReplayError::Client(error)
+    }
+}
+
+// Frames are strings, sent as their UTF-8 bytes like the tester does
+pub fn load_frames(json: &[u8]) -> Result<Vec<Frame>, ReplayError> {
+    let frames: Vec<(u32, String, u64)> = match serde_json::from_slice(json) {
+        Ok(frames) => frames,
+        Err(error) => return Err(ReplayError::InvalidFixture(error.to_string()))
+    };
+    Ok(frames.into_iter().map(|(channel, data, timestamp)| Frame { channel, data: data.into_bytes(), timestamp }).collect())
+}
+
+pub fn encode_frames(secrets: &[u8], frames: &[Frame]) -> Result<Vec<Vec<u8>>, ReplayError> {
+    let encoder = Encoder::new(secrets)?;
+    let mut encoded = Vec::with_capacity(frames.len());
+    for frame in frames {
+        encoded.push(encoder.encode(frame.channel, &frame.data, frame.timestamp)?);
+    }
+    Ok(encoded)
+}
+
+// One subscription per channel, from its first to its last frame
+// Free channels, channel 0 among them, need none and the decoder refuses one
+pub fn fixture_subscriptions(secrets: &[u8], provisioning_key: &[u8], decoder_id: u32, free_channels: &[u32], frames: &[Frame]) -> Result<Vec<Vec<u8>>, ReplayError> {
+    let mut windows: BTreeMap<u32, (u64, u64)> = BTreeMap::new();
+    for frame in frames.iter().filter(|frame| !free_channels.contains(&frame.channel)) {
+        let window = windows.entry(frame.channel).or_insert((frame.timestamp, frame.timestamp));
+        window.0 = window.0.min(frame.timestamp);
+        window.1 = window.1.max(frame.timestamp);
+    }
+    let mut subscriptions = Vec::with_capacity(windows.len());
+    for (channel, (start, end)) in windows {
+        subscriptions.push(gen_subscription(secrets, provisioning_key, decoder_id, start, end, channel)?);
+    }
+    Ok(subscriptions)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Outcome {
+    Match,
+    Mismatch(Vec<u8>),
+    Error(DecoderError)
+}
+
+#[derive(Debug, Clone)]
+pub struct FrameResult {
+    // For a batch, the batch's round trip split evenly between its frames
+    pub latency: Duration,
+    pub outcome: Outcome
+}
+
+// Sends `encoded` in order, `batch_size` frames per message, and checks each
+// decoded frame against `frames`
+pub fn replay<T: Read + Write>(client: &mut DecoderClient<T>, frames: &[Frame], encoded: &[Vec<u8>], batch_size: usize) -> Result<Vec<FrameResult>, ReplayError> {
+    let mut results = Vec::with_capacity(frames.len());
+    for (frames, encoded) in frames.chunks(batch_size).zip(encoded.chunks(batch_size)) {
+        let sent = Instant::now();
+        let decoded: Vec<Result<Vec<u8>, DecoderError>> = if batch_size == 1 {
+            match client.decode(&encoded[0]) {
+                Ok(frame) => vec![Ok(frame)],
+                Err(ClientError::Decoder(error)) => vec![Err(error)],
+                Err(error) => return Err(ReplayError::Client(error))
+            }
+        } else {
+            let encoded: Vec<&[u8]> = encoded.iter().map(|frame| frame.as_slice()).collect();
+            client.decode_batch(&encoded)?.into_iter().map(|decoded| decoded.map(|decoded| decoded.frame)).collect()
+        };
+        let latency = sent.elapsed() / frames.len() as u32;
+        for (frame, decoded) in frames.iter().zip(decoded) {
+            let outcome = match decoded {
+                Ok(data) if data == frame.data => Outcome::Match,
+                Ok(data) => Outcome::Mismatch(data),
+                Err(error) => Outcome::Error(error)
+            };
+            results.push(FrameResult { latency, outcome });
+        }
+    }
+    Ok(results)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Summary {
+    pub frames: usize,
+    pub mismatches: usize,
+    pub errors: usize,
+    pub min: Duration,
+    pub mean: Duration,
+    pub median: Duration,
+    pub p95: Duration,
+    pub max: Duration,
+    pub total: Duration,
+    pub frames_per_second: f64
+}
+
+impl Summary {
+    // `total` is the wall time of the whole replay, None if there were no frames
+    pub fn new(results: &[FrameResult], total: Duration) -> Option<Self> {
+        if results.is_empty() { return None; }
+        let mut latencies: Vec<Duration> = results.iter().map(|result| result.latency).collect();
+        latencies.sort();
+        let percentile = |percent: usize| latencies[(latencies.len() - 1) * percent / 100];
+        Some(Summary {
+            frames: results.len(),
+            mismatches: results.iter().filter(|result| matches!(result.outcome, Outcome::Mismatch(_))).count(),
+            errors: results.iter().filter(|result| matches!(result.outcome, Outcome::Error(_))).count(),
+            min: latencies[0],
+            mean: latencies.iter().sum::<Duration>() / latencies.len() as u32,
+            median: percentile(50),
+            p95: percentile(95),
+            max: latencies[latencies.len() - 1],
+            total,
+            frames_per_second: results.len() as f64 / total.as_secs_f64()
+        })
+    }
+
+    pub fn passed(&self) -> bool {
+        self.mismatches == 0 && self.errors == 0
+    }
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} frames, {} mismatches, {} errors", self.frames, self.mismatches, self.errors)?;
+        writeln!(f, "latency min {:.3?}, mean {:.3?}, median {:.3?}, p95 {:.3?}, max {:.3?}", self.min, self.mean, self.median, self.p95, self.max)?;
+        write!(f, "throughput {:.1} frames/s over {:.3?}", self.frames_per_second, self.total)
+    }
+}
@@ -0,0 +1,149 @@
+//! Command line for replaying frame fixtures against a board or the simulator
+
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::Instant;
+
+use clap::{ArgGroup, Parser};
+
+use decoder_client::{DecoderClient, FramePriority};
+use decoder_replay::{encode_frames, fixture_subscriptions, load_frames, replay, Frame, Outcome, Summary};
+use decoder_sim::{FreeChannel, Simulator};
+
+#[derive(Parser)]
+#[command(name = "decoder-replay", version, about = "Replay a frames JSON file through a decoder and measure it")]
+#[command(group(ArgGroup::new("decoder").required(true).args(["port", "sim"])))]
+struct Cli {
+    /// Path to the secrets file generated by gen_secrets
+    secrets_file: PathBuf,
+    /// Frames file like [[channel, frame, timestamp], ...], see frames/
+    frames_file: PathBuf,
+    /// Serial port to the decoder
+    #[arg(short, long)]
+    port: Option<String>,
+    /// Replay against the host simulator instead of a board
+    #[arg(long, requires = "provisioning_key")]
+    sim: bool,
+    /// Decoder ID, used for --subscribe and to provision the simulator
+    #[arg(short, long, default_value = "0xdeadbeef", value_parser = parse_device_id)]
+    decoder_id: u32,
+    /// Subscribe to every channel in the frames file before replaying, always done for --sim
+    #[arg(short, long, requires = "provisioning_key")]
+    subscribe: bool,
+    /// Path to the provisioning key file generated by gen_secrets, signs the subscriptions
+    #[arg(short = 'k', long)]
+    provisioning_key: Option<PathBuf>,
+    /// The decoder's free channels, which get no subscription, as in its FREE_CHANNELS build setting
+    #[arg(short, long, value_delimiter = ',', default_value = "0:emergency", value_parser = parse_free_channel)]
+    free_channels: Vec<FreeChannel>,
+    /// Frames per DecodeBatch message, 1 sends plain Decode messages
+    #[arg(short, long, default_value_t = 1, value_parser = clap::value_parser!(u8).range(1..=16))]
+    batch: u8,
+    /// Only print the summary
+    #[arg(short, long)]
+    quiet: bool
+}
+
+// Same forms as Python's int(x, 0), like ectf25-design
+fn parse_device_id(value: &str) -> Result<u32, String> {
+    let lower = value.replace('_', "").to_ascii_lowercase();
+    let parsed = if let Some(hex) = lower.strip_prefix("0x") {
+        u32::from_str_radix(hex, 16)
+    } else if let Some(octal) = lower.strip_prefix("0o") {
+        u32::from_str_radix(octal, 8)
+    } else if let Some(binary) = lower.strip_prefix("0b") {
+        u32::from_str_radix(binary, 2)
+    } else {
+        lower.parse::<u32>()
+    };
+    parsed.map_err(|error| format!("decoder_id is not representable as u32 ({})", error))
+}
+
+// `<channel>` or `<channel>:<priority>`, like the firmware's FREE_CHANNELS
+fn parse_free_channel(value: &str) -> Result<FreeChannel, String> {
+    let value = value.trim();
+    let (channel, priority) = value.split_once(':').unwrap_or((value, "normal"));
+    let channel_id = channel.parse::<u32>().map_err(|_| format!("invalid channel ID {:?}", channel))?;
+    let priority = match priority {
+        "normal" => FramePriority::Normal,
+        "emergency" => FramePriority::Emergency,
+        other => return Err(format!("unknown priority {:?} for channel {}", other, channel_id))
+    };
+    Ok(FreeChannel { channel_id, priority })
+}
+
+fn read_file(path: &Path) -> Result<Vec<u8>, String> {
+    fs::read(path).map_err(|error| format!("{}: {}", path.display(), error))
+}
+
+fn run_replay<T: Read + Write>(client: &mut DecoderClient<T>, cli: &Cli, secrets: &[u8], frames: &[Frame]) -> Result<bool, String> {
+    let encoded = encode_frames(secrets, frames).map_err(|error| error.to_string())?;
+    if cli.subscribe || cli.sim {
+        // Both flags require the provisioning key
+        let provisioning_key = read_file(cli.provisioning_key.as_ref().unwrap())?;
+        let free_channels: Vec<u32> = cli.free_channels.iter().map(|channel| channel.channel_id).collect();
+        for subscription in fixture_subscriptions(secrets, &provisioning_key, cli.decoder_id, &free_channels, frames).map_err(|error| error.to_string())? {
+            client.subscribe(&subscription).map_err(|error| error.to_string())?;
+        }
+    }
+
+    let started = Instant::now();
+    let results = replay(client, frames, &encoded, cli.batch as usize).map_err(|error| error.to_string())?;
+    let total = started.elapsed();
+
+    for (index, (frame, result)) in frames.iter().zip(&results).enumerate() {
+        let status = match &result.outcome {
+            Outcome::Match => "ok".to_string(),
+            Outcome::Mismatch(data) => format!("MISMATCH, got {:?}", String::from_utf8_lossy(data)),
+            Outcome::Error(error) => error.to_string()
+        };
+        if !cli.quiet || result.outcome != Outcome::Match {
+            println!("frame {:>4}  channel {:>3}  timestamp {:>12}  {:>10.3?}  {}", index, frame.channel, frame.timestamp, result.latency, status);
+        }
+    }
+    match Summary::new(&results, total) {
+        Some(summary) => {
+            println!("{}", summary);
+            Ok(summary.passed())
+        },
+        None => {
+            println!("No frames to replay");
+            Ok(true)
+        }
+    }
+}
+
+fn run(cli: Cli) -> Result<bool, String> {
+    if !cli.free_channels.iter().any(|channel| channel.channel_id == 0) { return Err(String::from("free channels must hold channel 0, as every decoder's do")); }
+    let secrets = read_file(&cli.secrets_file)?;
+    let frames = load_frames(&read_file(&cli.frames_file)?).map_err(|error| format!("{}: {}", cli.frames_file.display(), error))?;
+    match &cli.port {
+        Some(port) => {
+            let mut client = DecoderClient::open_serial(port).map_err(|error| format!("{}: {}", port, error))?;
+            run_replay(&mut client, &cli, &secrets, &frames)
+        },
+        None => {
+            // The simulator keeps its free channels for good, and this one lives until exit anyway
+            let free_channels = Box::leak(cli.free_channels.clone().into_boxed_slice());
+            let simulator = Simulator::new(&secrets, cli.decoder_id).map_err(|error| error.to_string())?.with_free_channels(free_channels);
+            let mut client = DecoderClient::new(simulator.power_on());
+            let passed = run_replay(&mut client, &cli, &secrets, &frames)?;
+            // The simulator never sleeps, so report what a board would have spent in delays
+            println!("simulated decoder delays {:.3?}", std::time::Duration::from_micros(simulator.elapsed_us()));
+            Ok(passed)
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse()) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(error) => {
+            eprintln!("error: {}", error);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,120 @@
+//! Replays the frames/ fixtures through the simulator
+
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use decoder_client::{DecoderClient, FramePriority};
+use decoder_replay::{encode_frames, fixture_subscriptions, load_frames, replay, Frame, FrameResult, Outcome, ReplayError, Summary};
+use decoder_sim::{FreeChannel, Simulator};
+use ectf25_design::gen_secrets;
+
+const DECODER_ID: u32 = 0xdeadbeef;
+
+fn fixture(name: &str) -> Vec<Frame> {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../frames").join(name);
+    load_frames(&fs::read(path).unwrap()).unwrap()
+}
+
+fn replay_on_simulator(frames: &[Frame], expected: &[Frame], batch_size: usize) -> Vec<FrameResult> {
+    let (secrets, provisioning_key) = gen_secrets(&[1, 2, 3]).unwrap();
+    let simulator = Simulator::new(&secrets, DECODER_ID).unwrap();
+    let mut client = DecoderClient::new(simulator.power_on());
+    for subscription in fixture_subscriptions(&secrets, &provisioning_key, DECODER_ID, &[0], frames).unwrap() {
+        client.subscribe(&subscription).unwrap();
+    }
+    let encoded = encode_frames(&secrets, frames).unwrap();
+    replay(&mut client, expected, &encoded, batch_size).unwrap()
+}
+
+#[test]
+fn every_fixture_decodes() {
+    for name in ["x_c0.json", "x_c0123.json", "x_c1.json", "x_c1_odd.json"] {
+        let frames = fixture(name);
+        for batch_size in [1, 16] {
+            let results = replay_on_simulator(&frames, &frames, batch_size);
+            let summary = Summary::new(&results, Duration::from_secs(1)).unwrap();
+            assert_eq!(summary.frames, frames.len(), "{}", name);
+            assert!(summary.passed(), "{} with batches of {}: {:?}", name, batch_size, results);
+        }
+    }
+}
+
+#[test]
+fn reports_mismatches_and_errors() {
+    let frames = fixture("x_c1.json");
+    let mut expected = frames.clone();
+    expected[1].data[0] ^= 1;
+    let mut frames = frames;
+    // Replayed, so the decoder rejects it
+    frames[3] = frames[2].clone();
+    expected[3] = frames[2].clone();
+
+    let results = replay_on_simulator(&frames, &expected, 1);
+    assert_eq!(results[1].outcome, Outcome::Mismatch(frames[1].data.clone()));
+    match &results[3].outcome {
+        Outcome::Error(error) => assert!(error.is("FramePast"), "{}", error),
+        other => panic!("expected a decoder error, got {:?}", other)
+    }
+    let summary = Summary::new(&results, Duration::from_secs(1)).unwrap();
+    assert_eq!((summary.mismatches, summary.errors), (1, 1));
+    assert!(!summary.passed());
+}
+
+#[test]
+fn subscribes_to_each_channel_for_its_frames() {
+    let (secrets, provisioning_key) = gen_secrets(&[1, 2, 3]).unwrap();
+    let frames = fixture("x_c0123.json");
+    let simulator = Simulator::new(&secrets, DECODER_ID).unwrap();
+    let mut client = DecoderClient::new(simulator.power_on());
+    for subscription in fixture_subscriptions(&secrets, &provisioning_key, DECODER_ID, &[0], &frames).unwrap() {
+        client.subscribe(&subscription).unwrap();
+    }
+    let mut subscriptions = client.list().unwrap();
+    subscriptions.sort_by_key(|subscription| subscription.channel_id);
+    let channels: Vec<u32> = subscriptions.iter().map(|subscription| subscription.channel_id).collect();
+    assert_eq!(channels, vec![1, 2, 3]);
+    for subscription in subscriptions {
+        let timestamps: Vec<u64> = frames.iter().filter(|frame| frame.channel == subscription.channel_id).map(|frame| frame.timestamp).collect();
+        assert_eq!(subscription.start, *timestamps.iter().min().unwrap());
+        assert_eq!(subscription.end, *timestamps.iter().max().unwrap());
+    }
+}
+
+#[test]
+fn skips_every_free_channel() {
+    let (secrets, provisioning_key) = gen_secrets(&[1, 2, 3]).unwrap();
+    let frames = fixture("x_c0123.json");
+    let simulator = Simulator::new(&secrets, DECODER_ID).unwrap()
+        .with_free_channels(&[FreeChannel { channel_id: 0, priority: FramePriority::Emergency }, FreeChannel { channel_id: 2, priority: FramePriority::Normal }]);
+    let mut client = DecoderClient::new(simulator.power_on());
+    // The decoder refuses a subscription to a free channel, so none may be sent
+    for subscription in fixture_subscriptions(&secrets, &provisioning_key, DECODER_ID, &[0, 2], &frames).unwrap() {
+        client.subscribe(&subscription).unwrap();
+    }
+    let channels: Vec<u32> = client.list().unwrap().iter().map(|subscription| subscription.channel_id).collect();
+    assert_eq!(channels, vec![1, 3]);
+
+    let results = replay(&mut client, &frames, &encode_frames(&secrets, &frames).unwrap(), 1).unwrap();
+    assert!(Summary::new(&results, Duration::from_secs(1)).unwrap().passed(), "{:?}", results);
+}
+
+#[test]
+fn summarizes_latencies() {
+    let results: Vec<FrameResult> = (1..=100).map(|ms| FrameResult { latency: Duration::from_millis(ms), outcome: Outcome::Match }).collect();
+    let summary = Summary::new(&results, Duration::from_secs(4)).unwrap();
+    assert_eq!(summary.min, Duration::from_millis(1));
+    assert_eq!(summary.max, Duration::from_millis(100));
+    assert_eq!(summary.median, Duration::from_millis(50));
+    assert_eq!(summary.p95, Duration::from_millis(95));
+    assert_eq!(summary.mean, Duration::from_micros(50_500));
+    assert_eq!(summary.frames_per_second, 25.0);
+    assert!(Summary::new(&[], Duration::from_secs(1)).is_none());
+}
+
+#[test]
+fn rejects_malformed_fixtures() {
+    assert!(matches!(load_frames(b"{\"frames\": []}"), Err(ReplayError::InvalidFixture(_))));
+    assert!(matches!(load_frames(b"[[1, \"frame\"]]"), Err(ReplayError::InvalidFixture(_))));
+    assert_eq!(load_frames(b"[[2, \"ab\", 7]]").unwrap(), vec![Frame { channel: 2, data: b"ab".to_vec(), timestamp: 7 }]);
+}
@@ -0,0 +1,155 @@
+//! In-memory flash with the decoder's pages at their device addresses
+//! Behaves like the MAX78000 flash controller: erases work a page at a time,
+//! and writes can only clear bits
+
+use std::cell::Cell;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use decoder_core::platform::{Flash, FlashError, FlashRegion, FLASH_PAGE_SIZE};
+use decoder_core::sys::lockout::blank_lockout_page;
+use decoder_types::SECRETS_IMAGE_SIZE;
+
+// Same as memory.x
+pub const SUBSCRIPTIONS_ADDRESS: u32 = 0x1003_6000;
+pub const LOCKOUT_ADDRESS: u32 = 0x1003_8000;
+pub const SECRETS_ADDRESS: u32 = 0x1003_A000;
+const FLASH_END: u32 = 0x1003_E000;
+
+const ERASED_BYTE: u8 = 0xFF;
+
+pub fn region_address(region: FlashRegion) -> u32 {
+    match region {
+        FlashRegion::Subscriptions => SUBSCRIPTIONS_ADDRESS,
+        FlashRegion::Lockout => LOCKOUT_ADDRESS,
+        FlashRegion::Secrets => SECRETS_ADDRESS
+    }
+}
+
+// Contents of every simulated page, kept across power cycles
+#[derive(Debug, Clone)]
+pub struct FlashMemory {
+    bytes: Vec<u8>
+}
+
+impl FlashMemory {
+    // Flash as programmed, with `secrets_image` in the secrets pages
+    pub fn new(secrets_image: &[u8; SECRETS_IMAGE_SIZE]) -> Self {
+        let mut memory = FlashMemory { bytes: vec![ERASED_BYTE; (FLASH_END - SUBSCRIPTIONS_ADDRESS) as usize] };
+        let lockout_page: Vec<u8> = blank_lockout_page().iter().flat_map(|word| word.to_le_bytes()).collect();
+        memory.region_mut(FlashRegion::Lockout, lockout_page.len()).copy_from_slice(&lockout_page);
+        memory.region_mut(FlashRegion::Secrets, SECRETS_IMAGE_SIZE).copy_from_slice(secrets_image);
+        memory
+    }
+
+    pub fn read(&self, address: u32, length: usize) -> Result<&[u8], FlashError> {
+        let offset = offset(address, length)?;
+        Ok(&self.bytes[offset..offset + length])
+    }
+
+    pub fn region(&self, region: FlashRegion, length: usize) -> &[u8] {
+        self.read(region_address(region), length).unwrap()
+    }
+
+    fn region_mut(&mut self, region: FlashRegion, length: usize) -> &mut [u8] {
+        let offset = offset(region_address(region), length).unwrap();
+        &mut self.bytes[offset..offset + length]
+    }
+
+    pub fn erase_page(&mut self, address: u32) -> Result<(), FlashError> {
+        let page_offset = offset(address, 1)? / FLASH_PAGE_SIZE * FLASH_PAGE_SIZE;
+        self.bytes[page_offset..page_offset + FLASH_PAGE_SIZE].fill(ERASED_BYTE);
+        Ok(())
+    }
+
+    pub fn write(&mut self, address: u32, data: &[u8]) -> Result<(), FlashError> {
+        if !address.is_multiple_of(4) { return Err(FlashError::InvalidAddress); }
+        let offset = offset(address, data.len())?;
+        for (byte, data_byte) in self.bytes[offset..offset + data.len()].iter_mut().zip(data) {
+            *byte &= data_byte;
+        }
+        Ok(())
+    }
+}
+
+fn offset(address: u32, length: usize) -> Result<usize, FlashError> {
+    let end = address as u64 + length as u64;
+    if address < SUBSCRIPTIONS_ADDRESS || end > FLASH_END as u64 { return Err(FlashError::InvalidAddress); }
+    Ok((address - SUBSCRIPTIONS_ADDRESS) as usize)
+}
+
+// Every read made through one controller, in order
+// Lookups that must not leak what they found have to leave the same trace whatever the input
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReadTrace {
+    pub reads: u64,
+    pub bytes: u64,
+    // FNV-1a over the address and length of every read
+    pub digest: u64
+}
+
+impl ReadTrace {
+    const EMPTY: ReadTrace = ReadTrace { reads: 0, bytes: 0, digest: 0xcbf2_9ce4_8422_2325 };
+
+    fn record(&mut self, address: u32, length: usize) {
+        self.reads += 1;
+        self.bytes += length as u64;
+        for byte in address.to_le_bytes().into_iter().chain((length as u32).to_le_bytes()) {
+            self.digest = (self.digest ^ byte as u64).wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+}
+
+// Flash controller handed to the decoder logic
+pub struct SimFlash {
+    memory: Arc<Mutex<FlashMemory>>,
+    trace: Cell<ReadTrace>
+}
+
+impl SimFlash {
+    pub fn new(memory: Arc<Mutex<FlashMemory>>) -> Self {
+        SimFlash { memory, trace: Cell::new(ReadTrace::EMPTY) }
+    }
+
+    // Returns the reads made since the last call
+    pub fn take_trace(&self) -> ReadTrace {
+        self.trace.replace(ReadTrace::EMPTY)
+    }
+
+    fn memory(&self) -> MutexGuard<'_, FlashMemory> {
+        self.memory.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl Flash for SimFlash {
+    fn region_address(&self, region: FlashRegion) -> u32 {
+        region_address(region)
+    }
+
+    fn read_32(&self, address: u32) -> Result<u32, FlashError> {
+        let bytes = self.read_bytes::<4>(address)?;
+        Ok(u32::from_le_bytes(bytes))
+    }
+
+    fn read_bytes<const N: usize>(&self, address: u32) -> Result<[u8; N], FlashError> {
+        let mut trace = self.trace.get();
+        trace.record(address, N);
+        self.trace.set(trace);
+        let mut bytes = [0u8; N];
+        bytes.copy_from_slice(self.memory().read(address, N)?);
+        Ok(bytes)
+    }
+
+    fn erase_page(&self, address: u32) -> Result<(), FlashError> {
+        self.memory().erase_page(address)
+    }
+
+    fn write_u32_slice(&self, address: u32, data: &[u32]) -> Result<(), FlashError> {
+        // Word by word, so the simulator does not allocate while the decoder runs
+        let mut memory = self.memory();
+        offset(address, data.len() * 4)?;
+        for (word_address, word) in (address..).step_by(4).zip(data) {
+            memory.write(word_address, &word.to_le_bytes())?;
+        }
+        Ok(())
+    }
+}
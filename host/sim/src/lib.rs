@@ -0,0 +1,233 @@
+//! Host simulator for the decoder
+//! Runs the `decoder_core` logic, the same code the firmware runs, in a thread
+//! against in-memory flash, a software AES engine, and a UART pipe.
+//! Contains:
+//! - Simulated peripherals
+//! - Secrets image provisioning
+//! - Power cycling, with flash kept across cycles
+//!
+//! The decoder logic keeps its RAM state in statics, like on the device, so only
+//! one simulated decoder runs per process at a time. Powering on while another
+//! simulated decoder is running waits for it to be powered off.
+
+use std::io;
+use std::io::{Read, Write};
+use std::panic;
+use std::panic::AssertUnwindSafe;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use decoder_core::sys::rng::new_rng;
+use decoder_core::sys::free_channels::FramePriority;
+use decoder_core::{boot, serve_message};
+use decoder_types::index::index_secrets;
+use decoder_types::{AesKey, Secret, SecretType, SecretsHeader, SECRETS_CAPACITY, SECRETS_IMAGE_SIZE, SECRETS_HEADER_SIZE, SECRET_RECORD_SIZE};
+use ectf25_design::{DesignError, Secrets};
+
+// Taken by `with_delays`, `with_free_channels` and `with_replay_policy`
+pub use decoder_core::sys::rng::{DelayConfig, DelayConfigs, DelayPolicy, DelayTrigger};
+pub use decoder_core::sys::free_channels::FreeChannel;
+pub use decoder_core::sys::replay::ReplayPolicy;
+
+pub mod flash;
+pub mod peripherals;
+pub mod uart;
+
+use flash::{FlashMemory, SimFlash};
+use peripherals::{SimAes, SimDelay, SimEntropy};
+use uart::{uart_pair, Disconnected, HostPort};
+
+static POWERED: Mutex<()> = Mutex::new(());
+
+// Same as the firmware built without any DELAY_* variables
+pub const DEFAULT_DELAYS: DelayConfigs = DelayConfigs {
+    list: DelayConfig { policy: DelayPolicy::None, trigger: DelayTrigger::Always },
+    update: DelayConfig { policy: DelayPolicy::Uniform { min_us: 100, max_us: 500 }, trigger: DelayTrigger::Always },
+    decode: DelayConfig { policy: DelayPolicy::Uniform { min_us: 100, max_us: 500 }, trigger: DelayTrigger::Always },
+    receive: DelayConfig { policy: DelayPolicy::Uniform { min_us: 100, max_us: 500 }, trigger: DelayTrigger::Always }
+};
+
+// Same as the firmware built without FREE_CHANNELS
+pub const DEFAULT_FREE_CHANNELS: &[FreeChannel] = &[FreeChannel { channel_id: 0, priority: FramePriority::Emergency }];
+
+// Same as the firmware built without REPLAY_POLICY
+pub const DEFAULT_REPLAY_POLICY: ReplayPolicy = ReplayPolicy::STRICT;
+
+const DEFAULT_SEED: u64 = 0x4861_6D6D_6572_2025;
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Secrets image the build script would make from the same secrets file
+pub fn secrets_image(secrets: &[u8], decoder_id: u32) -> Result<[u8; SECRETS_IMAGE_SIZE], DesignError> {
+    let secrets = Secrets::from_json(secrets)?;
+    let master = secrets.master()?;
+    let verifying_key = secrets.verifying_key()?;
+    let mut records = vec![Secret { secret_type: SecretType::Master, valid: true, aes_key: master.key, aes_iv: master.iv }];
+    for channel in secrets.channels() {
+        let pair = secrets.channel(channel)?;
+        records.push(Secret { secret_type: SecretType::Channel(channel), valid: true, aes_key: pair.key, aes_iv: pair.iv });
+    }
+    if records.len() > SECRETS_CAPACITY { return Err(DesignError::InvalidSecrets(format!("more than {} secrets", SECRETS_CAPACITY))); }
+    let (index, records) = match index_secrets(&records) {
+        Some(indexed) => indexed,
+        None => return Err(DesignError::InvalidSecrets(format!("no index seed places all {} secrets", records.len())))
+    };
+
+    let mut image = [0u8; SECRETS_IMAGE_SIZE];
+    image[..SECRETS_HEADER_SIZE].copy_from_slice(&SecretsHeader { decoder_id, verifying_key: *verifying_key, index }.to_bytes());
+    for (record_bytes, record) in image[SECRETS_HEADER_SIZE..].chunks_exact_mut(SECRET_RECORD_SIZE).zip(records) {
+        record_bytes.copy_from_slice(&record.to_bytes());
+    }
+    Ok(image)
+}
+
+// One provisioned decoder, its flash outlives every power cycle
+pub struct Simulator {
+    flash: Arc<Mutex<FlashMemory>>,
+    aes_key_register: Arc<Mutex<AesKey>>,
+    elapsed_us: Arc<AtomicU64>,
+    boots: AtomicU64,
+    delays: DelayConfigs,
+    free_channels: &'static [FreeChannel],
+    replay_policy: ReplayPolicy,
+    seed: u64,
+    timeout: Duration
+}
+
+impl Simulator {
+    pub fn new(secrets: &[u8], decoder_id: u32) -> Result<Self, DesignError> {
+        Ok(Simulator::from_image(&secrets_image(secrets, decoder_id)?))
+    }
+
+    pub fn from_image(secrets_image: &[u8; SECRETS_IMAGE_SIZE]) -> Self {
+        Simulator {
+            flash: Arc::new(Mutex::new(FlashMemory::new(secrets_image))),
+            aes_key_register: Arc::new(Mutex::new([0; 32])),
+            elapsed_us: Arc::new(AtomicU64::new(0)),
+            boots: AtomicU64::new(0),
+            delays: DEFAULT_DELAYS,
+            free_channels: DEFAULT_FREE_CHANNELS,
+            replay_policy: DEFAULT_REPLAY_POLICY,
+            seed: DEFAULT_SEED,
+            timeout: DEFAULT_TIMEOUT
+        }
+    }
+
+    pub fn with_delays(mut self, delays: DelayConfigs) -> Self {
+        self.delays = delays;
+        self
+    }
+
+    // Like the firmware, the list must hold channel 0 and channels with secrets
+    pub fn with_free_channels(mut self, free_channels: &'static [FreeChannel]) -> Self {
+        self.free_channels = free_channels;
+        self
+    }
+
+    // Takes effect from the next power cycle, like reflashing the firmware
+    pub fn with_replay_policy(mut self, replay_policy: ReplayPolicy) -> Self {
+        self.replay_policy = replay_policy;
+        self
+    }
+
+    // Seeds the simulated trng, each power cycle draws a different stream from it
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    // How long host reads wait for the decoder
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    // Boots the decoder and returns the host end of its UART
+    pub fn power_on(&self) -> Connection {
+        let (uart, port) = uart_pair(self.timeout);
+        let flc = SimFlash::new(Arc::clone(&self.flash));
+        let mut delay = SimDelay::new(Arc::clone(&self.elapsed_us));
+        let seed = self.seed.wrapping_add(self.boots.fetch_add(1, Ordering::Relaxed));
+        let delays = self.delays;
+        let free_channels = self.free_channels;
+        let replay_policy = self.replay_policy;
+        let aes = SimAes::with_key_register(Arc::clone(&self.aes_key_register));
+        let decoder = thread::spawn(move || {
+            let _powered = POWERED.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                let mut rng = new_rng(SimEntropy::new(seed));
+                boot(&flc, &mut delay, replay_policy);
+                loop {
+                    serve_message(&flc, &aes, &uart, &mut delay, &mut rng, &delays, free_channels);
+                }
+            }));
+            if let Err(payload) = result {
+                if !payload.is::<Disconnected>() { panic::resume_unwind(payload); }
+            }
+        });
+        Connection { port, decoder: Some(decoder) }
+    }
+
+    pub fn flash(&self) -> MutexGuard<'_, FlashMemory> {
+        self.flash.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    // Key register of the AES engine, which like flash outlives every power cycle
+    pub fn aes_key_register(&self) -> AesKey {
+        *self.aes_key_register.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    // Total time the decoder has spent in delays, across power cycles
+    pub fn elapsed_us(&self) -> u64 {
+        self.elapsed_us.load(Ordering::Relaxed)
+    }
+}
+
+// Host end of a powered decoder, dropping it powers the decoder off
+pub struct Connection {
+    port: HostPort,
+    decoder: Option<JoinHandle<()>>
+}
+
+impl Connection {
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.port.set_timeout(timeout);
+    }
+
+    // Waits for the decoder to stop, Err if it panicked
+    pub fn power_off(mut self) -> thread::Result<()> {
+        self.stop()
+    }
+
+    fn stop(&mut self) -> thread::Result<()> {
+        self.port.hang_up();
+        match self.decoder.take() {
+            Some(decoder) => decoder.join(),
+            None => Ok(())
+        }
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        let _ = self.stop();
+    }
+}
+
+impl Read for Connection {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        self.port.read(buffer)
+    }
+}
+
+impl Write for Connection {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        self.port.write(bytes)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.port.flush()
+    }
+}
@@ -0,0 +1,105 @@
+//! Simulated AES engine, delay timer, and entropy source
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use aes::cipher::{BlockEncrypt, KeyInit};
+use aes::Aes256;
+
+use decoder_core::platform::{Aes, AesError, Delay};
+use decoder_core::sys::rng::EntropySource;
+use decoder_types::{AesBlock, AesKey};
+
+// Behaves the way the firmware drives the MAX78000 engine: keys and blocks
+// are byte reversed, and `decrypt_block` runs AES forwards, which is the
+// direction the anti-CBC scheme needs on the decoder
+// The key register holds the key as loaded, so a test can look for keys left in it
+pub struct SimAes {
+    key_register: Arc<Mutex<AesKey>>
+}
+
+impl SimAes {
+    pub fn new() -> Self {
+        SimAes::with_key_register(Arc::new(Mutex::new([0; 32])))
+    }
+
+    pub fn with_key_register(key_register: Arc<Mutex<AesKey>>) -> Self {
+        SimAes { key_register }
+    }
+
+    fn key_register(&self) -> MutexGuard<'_, AesKey> {
+        self.key_register.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl Default for SimAes {
+    fn default() -> Self {
+        SimAes::new()
+    }
+}
+
+impl Aes for SimAes {
+    fn set_key(&self, key: &AesKey) {
+        *self.key_register() = *key;
+    }
+
+    fn clear_key(&self) {
+        *self.key_register() = [0; 32];
+    }
+
+    fn decrypt_block(&self, block: AesBlock) -> Result<AesBlock, AesError> {
+        let mut key = *self.key_register();
+        key.reverse();
+        let cipher = Aes256::new(&key.into());
+        key.fill(0);
+        let mut block = block;
+        block.reverse();
+        let mut aes_block = block.into();
+        cipher.encrypt_block(&mut aes_block);
+        let mut block: AesBlock = aes_block.into();
+        block.reverse();
+        Ok(block)
+    }
+}
+
+// Never sleeps, only adds up the time the decoder asked to wait
+pub struct SimDelay {
+    elapsed_us: Arc<AtomicU64>
+}
+
+impl SimDelay {
+    pub fn new(elapsed_us: Arc<AtomicU64>) -> Self {
+        SimDelay { elapsed_us }
+    }
+}
+
+impl Delay for SimDelay {
+    fn delay_us(&mut self, us: u32) {
+        self.elapsed_us.fetch_add(us as u64, Ordering::Relaxed);
+    }
+
+    fn delay_ms(&mut self, ms: u32) {
+        self.elapsed_us.fetch_add(ms as u64 * 1000, Ordering::Relaxed);
+    }
+}
+
+// Deterministic xorshift64* stream standing in for the trng
+pub struct SimEntropy {
+    state: u64
+}
+
+impl SimEntropy {
+    pub fn new(seed: u64) -> Self {
+        // Zero is the one state xorshift never leaves
+        SimEntropy { state: if seed == 0 { 0x9E37_79B9_7F4A_7C15 } else { seed } }
+    }
+}
+
+impl EntropySource for SimEntropy {
+    fn gen_u32(&mut self) -> u32 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        (self.state.wrapping_mul(0x2545_F491_4F6C_DD1D) >> 32) as u32
+    }
+}
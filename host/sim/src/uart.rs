@@ -0,0 +1,100 @@
+//! Simulated UART between the host and the decoder thread
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::io;
+use std::io::{Read, Write};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
+use std::time::Duration;
+
+use decoder_core::platform::Uart;
+
+// Unwinds the decoder thread out of a blocking read once the host hangs up
+pub(crate) struct Disconnected;
+
+pub(crate) fn uart_pair(timeout: Duration) -> (SimUart, HostPort) {
+    let (host_tx, decoder_rx) = channel();
+    let (decoder_tx, host_rx) = channel();
+    let uart = SimUart { rx: decoder_rx, tx: decoder_tx, pending: RefCell::new(VecDeque::new()) };
+    let port = HostPort { rx: host_rx, tx: Some(host_tx), pending: VecDeque::new(), timeout };
+    (uart, port)
+}
+
+// Decoder end
+pub struct SimUart {
+    rx: Receiver<Vec<u8>>,
+    tx: Sender<Vec<u8>>,
+    pending: RefCell<VecDeque<u8>>
+}
+
+impl Uart for SimUart {
+    fn read_bytes(&self, buffer: &mut [u8]) {
+        let mut pending = self.pending.borrow_mut();
+        for byte in buffer.iter_mut() {
+            while pending.is_empty() {
+                match self.rx.recv() {
+                    Ok(bytes) => pending.extend(bytes),
+                    Err(_) => std::panic::resume_unwind(Box::new(Disconnected))
+                }
+            }
+            *byte = pending.pop_front().unwrap();
+        }
+    }
+
+    fn write_bytes(&self, bytes: &[u8]) {
+        // The host may already be gone, the bytes are lost like on a real wire
+        let _ = self.tx.send(bytes.to_vec());
+    }
+}
+
+// Host end, reads time out instead of blocking forever on a stuck decoder
+pub struct HostPort {
+    rx: Receiver<Vec<u8>>,
+    tx: Option<Sender<Vec<u8>>>,
+    pending: VecDeque<u8>,
+    timeout: Duration
+}
+
+impl HostPort {
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    pub(crate) fn hang_up(&mut self) {
+        self.tx = None;
+    }
+}
+
+impl Read for HostPort {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        if buffer.is_empty() { return Ok(0); }
+        if self.pending.is_empty() {
+            match self.rx.recv_timeout(self.timeout) {
+                Ok(bytes) => self.pending.extend(bytes),
+                Err(RecvTimeoutError::Timeout) => return Err(io::Error::new(io::ErrorKind::TimedOut, "decoder did not respond")),
+                // Decoder thread stopped, after a panic or glitch trap
+                Err(RecvTimeoutError::Disconnected) => return Ok(0)
+            }
+        }
+        let count = buffer.len().min(self.pending.len());
+        for (byte, pending_byte) in buffer.iter_mut().zip(self.pending.drain(..count)) {
+            *byte = pending_byte;
+        }
+        Ok(count)
+    }
+}
+
+impl Write for HostPort {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        let sent = match &self.tx {
+            Some(tx) => tx.send(bytes.to_vec()).is_ok(),
+            None => false
+        };
+        if !sent { return Err(io::Error::new(io::ErrorKind::BrokenPipe, "decoder is powered off")); }
+        Ok(bytes.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
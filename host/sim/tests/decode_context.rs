@@ -0,0 +1,62 @@
+//! Per-channel decode contexts with interleaved channel streams
+//! One test only: the contexts are statics, read back here after power off, so
+//! nothing else may boot a decoder in this process.
+
+mod support;
+
+use decoder_client::DecoderClient;
+use decoder_core::sys::context::{channel_stats, ChannelStats};
+use decoder_sim::Simulator;
+use decoder_types::packet::frame_padding;
+use ectf25_design::encoder::build_frame;
+use ectf25_design::{gen_secrets, gen_subscription, Encoder, Secrets};
+
+use support::decoder_error;
+
+const DECODER_ID: u32 = 0xdeadbeef;
+
+// A frame for `channel` with its inner layer under `key_channel`'s key
+fn encode_with_key(secrets: &[u8], channel: u32, key_channel: u32, frame: &[u8], timestamp: u64) -> Vec<u8> {
+    let secrets = Secrets::from_json(secrets).unwrap();
+    let padding = vec![0; frame_padding(frame.len())];
+    build_frame(secrets.master().unwrap(), secrets.channel(key_channel).unwrap(), channel, frame, &padding, timestamp)
+}
+
+#[test]
+fn tracks_interleaved_channels_apart() {
+    let (secrets, provisioning_key) = gen_secrets(&[1, 2, 3]).unwrap();
+    let encoder = Encoder::new(&secrets).unwrap();
+    let simulator = Simulator::new(&secrets, DECODER_ID).unwrap();
+    let mut client = DecoderClient::new(simulator.power_on());
+    for channel in 1..=3 {
+        client.subscribe(&gen_subscription(&secrets, &provisioning_key, DECODER_ID, 0, 1000, channel).unwrap()).unwrap();
+    }
+
+    // Channels take turns, each frame newer than the last on any channel
+    let mut timestamp = 0;
+    for round in 0..4u64 {
+        for channel in 0..=3 {
+            if channel == 2 && round % 2 == 1 { continue; }
+            timestamp += 10;
+            let frame = format!("round {} channel {}", round, channel).into_bytes();
+            assert_eq!(client.decode(&encoder.encode(channel, &frame, timestamp).unwrap()).unwrap(), frame);
+        }
+    }
+
+    // Replay protection is global: older than channel 3's last frame, newer than channel 1's
+    let stale = timestamp - 5;
+    assert!(decoder_error(client.decode(&encoder.encode(1, b"stale", stale).unwrap())).starts_with("FramePast"));
+
+    // A frame under the wrong channel key fails on the channel it claims
+    let forged = encode_with_key(&secrets, 2, 3, b"forged", timestamp + 10);
+    assert!(decoder_error(client.decode(&forged)).starts_with("FrameCompanyStampIncorrect"));
+    // and does not move the global timestamp
+    assert_eq!(client.decode(&encoder.encode(2, b"after", timestamp + 10).unwrap()).unwrap(), b"after");
+
+    client.into_inner().power_off().unwrap();
+    assert_eq!(channel_stats(0), Some(ChannelStats { decoded: 4, authentication_failures: 0, last_timestamp: Some(120) }));
+    assert_eq!(channel_stats(1), Some(ChannelStats { decoded: 4, authentication_failures: 0, last_timestamp: Some(130) }));
+    assert_eq!(channel_stats(2), Some(ChannelStats { decoded: 3, authentication_failures: 1, last_timestamp: Some(150) }));
+    assert_eq!(channel_stats(3), Some(ChannelStats { decoded: 4, authentication_failures: 0, last_timestamp: Some(140) }));
+    assert_eq!(channel_stats(4), None);
+}
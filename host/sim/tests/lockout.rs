@@ -0,0 +1,17 @@
+//! The lockout back-off schedule
+//! The first few failures are free, then each one doubles the delay up to its cap.
+
+use decoder_core::sys::lockout::lockout_delay_ms;
+
+#[test]
+fn free_failures_then_doubling_up_to_the_cap() {
+    let delays: Vec<u32> = (0..=13).map(lockout_delay_ms).collect();
+    assert_eq!(delays, vec![0, 0, 0, 0, 0, 250, 500, 1000, 2000, 4000, 8000, 16000, 32000, 32000]);
+}
+
+#[test]
+fn stays_capped_however_many_failures() {
+    for failures in [20, 21, 100, 0xffff, u32::MAX - 1, u32::MAX] {
+        assert_eq!(lockout_delay_ms(failures), 32_000);
+    }
+}
@@ -0,0 +1,135 @@
+//! Timing of the secret, subscription and decode context lookups
+//! Each lookup is run single stepped, with the trap flag set, and its user mode
+//! instructions counted. Lookups that find their record must take the same count
+//! wherever it is, and so must lookups that find nothing, whatever the channel.
+//! They must make as many flash reads of the same sizes. Secret lookups read the
+//! record the index picks, so only their addresses may differ. A subscription
+//! lookup reads every slot in turn, even when a record fails to parse.
+//! A decode context lookup must cost the same whichever slot holds the channel,
+//! and on a miss whichever slot the secret is cached in.
+//! One test only: the step counter and the decode contexts are statics.
+
+#![cfg(all(target_arch = "x86_64", target_os = "linux"))]
+
+use std::arch::asm;
+use std::fmt::Debug;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use decoder_core::sys::context::{channel_secret, channel_stats, reset_contexts, CONTEXT_CAPACITY};
+use decoder_core::sys::secure_memory::{merge_subscription, retrieve_channel_secret, retrieve_subscription, retrieve_subscription_sequence, SecureMemoryError, Subscription};
+use decoder_sim::flash::{FlashMemory, ReadTrace, SimFlash, SUBSCRIPTIONS_ADDRESS};
+use decoder_sim::secrets_image;
+use decoder_types::{RecordError, SUBSCRIPTION_RECORD_SIZE};
+use ectf25_design::gen_secrets;
+
+const DECODER_ID: u32 = 0x7157_0001;
+const CHANNELS: u32 = 8;
+// Windows per channel, none touching, so every one takes a slot
+const WINDOWS: u64 = 3;
+
+static STEPS: AtomicU64 = AtomicU64::new(0);
+
+extern "C" fn count_step(_signal: libc::c_int) {
+    STEPS.fetch_add(1, Ordering::Relaxed);
+}
+
+// Instructions `lookup` ran, and the flash reads it made
+#[inline(never)]
+fn measure<T>(flc: &SimFlash, lookup: impl FnOnce() -> T) -> (T, u64, ReadTrace) {
+    flc.take_trace();
+    STEPS.store(0, Ordering::Relaxed);
+    unsafe { asm!("pushfq", "or qword ptr [rsp], 0x100", "popfq"); }
+    let result = lookup();
+    unsafe { asm!("pushfq", "and qword ptr [rsp], ~0x100", "popfq"); }
+    (result, STEPS.load(Ordering::Relaxed), flc.take_trace())
+}
+
+// The lookup must cost the same for every key, returns that cost
+fn assert_same_cost<K: Debug + Copy, T: Debug>(flc: &SimFlash, what: &str, keys: &[K], lookup: impl Fn(K) -> T, expected: impl Fn(&T) -> bool) -> (u64, ReadTrace) {
+    let costs: Vec<(K, u64, ReadTrace)> = keys.iter().map(|&key| {
+        let (result, steps, trace) = measure(flc, || lookup(key));
+        assert!(expected(&result), "{} {:?} returned {:?}", what, key, result);
+        (key, steps, trace)
+    }).collect();
+    for (key, steps, trace) in &costs[1..] {
+        assert_eq!((*steps, trace.reads, trace.bytes), (costs[0].1, costs[0].2.reads, costs[0].2.bytes), "{} {:?} costs differently from {:?}", what, key, costs[0].0);
+    }
+    (costs[0].1, costs[0].2)
+}
+
+#[test]
+fn lookups_cost_the_same_wherever_they_match() {
+    unsafe {
+        let mut action: libc::sigaction = std::mem::zeroed();
+        action.sa_sigaction = count_step as *const () as usize;
+        assert_eq!(libc::sigaction(libc::SIGTRAP, &action, std::ptr::null_mut()), 0);
+    }
+    let (secrets, _) = gen_secrets(&(1..=CHANNELS).collect::<Vec<u32>>()).unwrap();
+    let memory = Arc::new(Mutex::new(FlashMemory::new(&secrets_image(&secrets, DECODER_ID).unwrap())));
+    let flc = SimFlash::new(Arc::clone(&memory));
+    // Channels take turns, so each channel's windows are spread over the page
+    for window in 0..WINDOWS {
+        for channel_id in 1..=CHANNELS {
+            let start = window * 20 + channel_id as u64;
+            merge_subscription(&flc, Subscription { channel_id, valid: true, end: start + 9, start, sequence: window }).unwrap();
+        }
+    }
+
+    // Found in the first record, in the middle, last, and the emergency channel
+    assert_same_cost(&flc, "channel secret", &[1, 4, CHANNELS, 0], |channel_id| retrieve_channel_secret(&flc, channel_id), |result| result.is_ok());
+    assert_same_cost(&flc, "channel secret", &[CHANNELS + 1, 0x1234_5678, u32::MAX], |channel_id| retrieve_channel_secret(&flc, channel_id), |result| result.is_err());
+
+    // A window holding the timestamp, in the first and last slots, then only a later or an earlier one
+    let (_, found_trace) = assert_same_cost(&flc, "subscription", &[(1, 1), (CHANNELS, 48), (4, 15), (5, 60)], |(channel_id, timestamp)| retrieve_subscription(&flc, channel_id, timestamp), |result| result.is_ok());
+    assert_same_cost(&flc, "subscription", &[(0, 5), (CHANNELS + 1, 5), (u32::MAX, 0)], |(channel_id, timestamp)| retrieve_subscription(&flc, channel_id, timestamp), |result| result == &Err(SecureMemoryError::NoSubscription));
+
+    assert_same_cost(&flc, "sequence", &[1, 4, CHANNELS], |channel_id| retrieve_subscription_sequence(&flc, channel_id), |result| result == &Ok(Some(WINDOWS - 1)));
+    assert_same_cost(&flc, "sequence", &[0, CHANNELS + 1], |channel_id| retrieve_subscription_sequence(&flc, channel_id), |result| result == &Ok(None));
+
+    // A record that fails to parse, right after the used slots, still leaves every slot read
+    let corrupt_slot = (CHANNELS as u64 * WINDOWS) as usize;
+    memory.lock().unwrap().write(SUBSCRIPTIONS_ADDRESS + (corrupt_slot * SUBSCRIPTION_RECORD_SIZE) as u32, &[0; 4]).unwrap();
+    let (result, _, trace) = measure(&flc, || retrieve_subscription(&flc, 1, 1));
+    assert_eq!(result, Err(SecureMemoryError::RecordError(RecordError::UnknownVersion(0))));
+    assert_eq!(trace, found_trace);
+
+    // One channel more than there are contexts, so a miss can find every slot taken
+    let context_channels = CONTEXT_CAPACITY as u32;
+    let (context_secrets, _) = gen_secrets(&(1..=context_channels).collect::<Vec<u32>>()).unwrap();
+    let context_flc = SimFlash::new(Arc::new(Mutex::new(FlashMemory::new(&secrets_image(&context_secrets, DECODER_ID).unwrap()))));
+    let fill = |channels: u32| {
+        reset_contexts();
+        for channel_id in 0..channels { channel_secret(&context_flc, channel_id).unwrap(); }
+    };
+
+    // Cached in the first, a middle and the last slot
+    fill(context_channels);
+    let (_, hit_trace) = assert_same_cost(&context_flc, "context secret", &[0, 4, context_channels - 1], |channel_id| channel_secret(&context_flc, channel_id), |result| result.is_ok());
+    assert_eq!(hit_trace.reads, 0);
+    for channel_id in 0..context_channels {
+        let cached = channel_secret(&context_flc, channel_id).unwrap();
+        let stored = retrieve_channel_secret(&context_flc, channel_id).unwrap();
+        assert_eq!((cached.valid, cached.aes_key, cached.aes_iv), (stored.valid, stored.aes_key, stored.aes_iv));
+    }
+
+    // A miss taking the first free slot, wherever it is
+    let free_costs: Vec<(u64, ReadTrace)> = (0..CONTEXT_CAPACITY as u32).map(|taken| {
+        fill(taken);
+        let (result, steps, trace) = measure(&context_flc, || channel_secret(&context_flc, context_channels));
+        assert!(result.is_ok());
+        (steps, trace)
+    }).collect();
+    // A miss dropping the least recently used context, wherever it is
+    let evict_costs: Vec<(u64, ReadTrace)> = (0..context_channels).map(|oldest| {
+        fill(context_channels);
+        for channel_id in (0..context_channels).filter(|channel_id| *channel_id != oldest) { channel_secret(&context_flc, channel_id).unwrap(); }
+        let (result, steps, trace) = measure(&context_flc, || channel_secret(&context_flc, context_channels));
+        assert!(result.is_ok());
+        assert_eq!(channel_stats(oldest), None, "context {} was not the one dropped", oldest);
+        (steps, trace)
+    }).collect();
+    for (steps, trace) in free_costs.iter().chain(&evict_costs) {
+        assert_eq!((*steps, trace.reads, trace.bytes), (free_costs[0].0, free_costs[0].1.reads, free_costs[0].1.bytes), "context misses cost differently");
+    }
+}
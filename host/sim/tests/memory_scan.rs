@@ -0,0 +1,137 @@
+//! Looks for key material the decoder leaves behind in simulated memory
+//! Every heap block the decoder thread frees is scanned for the provisioned keys
+//! and IVs, in either byte order, for the company stamp, which only shows up
+//! in plaintext once the channel layer is decrypted, and for the decoded frames.
+//! The AES key register must be cleared once each command is done.
+//! The channel keys cached in the decode contexts are checked after power off:
+//! an ended or replaced subscription must have had its key dropped.
+//! One test only: the allocator and the contexts are process wide.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::OnceLock;
+
+use decoder_client::DecoderClient;
+use decoder_core::sys::context::channel_stats;
+use decoder_sim::Simulator;
+use decoder_types::packet::COMPANY_STAMP;
+use ectf25_design::{gen_secrets, gen_subscription, gen_unlock, Encoder, SecretPair, Secrets};
+
+const DECODER_ID: u32 = 0x0bad_cafe;
+const NO_LEAK: usize = usize::MAX;
+
+static NEEDLES: OnceLock<Vec<[u8; 16]>> = OnceLock::new();
+static LEAKS: AtomicUsize = AtomicUsize::new(0);
+static FIRST_LEAK: AtomicUsize = AtomicUsize::new(NO_LEAK);
+
+thread_local! {
+    // The test thread handles the secrets itself, only the decoder is scanned
+    static EXEMPT: Cell<bool> = const { Cell::new(false) };
+}
+
+struct ScanningAllocator;
+
+unsafe impl GlobalAlloc for ScanningAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        if !EXEMPT.try_with(|exempt| exempt.get()).unwrap_or(true) {
+            if let Some(needles) = NEEDLES.get() {
+                scan(std::slice::from_raw_parts(ptr, layout.size()), needles);
+            }
+        }
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: ScanningAllocator = ScanningAllocator;
+
+// Must not allocate, it runs inside the allocator
+fn scan(block: &[u8], needles: &[[u8; 16]]) {
+    for window in block.windows(16) {
+        if let Some(index) = needles.iter().position(|needle| needle == window) {
+            LEAKS.fetch_add(1, Ordering::Relaxed);
+            let _ = FIRST_LEAK.compare_exchange(NO_LEAK, index, Ordering::Relaxed, Ordering::Relaxed);
+        }
+    }
+}
+
+fn reversed<const N: usize>(bytes: &[u8; N]) -> [u8; N] {
+    let mut bytes = *bytes;
+    bytes.reverse();
+    bytes
+}
+
+// Both halves of the key and the IV, as provisioned and as loaded into the engine
+fn pair_needles(pair: &SecretPair) -> Vec<[u8; 16]> {
+    let mut needles = Vec::new();
+    for key in [pair.key, reversed(&pair.key)] {
+        needles.push(*key.first_chunk::<16>().unwrap());
+        needles.push(*key.last_chunk::<16>().unwrap());
+    }
+    needles.push(pair.iv);
+    needles.push(reversed(&pair.iv));
+    needles
+}
+
+#[test]
+fn leaves_no_key_material_behind() {
+    EXEMPT.with(|exempt| exempt.set(true));
+    let (secrets, provisioning_key) = gen_secrets(&[1, 2]).unwrap();
+    let parsed = Secrets::from_json(&secrets).unwrap();
+    let mut needles = pair_needles(parsed.master().unwrap());
+    for channel in [0, 1, 2] {
+        needles.extend(pair_needles(parsed.channel(channel).unwrap()));
+    }
+    needles.push(COMPANY_STAMP);
+
+    // Long enough for a needle each, decoded alone and in a batch
+    let plaintexts: Vec<Vec<u8>> = (0..6u64).map(|i| format!("frame {} plaintext body", i).into_bytes()).collect();
+    let batch_plaintexts: [&[u8]; 2] = [b"batched plaintext frame one", b"batched plaintext frame two, longer"];
+    for plaintext in plaintexts.iter().map(|plaintext| plaintext.as_slice()).chain(batch_plaintexts) {
+        needles.push(*plaintext.first_chunk::<16>().unwrap());
+    }
+    let needle_count = needles.len();
+
+    let encoder = Encoder::new(&secrets).unwrap();
+    let simulator = Simulator::new(&secrets, DECODER_ID).unwrap();
+    let subscriptions: Vec<Vec<u8>> = [1, 2].iter().map(|&channel| gen_subscription(&secrets, &provisioning_key, DECODER_ID, 0, 1000, channel).unwrap()).collect();
+    let renewal = gen_subscription(&secrets, &provisioning_key, DECODER_ID, 0, 2000, 2).unwrap();
+    let unlock = gen_unlock(&secrets, DECODER_ID, 0).unwrap();
+    let frames: Vec<Vec<u8>> = (0..6u64).map(|i| encoder.encode((i % 3) as u32, &plaintexts[i as usize], 10 + i).unwrap()).collect();
+    let expired = encoder.encode(1, b"expired", 5000).unwrap();
+    let batch = [encoder.encode(1, batch_plaintexts[0], 200).unwrap(), encoder.encode(2, batch_plaintexts[1], 201).unwrap()];
+    NEEDLES.set(needles).unwrap();
+
+    let mut client = DecoderClient::new(simulator.power_on());
+    for subscription in &subscriptions {
+        client.subscribe(subscription).unwrap();
+        assert_eq!(simulator.aes_key_register(), [0; 32]);
+    }
+    client.list().unwrap();
+    for frame in &frames {
+        client.decode(frame).unwrap();
+        assert_eq!(simulator.aes_key_register(), [0; 32]);
+    }
+    assert!(client.decode(&expired).is_err());
+    assert_eq!(simulator.aes_key_register(), [0; 32]);
+    let results = client.decode_batch(&[&batch[0], &batch[1]]).unwrap();
+    assert!(results.iter().all(|result| result.is_ok()));
+    assert_eq!(simulator.aes_key_register(), [0; 32]);
+    client.unlock(&unlock).unwrap();
+    assert_eq!(simulator.aes_key_register(), [0; 32]);
+    // Channel 1's subscription has ended and channel 2's is replaced, both after decoding on them
+    assert!(client.decode(&expired).is_err());
+    client.subscribe(&renewal).unwrap();
+    client.into_inner().power_off().unwrap();
+    assert!(channel_stats(0).is_some(), "the free channel's key is kept");
+    assert_eq!(channel_stats(1), None, "an ended subscription's key is still cached");
+    assert_eq!(channel_stats(2), None, "a replaced subscription's key is still cached");
+
+    let first_leak = FIRST_LEAK.load(Ordering::Relaxed);
+    assert_eq!(LEAKS.load(Ordering::Relaxed), 0, "needle {} of {} found in freed memory", first_leak, needle_count);
+}
@@ -0,0 +1,139 @@
+//! Replay protection with an acceptance window, and recovery with a signed resync
+//! Frames arrive out of order within the window, duplicates are still refused,
+//! and a far future frame that locked every later frame out is undone by a resync.
+
+mod support;
+
+use decoder_client::DecoderClient;
+use decoder_sim::{Connection, ReplayPolicy, Simulator};
+use ectf25_design::{gen_resync, gen_resync_with_sequence, gen_secrets, Encoder};
+
+use support::decoder_error;
+
+const DECODER_ID: u32 = 0xdeadbeef;
+
+fn power_on(secrets: &[u8], replay_policy: ReplayPolicy) -> DecoderClient<Connection> {
+    let simulator = Simulator::new(secrets, DECODER_ID).unwrap().with_replay_policy(replay_policy);
+    DecoderClient::new(simulator.power_on())
+}
+
+#[test]
+fn accepts_reordered_frames_once_within_the_window() {
+    let (secrets, _) = gen_secrets(&[]).unwrap();
+    let encoder = Encoder::new(&secrets).unwrap();
+    let mut client = power_on(&secrets, ReplayPolicy { window: 8, max_ahead: None });
+    let mut decode = |timestamp: u64| client.decode(&encoder.encode(0, b"frame", timestamp).unwrap());
+
+    assert!(decode(100).is_ok());
+    // Late but within the window, each once
+    assert!(decode(95).is_ok());
+    assert!(decoder_error(decode(95)).starts_with("FramePast"));
+    assert!(decode(93).is_ok());
+    assert!(decoder_error(decode(100)).starts_with("FramePast"));
+    // Just past the window
+    assert!(decoder_error(decode(92)).starts_with("FramePast"));
+
+    // The window slides up with the newest frame, keeping what was seen
+    assert!(decode(103).is_ok());
+    assert!(decode(96).is_ok());
+    assert!(decoder_error(decode(95)).starts_with("FramePast"));
+    assert!(decoder_error(decode(100)).starts_with("FramePast"));
+    assert!(decoder_error(decode(94)).starts_with("FramePast"));
+}
+
+#[test]
+fn strict_policy_refuses_any_late_frame() {
+    let (secrets, _) = gen_secrets(&[]).unwrap();
+    let encoder = Encoder::new(&secrets).unwrap();
+    let mut client = power_on(&secrets, ReplayPolicy::STRICT);
+
+    assert!(client.decode(&encoder.encode(0, b"frame", 100).unwrap()).is_ok());
+    assert!(decoder_error(client.decode(&encoder.encode(0, b"frame", 99).unwrap())).starts_with("FramePast"));
+    assert!(client.decode(&encoder.encode(0, b"frame", u64::MAX).unwrap()).is_ok());
+}
+
+#[test]
+fn bounds_how_far_ahead_a_frame_may_be() {
+    let (secrets, _) = gen_secrets(&[]).unwrap();
+    let encoder = Encoder::new(&secrets).unwrap();
+    let mut client = power_on(&secrets, ReplayPolicy { window: 4, max_ahead: Some(1000) });
+
+    // The first frame after boot sets the clock, however far it is
+    assert!(client.decode(&encoder.encode(0, b"frame", 1 << 40).unwrap()).is_ok());
+    let newest = 1 << 40;
+    assert!(decoder_error(client.decode(&encoder.encode(0, b"frame", newest + 1001).unwrap())).starts_with("FrameTooFarAhead"));
+    assert!(decoder_error(client.decode(&encoder.encode(0, b"frame", u64::MAX).unwrap())).starts_with("FrameTooFarAhead"));
+    // A refused frame does not move the clock
+    assert!(client.decode(&encoder.encode(0, b"frame", newest + 1000).unwrap()).is_ok());
+}
+
+#[test]
+fn resync_recovers_from_a_far_future_frame() {
+    let (secrets, provisioning_key) = gen_secrets(&[]).unwrap();
+    let encoder = Encoder::new(&secrets).unwrap();
+    let mut client = power_on(&secrets, ReplayPolicy { window: 8, max_ahead: None });
+
+    assert!(client.decode(&encoder.encode(0, b"frame", 100).unwrap()).is_ok());
+    assert!(client.decode(&encoder.encode(0, b"skewed", 1 << 60).unwrap()).is_ok());
+    assert!(decoder_error(client.decode(&encoder.encode(0, b"frame", 110).unwrap())).starts_with("FramePast"));
+
+    let resync = gen_resync_with_sequence(&secrets, &provisioning_key, DECODER_ID, 105, 10).unwrap();
+    client.resync(&resync).unwrap();
+    assert!(client.decode(&encoder.encode(0, b"frame", 110).unwrap()).is_ok());
+    // Nothing at or below the resync timestamp is accepted, even within the window
+    assert!(decoder_error(client.decode(&encoder.encode(0, b"frame", 104).unwrap())).starts_with("FramePast"));
+    assert!(decoder_error(client.decode(&encoder.encode(0, b"frame", 105).unwrap())).starts_with("FramePast"));
+
+    // A captured resync cannot rewind the clock again, nor can an older one
+    assert!(decoder_error(client.resync(&resync)).starts_with("ResyncSequenceStale"));
+    let older = gen_resync_with_sequence(&secrets, &provisioning_key, DECODER_ID, 0, 9).unwrap();
+    assert!(decoder_error(client.resync(&older)).starts_with("ResyncSequenceStale"));
+    assert!(decoder_error(client.decode(&encoder.encode(0, b"frame", 103).unwrap())).starts_with("FramePast"));
+
+    // A newer resync may move the clock forwards too
+    client.resync(&gen_resync(&secrets, &provisioning_key, DECODER_ID, 1000).unwrap()).unwrap();
+    assert!(decoder_error(client.decode(&encoder.encode(0, b"frame", 500).unwrap())).starts_with("FramePast"));
+    assert!(client.decode(&encoder.encode(0, b"frame", 1001).unwrap()).is_ok());
+}
+
+#[test]
+fn stale_resyncs_earn_no_lockout() {
+    let (secrets, provisioning_key) = gen_secrets(&[]).unwrap();
+    let simulator = Simulator::new(&secrets, DECODER_ID).unwrap();
+    let mut client = DecoderClient::new(simulator.power_on());
+    let resync = gen_resync_with_sequence(&secrets, &provisioning_key, DECODER_ID, 0, 10).unwrap();
+    client.resync(&resync).unwrap();
+
+    // Genuinely signed, so a host resending it is no authentication failure
+    for _ in 0..8 {
+        assert!(decoder_error(client.resync(&resync)).starts_with("ResyncSequenceStale"));
+    }
+    assert!(simulator.elapsed_us() < 100_000, "{} us of delays", simulator.elapsed_us());
+}
+
+#[test]
+fn refuses_forged_resyncs() {
+    let (secrets, provisioning_key) = gen_secrets(&[]).unwrap();
+    let encoder = Encoder::new(&secrets).unwrap();
+    let mut client = power_on(&secrets, ReplayPolicy::STRICT);
+    assert!(client.decode(&encoder.encode(0, b"frame", 100).unwrap()).is_ok());
+
+    // Signature over a different timestamp
+    let mut forged = gen_resync_with_sequence(&secrets, &provisioning_key, DECODER_ID, 0, 1).unwrap();
+    let other = gen_resync_with_sequence(&secrets, &provisioning_key, DECODER_ID, 1, 1).unwrap();
+    forged[32..].copy_from_slice(&other[32..]);
+    assert!(decoder_error(client.resync(&forged)).starts_with("ResyncSignatureInvalid"));
+
+    // Signed by another deployment's key
+    let (other_secrets, other_provisioning_key) = gen_secrets(&[]).unwrap();
+    let mut foreign = gen_resync_with_sequence(&secrets, &provisioning_key, DECODER_ID, 0, 1).unwrap();
+    foreign[32..].copy_from_slice(&gen_resync_with_sequence(&other_secrets, &other_provisioning_key, DECODER_ID, 0, 1).unwrap()[32..]);
+    assert!(decoder_error(client.resync(&foreign)).starts_with("ResyncSignatureInvalid"));
+
+    // For another decoder
+    let misdirected = gen_resync_with_sequence(&secrets, &provisioning_key, DECODER_ID + 1, 0, 1).unwrap();
+    assert!(decoder_error(client.resync(&misdirected)).starts_with("InvalidDecoderID"));
+
+    // None of them moved the clock
+    assert!(decoder_error(client.decode(&encoder.encode(0, b"frame", 50).unwrap())).starts_with("FramePast"));
+}
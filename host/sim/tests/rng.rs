@@ -0,0 +1,128 @@
+//! Health tests on the raw entropy and reseeding of the decoder's rng
+//! A stuck or biased source must fail at exactly the SP 800-90B cutoffs, fail
+//! closed through a panic wherever the rng is used, and a healthy source must be
+//! drawn from again every `RESEED_INTERVAL` outputs.
+
+use std::cell::Cell;
+use std::rc::Rc;
+
+use decoder_core::sys::rng::{new_rng, EntropySource, ReseedingRng, RngError};
+use rand_core::RngCore;
+
+// Words drawn on startup: 256 health tested, then 8 for the seed
+const STARTUP_WORDS: usize = 264;
+const SEED_WORDS: usize = 8;
+const RESEED_INTERVAL: usize = 256;
+
+// Plays back `byte(i)` for the i-th sample, counting the words drawn
+struct ScriptedSource<F: Fn(usize) -> u8> {
+    byte: F,
+    words: Rc<Cell<usize>>
+}
+
+impl<F: Fn(usize) -> u8> ScriptedSource<F> {
+    fn new(byte: F) -> (Self, Rc<Cell<usize>>) {
+        let words = Rc::new(Cell::new(0));
+        (ScriptedSource { byte, words: words.clone() }, words)
+    }
+}
+
+impl<F: Fn(usize) -> u8> EntropySource for ScriptedSource<F> {
+    fn gen_u32(&mut self) -> u32 {
+        let first = self.words.get() * 4;
+        self.words.set(self.words.get() + 1);
+        u32::from_le_bytes([0, 1, 2, 3].map(|offset| (self.byte)(first + offset)))
+    }
+}
+
+// Counts up through every byte value, so no sample repeats in a row and each
+// appears twice in a 512 sample window
+fn healthy(i: usize) -> u8 {
+    i as u8
+}
+
+// Starts with `length` copies of 0x42
+fn with_run(length: usize) -> impl Fn(usize) -> u8 {
+    move |i| if i < length { 0x42 } else { healthy(i) }
+}
+
+// 0xAA opens the first window and every 8th sample after it, `count` times in all
+fn with_bias(count: usize) -> impl Fn(usize) -> u8 {
+    move |i| {
+        if i % 8 == 0 && i / 8 < count { return 0xAA; }
+        if healthy(i) == 0xAA { 0 } else { healthy(i) }
+    }
+}
+
+fn rng<F: Fn(usize) -> u8>(byte: F) -> Result<ReseedingRng<ScriptedSource<F>>, RngError> {
+    ReseedingRng::new(ScriptedSource::new(byte).0)
+}
+
+#[test]
+fn passes_a_healthy_source() {
+    assert!(rng(healthy).is_ok());
+}
+
+#[test]
+fn repetition_count_fails_at_six_identical_samples() {
+    assert!(rng(with_run(5)).is_ok());
+    assert!(matches!(rng(with_run(6)), Err(RngError::RepetitionCountFailure(0x42))));
+}
+
+#[test]
+fn adaptive_proportion_fails_at_62_in_a_window() {
+    assert!(rng(with_bias(61)).is_ok());
+    assert!(matches!(rng(with_bias(62)), Err(RngError::AdaptiveProportionFailure(0xAA))));
+}
+
+#[test]
+fn startup_test_rejects_a_stuck_source() {
+    // Caught at the sixth sample, long before a seed is drawn
+    let (source, words) = ScriptedSource::new(|_| 0);
+    assert!(matches!(ReseedingRng::new(source), Err(RngError::RepetitionCountFailure(0))));
+    assert_eq!(words.get(), 2);
+    // The seed words are tested too
+    let (source, words) = ScriptedSource::new(|i| if i < 4 * (STARTUP_WORDS - SEED_WORDS) { healthy(i) } else { 0x42 });
+    assert!(matches!(ReseedingRng::new(source), Err(RngError::RepetitionCountFailure(0x42))));
+    assert!(words.get() > STARTUP_WORDS - SEED_WORDS);
+}
+
+#[test]
+#[should_panic(expected = "Entropy source failed startup health test")]
+fn new_rng_fails_closed_on_a_stuck_source() {
+    new_rng(ScriptedSource::new(|_| 0).0);
+}
+
+#[test]
+#[should_panic(expected = "Entropy source failed health test")]
+fn reseeding_fails_closed_once_the_source_sticks() {
+    let mut rng = new_rng(ScriptedSource::new(|i| if i < 4 * STARTUP_WORDS { healthy(i) } else { 0 }).0);
+    for _ in 0..RESEED_INTERVAL {
+        rng.next_u32();
+    }
+    rng.next_u32();
+}
+
+#[test]
+fn reseeds_every_interval() {
+    let (source, words) = ScriptedSource::new(healthy);
+    let mut rng = new_rng(source);
+    assert_eq!(words.get(), STARTUP_WORDS);
+
+    // The output after every `RESEED_INTERVAL` draws a fresh seed first
+    for output in 0..=4 * RESEED_INTERVAL {
+        rng.next_u32();
+        assert_eq!(words.get(), STARTUP_WORDS + output / RESEED_INTERVAL * SEED_WORDS);
+    }
+    // Every kind of output counts as one, whatever its length
+    for output in 1..RESEED_INTERVAL {
+        match output % 3 {
+            0 => { rng.next_u64(); },
+            1 => rng.fill_bytes(&mut [0; 64]),
+            _ => rng.try_fill_bytes(&mut [0; 64]).unwrap()
+        }
+    }
+    assert_eq!(words.get(), STARTUP_WORDS + 4 * SEED_WORDS);
+    rng.fill_bytes(&mut [0; 64]);
+    assert_eq!(words.get(), STARTUP_WORDS + 5 * SEED_WORDS);
+}
@@ -0,0 +1,216 @@
+//! Property tests between the Rust encoder and the simulated decoder
+//! Every frame the encoder makes must decode bit-exact when the decoder should
+//! accept it, and fail the way a model of `secure_memory` predicts otherwise.
+
+mod support;
+
+use std::collections::BTreeMap;
+use std::sync::OnceLock;
+
+use proptest::prelude::*;
+
+use decoder_client::{ClientError, DecoderClient, SubscriptionInfo};
+use decoder_sim::{Connection, Simulator};
+use decoder_types::packet::{frame_padding, MAX_FRAME_LENGTH};
+use decoder_types::{SUBSCRIPTIONS_CAPACITY, SUBSCRIPTION_WINDOWS_CAPACITY};
+use ectf25_design::encoder::build_frame;
+use ectf25_design::{gen_secrets, gen_subscription, Encoder, Secrets};
+
+use support::decoder_error;
+
+const DECODER_ID: u32 = 0x1234_5678;
+// More channels than subscription slots, so the memory can fill up
+const CHANNELS: u32 = 10;
+
+// Secrets and provisioning key
+fn deployment() -> &'static (Vec<u8>, Vec<u8>) {
+    static DEPLOYMENT: OnceLock<(Vec<u8>, Vec<u8>)> = OnceLock::new();
+    DEPLOYMENT.get_or_init(|| gen_secrets(&(1..=CHANNELS).collect::<Vec<u32>>()).unwrap())
+}
+
+fn secrets() -> &'static [u8] {
+    &deployment().0
+}
+
+fn power_on() -> (Simulator, DecoderClient<Connection>) {
+    let simulator = Simulator::new(secrets(), DECODER_ID).unwrap();
+    let client = DecoderClient::new(simulator.power_on());
+    (simulator, client)
+}
+
+fn subscribe(client: &mut DecoderClient<Connection>, channel: u32, start: u64, end: u64) -> Result<(), ClientError> {
+    client.subscribe(&gen_subscription(secrets(), &deployment().1, DECODER_ID, start, end, channel).unwrap())
+}
+
+// Inclusive start and end timestamps
+type Window = (u64, u64);
+
+// What the decoder keeps in flash and RAM, and which checks it runs in which order
+#[derive(Debug, Default)]
+struct Model {
+    // Each channel's windows, which never overlap or touch
+    subscriptions: BTreeMap<u32, Vec<Window>>,
+    last_timestamp: Option<u64>
+}
+
+impl Model {
+    // Err holds the name of the error variant the decoder should return
+    // When full, windows that ended by the last decoded frame are dropped and it is tried again
+    fn subscribe(&mut self, channel: u32, start: u64, end: u64) -> Result<(), &'static str> {
+        if Model::place(&mut self.subscriptions, channel, start, end).is_ok() { return Ok(()); }
+        let last_timestamp = match self.last_timestamp {
+            Some(last_timestamp) => last_timestamp,
+            None => return Err("SubscriptionMemoryFull")
+        };
+        let mut subscriptions = self.subscriptions.clone();
+        for windows in subscriptions.values_mut() {
+            windows.retain(|window| window.1 > last_timestamp);
+        }
+        subscriptions.retain(|_, windows| !windows.is_empty());
+        Model::place(&mut subscriptions, channel, start, end)?;
+        self.subscriptions = subscriptions;
+        Ok(())
+    }
+
+    // Leaves `subscriptions` as it was on failure
+    fn place(subscriptions: &mut BTreeMap<u32, Vec<Window>>, channel: u32, start: u64, end: u64) -> Result<(), &'static str> {
+        if !subscriptions.contains_key(&channel) && subscriptions.len() == SUBSCRIPTIONS_CAPACITY { return Err("SubscriptionMemoryFull"); }
+        let windows = subscriptions.get(&channel).cloned().unwrap_or_default();
+        let (touching, mut kept): (Vec<Window>, Vec<Window>) = windows.into_iter().partition(|window| window.0 <= end.saturating_add(1) && start <= window.1.saturating_add(1));
+        let merged = touching.iter().fold((start, end), |merged, window| (merged.0.min(window.0), merged.1.max(window.1)));
+        let stored: usize = subscriptions.values().map(Vec::len).sum();
+        if stored - touching.len() == SUBSCRIPTION_WINDOWS_CAPACITY { return Err("SubscriptionMemoryFull"); }
+        kept.push(merged);
+        kept.sort();
+        subscriptions.insert(channel, kept);
+        Ok(())
+    }
+
+    fn decode(&mut self, channel: u32, timestamp: u64) -> Result<(), &'static str> {
+        if self.last_timestamp.is_some_and(|last| timestamp <= last) { return Err("FramePast"); }
+        if channel != 0 {
+            let windows = match self.subscriptions.get(&channel) {
+                Some(windows) => windows,
+                None => return Err("NoSubscription")
+            };
+            if !windows.iter().any(|window| window.0 <= timestamp && timestamp <= window.1) {
+                // Between windows counts as before the next one
+                if windows.iter().any(|window| timestamp < window.0) { return Err("SubscriptionFuture"); }
+                return Err("SubscriptionPast");
+            }
+        }
+        self.last_timestamp = Some(timestamp);
+        Ok(())
+    }
+
+    fn list(&self) -> Vec<SubscriptionInfo> {
+        self.subscriptions.iter().flat_map(|(channel_id, windows)| windows.iter().map(|(start, end)| SubscriptionInfo { channel_id: *channel_id, start: *start, end: *end })).collect()
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Operation {
+    Subscribe { channel: u32, start: u64, end: u64 },
+    Decode { channel: u32, timestamp: u64, frame: Vec<u8> }
+}
+
+fn frame() -> impl Strategy<Value = Vec<u8>> {
+    prop::collection::vec(any::<u8>(), 1..=MAX_FRAME_LENGTH)
+}
+
+// Timestamps from a small range, so windows and replays overlap often
+fn operation() -> impl Strategy<Value = Operation> {
+    prop_oneof![
+        // Short windows, so a channel often holds several
+        (1..=CHANNELS, 0..200u64, 0..40u64).prop_map(|(channel, start, length)| Operation::Subscribe { channel, start, end: start + length }),
+        (0..=CHANNELS, 0..200u64, frame()).prop_map(|(channel, timestamp, frame)| Operation::Decode { channel, timestamp, frame })
+    ]
+}
+
+// Same as Encoder::encode, but with `padding_byte` in the last byte of zero padding
+fn encode_with_padding(channel: u32, frame: &[u8], timestamp: u64, padding_byte: u8) -> Vec<u8> {
+    let secrets = Secrets::from_json(secrets()).unwrap();
+    let mut padding = vec![0; frame_padding(frame.len())];
+    *padding.last_mut().unwrap() = padding_byte;
+    build_frame(secrets.master().unwrap(), secrets.channel(channel).unwrap(), channel, frame, &padding, timestamp)
+}
+
+proptest! {
+    #[test]
+    fn decodes_what_the_encoder_makes(channel in 0..=CHANNELS, timestamp in any::<u64>(), frame in frame()) {
+        let (_simulator, mut client) = power_on();
+        if channel != 0 { subscribe(&mut client, channel, 0, u64::MAX).unwrap(); }
+        let encoded = Encoder::new(secrets()).unwrap().encode(channel, &frame, timestamp).unwrap();
+        prop_assert_eq!(client.decode(&encoded).unwrap(), frame);
+    }
+
+    #[test]
+    fn rejects_replayed_frames(channel in 0..=CHANNELS, timestamp in 1..u64::MAX, earlier in any::<u64>(), frame in frame()) {
+        let earlier = earlier % timestamp;
+        let (_simulator, mut client) = power_on();
+        if channel != 0 { subscribe(&mut client, channel, 0, u64::MAX).unwrap(); }
+        let encoder = Encoder::new(secrets()).unwrap();
+        let encoded = encoder.encode(channel, &frame, timestamp).unwrap();
+        client.decode(&encoded).unwrap();
+        prop_assert!(decoder_error(client.decode(&encoded)).starts_with("FramePast"));
+        prop_assert!(decoder_error(client.decode(&encoder.encode(channel, &frame, earlier).unwrap())).starts_with("FramePast"));
+        if timestamp < u64::MAX {
+            prop_assert_eq!(client.decode(&encoder.encode(channel, &frame, timestamp + 1).unwrap()).unwrap(), frame);
+        }
+    }
+
+    #[test]
+    fn enforces_subscription_windows(channel in 1..=CHANNELS, a in any::<u64>(), b in any::<u64>(), timestamp in any::<u64>(), frame in frame()) {
+        let (start, end) = (a.min(b), a.max(b));
+        let (_simulator, mut client) = power_on();
+        let encoded = Encoder::new(secrets()).unwrap().encode(channel, &frame, timestamp).unwrap();
+        prop_assert!(decoder_error(client.decode(&encoded)).contains("NoSubscription"));
+        subscribe(&mut client, channel, start, end).unwrap();
+        let result = client.decode(&encoded);
+        if timestamp < start {
+            prop_assert!(decoder_error(result).starts_with("SubscriptionFuture"));
+        } else if timestamp > end {
+            prop_assert!(decoder_error(result).starts_with("SubscriptionPast"));
+        } else {
+            prop_assert_eq!(result.unwrap(), frame);
+        }
+    }
+
+    #[test]
+    fn rejects_nonzero_padding(channel in 0..=CHANNELS, timestamp in any::<u64>(), frame in frame(), padding_byte in 1..=u8::MAX) {
+        prop_assume!(frame_padding(frame.len()) != 0);
+        let (_simulator, mut client) = power_on();
+        if channel != 0 { subscribe(&mut client, channel, 0, u64::MAX).unwrap(); }
+        let encoded = encode_with_padding(channel, &frame, timestamp, padding_byte);
+        prop_assert_eq!(decoder_error(client.decode(&encoded)), format!("ZeroPaddingNotIntact({})", padding_byte));
+        // Intact padding still decodes
+        prop_assert_eq!(client.decode(&encode_with_padding(channel, &frame, timestamp, 0)).unwrap(), frame);
+    }
+
+    #[test]
+    fn follows_the_model(operations in prop::collection::vec(operation(), 1..40)) {
+        let (_simulator, mut client) = power_on();
+        let encoder = Encoder::new(secrets()).unwrap();
+        let mut model = Model::default();
+        for operation in operations {
+            match operation {
+                Operation::Subscribe { channel, start, end } => {
+                    match (model.subscribe(channel, start, end), subscribe(&mut client, channel, start, end)) {
+                        (Ok(()), Ok(())) => {},
+                        (Err(expected), Err(ClientError::Decoder(error))) => prop_assert!(error.is(expected), "expected {}, got {}", expected, error),
+                        (expected, result) => prop_assert!(false, "expected {:?}, got {:?}", expected, result)
+                    }
+                },
+                Operation::Decode { channel, timestamp, frame } => {
+                    let result = client.decode(&encoder.encode(channel, &frame, timestamp).unwrap());
+                    match (model.decode(channel, timestamp), result) {
+                        (Ok(()), Ok(decoded)) => prop_assert_eq!(decoded, frame),
+                        (Err(expected), Err(ClientError::Decoder(error))) => prop_assert!(error.is(expected), "expected {}, got {}", expected, error),
+                        (expected, result) => prop_assert!(false, "expected {:?}, got {:?}", expected, result)
+                    }
+                }
+            }
+        }
+        prop_assert_eq!(client.list().unwrap(), model.list());
+    }
+}
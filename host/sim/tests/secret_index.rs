@@ -0,0 +1,55 @@
+//! The perfect hash index over the secret records
+//! A full table must index, and every lookup must find its own record,
+//! reading only the header and that one record.
+
+use std::sync::{Arc, Mutex};
+
+use decoder_core::sys::secure_memory::{retrieve_channel_secret, retrieve_master_secret, SecureMemoryError};
+use decoder_sim::flash::{FlashMemory, SimFlash};
+use decoder_sim::secrets_image;
+use decoder_types::index::index_secrets;
+use decoder_types::{Secret, SecretType, SECRETS_CAPACITY, SECRETS_HEADER_SIZE, SECRET_RECORD_SIZE};
+use ectf25_design::{gen_secrets, Secrets};
+
+const DECODER_ID: u32 = 0x1dec_0de5;
+
+fn flash(secrets: &[u8]) -> SimFlash {
+    SimFlash::new(Arc::new(Mutex::new(FlashMemory::new(&secrets_image(secrets, DECODER_ID).unwrap()))))
+}
+
+#[test]
+fn finds_every_secret_of_a_full_table_in_one_read() {
+    // The master secret and channel 0 take two of the slots
+    let channels: Vec<u32> = (0..SECRETS_CAPACITY as u32 - 2).map(|i| (i + 1).wrapping_mul(0x9e37_79b9)).collect();
+    let (secrets, _) = gen_secrets(&channels).unwrap();
+    let parsed = Secrets::from_json(&secrets).unwrap();
+    let flc = flash(&secrets);
+
+    flc.take_trace();
+    let master = retrieve_master_secret(&flc).unwrap();
+    assert_eq!((master.aes_key, master.aes_iv), (parsed.master().unwrap().key, parsed.master().unwrap().iv));
+    for channel in parsed.channels() {
+        let secret = retrieve_channel_secret(&flc, channel).unwrap();
+        assert_eq!(secret.secret_type, SecretType::Channel(channel));
+        assert_eq!((secret.aes_key, secret.aes_iv), (parsed.channel(channel).unwrap().key, parsed.channel(channel).unwrap().iv));
+    }
+    let trace = flc.take_trace();
+    assert_eq!(trace.reads, 2 * SECRETS_CAPACITY as u64);
+    assert_eq!(trace.bytes, (SECRETS_HEADER_SIZE + SECRET_RECORD_SIZE) as u64 * SECRETS_CAPACITY as u64);
+}
+
+#[test]
+fn rejects_channels_missing_from_the_table() {
+    let (secrets, _) = gen_secrets(&[1, 2, 3]).unwrap();
+    let flc = flash(&secrets);
+    for channel in [4, 0x8000_0000, u32::MAX] {
+        assert_eq!(retrieve_channel_secret(&flc, channel).unwrap_err(), SecureMemoryError::NoChannelSecret);
+    }
+}
+
+#[test]
+fn cannot_index_a_type_twice() {
+    let secret = Secret { secret_type: SecretType::Channel(7), valid: true, aes_key: [7; 32], aes_iv: [7; 16] };
+    assert!(index_secrets(&[secret, secret]).is_none());
+    assert!(index_secrets(&[Secret::EMPTY; SECRETS_CAPACITY + 1]).is_none());
+}
@@ -0,0 +1,235 @@
+//! Model-based tests of subscription storage
+//! Random sequences of updates, lists and lookups run against the real
+//! `secure_memory` on simulated flash and against a reference model of its slots.
+//! After every step both must return the same result and hold the same slots.
+//! Each slot holds one window of a channel, and updates merge touching windows.
+//! Frames decoded in between move the newest verified timestamp, which decides
+//! which windows have expired and may be reclaimed.
+//! Every window of a channel carries the sequence number of its latest update.
+
+use std::fmt::Debug;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use proptest::prelude::*;
+
+use decoder_core::sys::replay::{reset_timestamp, set_timestamp, ReplayPolicy};
+use decoder_core::sys::secure_memory::{merge_subscription, retrieve_subscription, retrieve_subscriptions, SecureMemoryError, Subscription};
+use decoder_sim::flash::{FlashMemory, SimFlash};
+use decoder_types::{SECRETS_IMAGE_SIZE, SUBSCRIPTIONS_CAPACITY, SUBSCRIPTION_WINDOWS_CAPACITY};
+
+// More channels than slots, so the memory fills up
+const CHANNELS: u32 = 12;
+
+// The last verified timestamp is a static, so runs must not overlap
+static TIMESTAMP: Mutex<()> = Mutex::new(());
+
+#[derive(Debug, Clone)]
+enum Operation {
+    Update(Subscription),
+    List,
+    // The lookup decode runs before checking a frame's window, with the frame's timestamp
+    Lookup(u32, u64),
+    // A frame with this timestamp was verified, a late one within the replay window leaves the newest as is
+    Verify(u64)
+}
+
+type Slots = [Option<Subscription>; SUBSCRIPTION_WINDOWS_CAPACITY];
+
+// Slots as the firmware should fill them: windows of the channel touching the
+// new one are freed and merged into it, which then takes the first empty slot
+// Only when that does not fit are windows ending by the newest verified timestamp emptied
+#[derive(Debug)]
+struct Model {
+    slots: Slots,
+    last_timestamp: Option<u64>
+}
+
+impl Model {
+    fn new() -> Self {
+        Model { slots: [None; SUBSCRIPTION_WINDOWS_CAPACITY], last_timestamp: None }
+    }
+
+    fn update(&mut self, subscription: Subscription) -> Result<(), SecureMemoryError> {
+        if subscription.channel_id == 0 { return Err(SecureMemoryError::InvalidSubscriptionChannel(0)); }
+        if !subscription.valid || subscription.end < subscription.start { return Err(SecureMemoryError::SubscriptionNotValid(subscription.channel_id)); }
+        let mut slots = self.slots;
+        if let Err(error) = Model::place(&mut slots, subscription) {
+            let last_timestamp = match self.last_timestamp {
+                Some(last_timestamp) => last_timestamp,
+                None => return Err(error)
+            };
+            slots = self.slots.map(|slot| slot.filter(|window| window.end > last_timestamp));
+            Model::place(&mut slots, subscription)?;
+        }
+        self.slots = slots;
+        Ok(())
+    }
+
+    fn place(slots: &mut Slots, subscription: Subscription) -> Result<(), SecureMemoryError> {
+        let mut channels: Vec<u32> = slots.iter().flatten().map(|slot| slot.channel_id).collect();
+        channels.sort();
+        channels.dedup();
+        if !channels.contains(&subscription.channel_id) && channels.len() == SUBSCRIPTIONS_CAPACITY { return Err(SecureMemoryError::SubscriptionMemoryFull); }
+        let touches = |slot: &Subscription| slot.channel_id == subscription.channel_id && slot.start <= subscription.end.saturating_add(1) && subscription.start <= slot.end.saturating_add(1);
+        let mut merged = subscription;
+        for window in slots.iter_mut().flatten().filter(|window| window.channel_id == subscription.channel_id) {
+            window.sequence = subscription.sequence;
+        }
+        for slot in slots.iter_mut() {
+            if let Some(window) = slot.filter(touches) {
+                merged.start = merged.start.min(window.start);
+                merged.end = merged.end.max(window.end);
+                *slot = None;
+            }
+        }
+        match slots.iter().position(|slot| slot.is_none()) {
+            Some(slot) => slots[slot] = Some(merged),
+            None => return Err(SecureMemoryError::SubscriptionMemoryFull)
+        }
+        Ok(())
+    }
+
+    fn list(&self) -> Vec<Subscription> {
+        self.slots.iter().flatten().copied().collect()
+    }
+
+    // The window holding `timestamp`, else the next one after it, else the last one before it
+    fn lookup(&self, channel_id: u32, timestamp: u64) -> Result<Subscription, SecureMemoryError> {
+        let windows: Vec<Subscription> = self.slots.iter().flatten().filter(|slot| slot.channel_id == channel_id).copied().collect();
+        let covering = windows.iter().find(|window| window.start <= timestamp && timestamp <= window.end);
+        let next = windows.iter().filter(|window| timestamp < window.start).min_by_key(|window| window.start);
+        let last = windows.iter().filter(|window| window.end < timestamp).max_by_key(|window| window.end);
+        match covering.or(next).or(last) {
+            Some(subscription) => Ok(*subscription),
+            None => Err(SecureMemoryError::NoSubscription)
+        }
+    }
+
+    // What each slot should read back as from flash
+    fn slot_records(&self) -> Vec<Subscription> {
+        self.slots.iter().map(|slot| slot.unwrap_or(Subscription::EMPTY)).collect()
+    }
+}
+
+fn compare<T: Debug + PartialEq>(step: usize, operation: &Operation, expected: T, actual: T) -> Result<(), String> {
+    if expected == actual { return Ok(()); }
+    Err(format!("step {} {:?}: model gave {:?}, firmware gave {:?}", step, operation, expected, actual))
+}
+
+// Runs `operations` on both, stopping at the first divergence
+fn run(operations: &[Operation]) -> Result<(), String> {
+    let _timestamp = TIMESTAMP.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    reset_timestamp(ReplayPolicy::STRICT);
+    let memory = Arc::new(Mutex::new(FlashMemory::new(&[0xFF; SECRETS_IMAGE_SIZE])));
+    let flc = SimFlash::new(memory);
+    let mut model = Model::new();
+    for (step, operation) in operations.iter().enumerate() {
+        match operation {
+            Operation::Update(subscription) => compare(step, operation, model.update(*subscription), merge_subscription(&flc, *subscription))?,
+            Operation::List => {
+                let listed = retrieve_subscriptions(&flc).map(|subscriptions| subscriptions.into_iter().filter(|subscription| subscription.valid).collect::<Vec<_>>());
+                compare(step, operation, Ok(model.list()), listed)?
+            },
+            Operation::Lookup(channel_id, timestamp) => compare(step, operation, model.lookup(*channel_id, *timestamp), retrieve_subscription(&flc, *channel_id, *timestamp))?,
+            Operation::Verify(timestamp) => {
+                model.last_timestamp = Some(model.last_timestamp.map_or(*timestamp, |last| last.max(*timestamp)));
+                set_timestamp(*timestamp)
+            }
+        }
+        let slots = retrieve_subscriptions(&flc);
+        if slots != Ok(model.slot_records()) {
+            return Err(format!("after step {} {:?}: model slots {:?}, flash slots {:?}", step, operation, model.slot_records(), slots));
+        }
+    }
+    Ok(())
+}
+
+// Each update gets a newer sequence number, as the generator issues them
+fn update(channel_id: u32, start: u64, end: u64) -> Operation {
+    static SEQUENCE: AtomicU64 = AtomicU64::new(1);
+    Operation::Update(Subscription { channel_id, valid: true, end, start, sequence: SEQUENCE.fetch_add(1, Ordering::Relaxed) })
+}
+
+fn operation() -> impl Strategy<Value = Operation> {
+    // Storage does not order sequence numbers, update_subscription does before merging
+    let subscription = (0..=CHANNELS, prop::bool::weighted(0.9), 0..100u64, 0..100u64, any::<u64>()).prop_map(|(channel_id, valid, start, end, sequence)| Subscription { channel_id, valid, end, start, sequence });
+    prop_oneof![
+        4 => subscription.prop_map(Operation::Update),
+        1 => Just(Operation::List),
+        2 => (0..=CHANNELS, 0..110u64).prop_map(|(channel_id, timestamp)| Operation::Lookup(channel_id, timestamp)),
+        1 => (0..110u64).prop_map(Operation::Verify)
+    ]
+}
+
+proptest! {
+    #[test]
+    fn storage_follows_the_model(operations in prop::collection::vec(operation(), 1..60)) {
+        if let Err(divergence) = run(&operations) {
+            prop_assert!(false, "{}", divergence);
+        }
+    }
+}
+
+#[test]
+fn fills_slots_in_order_until_full() {
+    let mut operations: Vec<Operation> = (1..=SUBSCRIPTIONS_CAPACITY as u32).map(|channel| update(channel, 0, 10)).collect();
+    operations.push(update(SUBSCRIPTIONS_CAPACITY as u32 + 1, 0, 10));
+    operations.push(Operation::List);
+    run(&operations).unwrap();
+}
+
+#[test]
+fn keeps_disjoint_windows() {
+    run(&[update(5, 0, 10), update(6, 0, 10), update(5, 20, 30), Operation::Lookup(5, 5), Operation::Lookup(5, 15), Operation::Lookup(5, 25), Operation::Lookup(5, 35), Operation::List]).unwrap();
+}
+
+#[test]
+fn merges_overlapping_and_adjacent_windows() {
+    run(&[update(5, 0, 10), update(5, 30, 40), update(5, 11, 15), update(5, 14, 29), Operation::Lookup(5, 20), Operation::List]).unwrap();
+}
+
+#[test]
+fn adds_windows_to_a_subscribed_channel_when_channels_are_full() {
+    let mut operations: Vec<Operation> = (1..=SUBSCRIPTIONS_CAPACITY as u32).map(|channel| update(channel, 0, 10)).collect();
+    operations.push(update(SUBSCRIPTIONS_CAPACITY as u32, 50, 60));
+    operations.push(Operation::Lookup(SUBSCRIPTIONS_CAPACITY as u32, 55));
+    run(&operations).unwrap();
+}
+
+#[test]
+fn fills_window_slots_until_full() {
+    let mut operations: Vec<Operation> = (0..SUBSCRIPTION_WINDOWS_CAPACITY as u64).map(|window| update(1, window * 3, window * 3 + 1)).collect();
+    operations.push(update(1, 200, 210));
+    // Bridging two windows frees a slot for the merged one
+    operations.push(update(1, 2, 2));
+    operations.push(update(1, 200, 210));
+    operations.push(Operation::List);
+    run(&operations).unwrap();
+}
+
+#[test]
+fn reclaims_expired_windows_only_when_full() {
+    let mut operations: Vec<Operation> = (1..=SUBSCRIPTIONS_CAPACITY as u32).map(|channel| update(channel, channel as u64 * 10, channel as u64 * 10 + 5)).collect();
+    // Nothing has expired before a frame is verified
+    operations.push(update(SUBSCRIPTIONS_CAPACITY as u32 + 1, 0, 100));
+    // Channels 1 and 2 end by 25, channel 3 ends after it
+    operations.push(Operation::Verify(25));
+    operations.push(Operation::List);
+    operations.push(update(SUBSCRIPTIONS_CAPACITY as u32 + 1, 0, 100));
+    operations.push(Operation::List);
+    operations.push(update(SUBSCRIPTIONS_CAPACITY as u32 + 2, 0, 100));
+    operations.push(update(SUBSCRIPTIONS_CAPACITY as u32 + 3, 0, 100));
+    run(&operations).unwrap();
+}
+
+#[test]
+fn rejects_invalid_updates_without_writing() {
+    run(&[
+        update(0, 0, 10),
+        update(3, 10, 0),
+        Operation::Update(Subscription { channel_id: 3, valid: false, end: 10, start: 0, sequence: 1 }),
+        Operation::Lookup(3, 5),
+        Operation::List
+    ]).unwrap();
+}
@@ -0,0 +1,11 @@
+//! Helpers shared by the simulator's test files
+
+use decoder_client::ClientError;
+
+// The decoder's error message, for results that must have failed on the decoder
+pub fn decoder_error<T: std::fmt::Debug>(result: Result<T, ClientError>) -> String {
+    match result {
+        Err(ClientError::Decoder(error)) => error.message,
+        other => panic!("expected a decoder error, got {:?}", other)
+    }
+}
@@ -16,11 +16,13 @@ import string
 import argparse
 import psutil
 from psutil import Process
+from pathlib import Path
 
 from loguru import logger
 
 from ectf25.utils.decoder import DecoderIntf, DecoderError
 from ectf25_design.encoder import Encoder
+from ectf25_design.gen_secrets import provisioning_key_path
 from ectf25_design.gen_subscription import gen_subscription
 
 
@@ -125,6 +127,7 @@ class StabilityTester:
         duration_minutes: int = 480,
         channel: int = 1,
         should_subscribe: bool = False,
+        provisioning_key: bytes | None = None,
     ):
         self.decoder_port = decoder_port
         self.global_secrets = global_secrets
@@ -132,6 +135,7 @@ class StabilityTester:
         self.results_file = results_file
         self.duration_minutes = duration_minutes
         self.channel = channel
+        self.provisioning_key = provisioning_key
 
         self.decoder_intf = DecoderIntf(decoder_port)
         self.encoder = Encoder(global_secrets)
@@ -149,7 +153,12 @@ class StabilityTester:
         ts_max = 0xFFFF_FFFF_FFFF_FFFF
         # these will throw their own errors, no need to check return values
         sub = gen_subscription(
-            self.global_secrets, self.device_id, ts_min, ts_max, self.channel
+            self.global_secrets,
+            self.device_id,
+            ts_min,
+            ts_max,
+            self.channel,
+            provisioning_key=self.provisioning_key,
         )
         self.decoder_intf.subscribe(sub)
 
@@ -302,7 +311,14 @@ def parse_args():
         default=False,
         help="Provide a subscription",
     )
-
+    parser.add_argument(
+        "-k",
+        "--provisioning-key",
+        type=str,
+        default=None,
+        help="Path to the provisioning key that signs the subscription "
+        "(default: the secrets file with a .provisioning.key suffix)",
+    )
     return parser.parse_args()
 
 
@@ -311,6 +327,12 @@ def main():
 
     with open(args.global_secrets, "rb") as f:
         global_secrets = f.read()
+    provisioning_key = None
+    if args.subscribe:
+        if args.provisioning_key is None:
+            args.provisioning_key = provisioning_key_path(Path(args.global_secrets))
+        with open(args.provisioning_key, "rb") as f:
+            provisioning_key = f.read()
 
     st = StabilityTester(
         args.port,
@@ -320,6 +342,7 @@ def main():
         args.duration,
         args.channel,
         args.subscribe,
+        provisioning_key,
     )
     st.run()
 